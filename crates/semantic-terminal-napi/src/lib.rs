@@ -39,6 +39,43 @@ impl From<semantic::State> for State {
     }
 }
 
+impl From<State> for semantic::State {
+    fn from(s: State) -> Self {
+        match s {
+            State::Starting => semantic::State::Starting,
+            State::Idle => semantic::State::Idle,
+            State::Thinking => semantic::State::Thinking,
+            State::ToolRunning => semantic::State::ToolRunning,
+            State::Confirming => semantic::State::Confirming,
+            State::Error => semantic::State::Error,
+        }
+    }
+}
+
+/// Input action for Claude Code
+#[napi(string_enum)]
+pub enum InputAction {
+    Submit,
+    Interrupt,
+    Exit,
+    CycleMode,
+    SlashCommand,
+    Rewind,
+}
+
+impl InputAction {
+    fn with_text(self, text: String) -> semantic::InputAction {
+        match self {
+            InputAction::Submit => semantic::InputAction::Submit(text),
+            InputAction::Interrupt => semantic::InputAction::Interrupt,
+            InputAction::Exit => semantic::InputAction::Exit,
+            InputAction::CycleMode => semantic::InputAction::CycleMode,
+            InputAction::SlashCommand => semantic::InputAction::SlashCommand(text),
+            InputAction::Rewind => semantic::InputAction::Rewind,
+        }
+    }
+}
+
 /// Type of confirmation dialog
 #[napi(string_enum)]
 pub enum ConfirmType {
@@ -55,6 +92,143 @@ impl From<semantic::ConfirmType> for ConfirmType {
     }
 }
 
+/// Action to take in response to confirmation
+#[napi(string_enum)]
+pub enum ConfirmAction {
+    Confirm,
+    Deny,
+    Select,
+    Input,
+    Choose,
+    DenyWithFeedback,
+}
+
+impl From<ConfirmAction> for semantic::ConfirmAction {
+    fn from(a: ConfirmAction) -> Self {
+        match a {
+            ConfirmAction::Confirm => semantic::ConfirmAction::Confirm,
+            ConfirmAction::Deny => semantic::ConfirmAction::Deny,
+            ConfirmAction::Select => semantic::ConfirmAction::Select,
+            ConfirmAction::Input => semantic::ConfirmAction::Input,
+            ConfirmAction::Choose => semantic::ConfirmAction::Choose,
+            ConfirmAction::DenyWithFeedback => semantic::ConfirmAction::DenyWithFeedback,
+        }
+    }
+}
+
+/// What choosing a confirm option does
+#[napi(string_enum)]
+pub enum ConfirmOptionKind {
+    AllowOnce,
+    AllowForSession,
+    AllowAlways,
+    Deny,
+    DenyWithFeedback,
+    Other,
+}
+
+impl From<semantic::ConfirmOptionKind> for ConfirmOptionKind {
+    fn from(k: semantic::ConfirmOptionKind) -> Self {
+        match k {
+            semantic::ConfirmOptionKind::AllowOnce => ConfirmOptionKind::AllowOnce,
+            semantic::ConfirmOptionKind::AllowForSession => ConfirmOptionKind::AllowForSession,
+            semantic::ConfirmOptionKind::AllowAlways => ConfirmOptionKind::AllowAlways,
+            semantic::ConfirmOptionKind::Deny => ConfirmOptionKind::Deny,
+            semantic::ConfirmOptionKind::DenyWithFeedback => ConfirmOptionKind::DenyWithFeedback,
+            semantic::ConfirmOptionKind::Other => ConfirmOptionKind::Other,
+        }
+    }
+}
+
+impl From<ConfirmOptionKind> for semantic::ConfirmOptionKind {
+    fn from(k: ConfirmOptionKind) -> Self {
+        match k {
+            ConfirmOptionKind::AllowOnce => semantic::ConfirmOptionKind::AllowOnce,
+            ConfirmOptionKind::AllowForSession => semantic::ConfirmOptionKind::AllowForSession,
+            ConfirmOptionKind::AllowAlways => semantic::ConfirmOptionKind::AllowAlways,
+            ConfirmOptionKind::Deny => semantic::ConfirmOptionKind::Deny,
+            ConfirmOptionKind::DenyWithFeedback => semantic::ConfirmOptionKind::DenyWithFeedback,
+            ConfirmOptionKind::Other => semantic::ConfirmOptionKind::Other,
+        }
+    }
+}
+
+/// File operation of an edit/write dialog
+#[napi(string_enum)]
+pub enum FileOperation {
+    Edit,
+    Create,
+    Overwrite,
+}
+
+impl From<semantic::FileOperation> for FileOperation {
+    fn from(op: semantic::FileOperation) -> Self {
+        match op {
+            semantic::FileOperation::Edit => FileOperation::Edit,
+            semantic::FileOperation::Create => FileOperation::Create,
+            semantic::FileOperation::Overwrite => FileOperation::Overwrite,
+        }
+    }
+}
+
+/// Kind of a diff line
+#[napi(string_enum)]
+pub enum DiffLineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+impl From<semantic::DiffLineKind> for DiffLineKind {
+    fn from(k: semantic::DiffLineKind) -> Self {
+        match k {
+            semantic::DiffLineKind::Context => DiffLineKind::Context,
+            semantic::DiffLineKind::Added => DiffLineKind::Added,
+            semantic::DiffLineKind::Removed => DiffLineKind::Removed,
+        }
+    }
+}
+
+/// How risky a flagged action is
+#[napi(string_enum)]
+pub enum RiskLevel {
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+impl From<semantic::RiskLevel> for RiskLevel {
+    fn from(l: semantic::RiskLevel) -> Self {
+        match l {
+            semantic::RiskLevel::None => RiskLevel::None,
+            semantic::RiskLevel::Low => RiskLevel::Low,
+            semantic::RiskLevel::Medium => RiskLevel::Medium,
+            semantic::RiskLevel::High => RiskLevel::High,
+        }
+    }
+}
+
+/// What kind of risk a flag describes
+#[napi(string_enum)]
+pub enum RiskCategory {
+    DestructiveCommand,
+    OutsideWorkspace,
+    Secret,
+    Network,
+}
+
+impl From<semantic::RiskCategory> for RiskCategory {
+    fn from(c: semantic::RiskCategory) -> Self {
+        match c {
+            semantic::RiskCategory::DestructiveCommand => RiskCategory::DestructiveCommand,
+            semantic::RiskCategory::OutsideWorkspace => RiskCategory::OutsideWorkspace,
+            semantic::RiskCategory::Secret => RiskCategory::Secret,
+            semantic::RiskCategory::Network => RiskCategory::Network,
+        }
+    }
+}
+
 /// Status phase
 #[napi(string_enum)]
 pub enum StatusPhase {
@@ -119,6 +293,16 @@ impl From<RustFingerprintCategory> for FingerprintCategory {
 
 // ============ Result Types ============
 
+/// Rule that fired during state detection
+#[napi(object)]
+pub struct StateEvidence {
+    pub rule: String,
+    pub state: State,
+    pub lines: Vec<u32>,
+    pub weight: f64,
+    pub contribution: f64,
+}
+
 /// State detection result
 #[napi(object)]
 pub struct StateResult {
@@ -126,6 +310,9 @@ pub struct StateResult {
     pub confidence: f64,
     pub needs_trust_confirm: Option<bool>,
     pub confirm_type: Option<ConfirmType>,
+    pub evidence: Vec<StateEvidence>,
+    /// Claude Code version profile used for detection
+    pub version: Option<String>,
 }
 
 /// Confirm option
@@ -134,6 +321,11 @@ pub struct ConfirmOption {
     pub key: String,
     pub label: String,
     pub is_default: bool,
+    /// Whether the ❯ selection marker is on this option
+    pub selected: bool,
+    pub kind: ConfirmOptionKind,
+    /// What an "always" option applies to
+    pub scope: Option<String>,
 }
 
 /// Tool info in confirmation
@@ -152,6 +344,109 @@ pub struct ConfirmInfo {
     pub options: Option<Vec<ConfirmOption>>,
     pub tool: Option<ToolInfo>,
     pub raw_prompt: String,
+    pub risk: Option<RiskReport>,
+    /// Details of a Bash command dialog
+    pub bash: Option<BashConfirm>,
+    /// Details of a file edit/create/overwrite dialog
+    pub file: Option<FileConfirm>,
+    /// Whether the "tell Claude what to do differently" field is open
+    pub feedback_entry: bool,
+}
+
+/// Bash command dialog details
+#[napi(object)]
+pub struct BashConfirm {
+    pub command: String,
+    pub description: Option<String>,
+    /// Command prefix the "don't ask again" option allows
+    pub allow_prefix: Option<String>,
+    /// Directory the "don't ask again" option applies to
+    pub allow_directory: Option<String>,
+}
+
+/// File dialog details
+#[napi(object)]
+pub struct FileConfirm {
+    pub operation: FileOperation,
+    pub path: String,
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// A change to one file shown under an Edit or Write result
+#[napi(object)]
+pub struct FileDiff {
+    pub path: String,
+    /// Whether the file is new
+    pub created: bool,
+    pub hunks: Vec<DiffHunk>,
+    /// Whether the display left lines out
+    pub truncated: bool,
+    /// Unified diff for `git apply` (unset when truncated)
+    pub unified: Option<String>,
+}
+
+impl From<semantic::FileDiff> for FileDiff {
+    fn from(d: semantic::FileDiff) -> Self {
+        let unified = d.to_unified();
+        FileDiff {
+            path: d.path,
+            created: d.created,
+            hunks: d.hunks.into_iter().map(Into::into).collect(),
+            truncated: d.truncated,
+            unified,
+        }
+    }
+}
+
+/// A contiguous block of changed lines
+#[napi(object)]
+pub struct DiffHunk {
+    pub old_start: u32,
+    pub new_start: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+/// A line of a diff hunk
+#[napi(object)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub old_line: Option<u32>,
+    pub new_line: Option<u32>,
+    pub text: String,
+}
+
+impl From<semantic::DiffHunk> for DiffHunk {
+    fn from(h: semantic::DiffHunk) -> Self {
+        DiffHunk {
+            old_start: h.old_start,
+            new_start: h.new_start,
+            lines: h
+                .lines
+                .into_iter()
+                .map(|l| DiffLine {
+                    kind: l.kind.into(),
+                    old_line: l.old_line,
+                    new_line: l.new_line,
+                    text: l.text,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A single flagged risk
+#[napi(object)]
+pub struct RiskFlag {
+    pub category: RiskCategory,
+    pub level: RiskLevel,
+    pub reason: String,
+}
+
+/// Risk assessment of a confirmation dialog
+#[napi(object)]
+pub struct RiskReport {
+    pub level: RiskLevel,
+    pub flags: Vec<RiskFlag>,
 }
 
 /// Status bar info
@@ -175,10 +470,46 @@ pub struct TitleInfo {
 #[napi(object)]
 pub struct ToolOutput {
     pub tool_name: String,
+    pub mcp_server: Option<String>,
+    pub is_mcp: bool,
     pub params: HashMap<String, serde_json::Value>,
+    /// Typed parameters: `{ tool: 'Read', file_path, offset, limit }` etc.
+    pub call: serde_json::Value,
     pub output: Option<String>,
+    /// Result summary: `{ kind: 'read', lines }`, `{ kind: 'error', exit_code, message }` etc.
+    pub summary: Option<serde_json::Value>,
+    /// Change shown under an Edit or Write result
+    pub diff: Option<FileDiff>,
     pub duration_ms: Option<f64>,
     pub status: ToolStatus,
+    /// Index of the block's header line
+    pub start_line: u32,
+    /// Index one past the block's last line
+    pub end_line: u32,
+}
+
+impl From<semantic::ToolOutputResult> for ToolOutput {
+    fn from(result: semantic::ToolOutputResult) -> Self {
+        let call = serde_json::to_value(result.data.call()).unwrap_or_default();
+        let summary = result
+            .data
+            .summary
+            .and_then(|s| serde_json::to_value(s).ok());
+        Self {
+            tool_name: result.data.tool_name,
+            mcp_server: result.data.mcp_server,
+            is_mcp: result.data.is_mcp,
+            params: result.data.params,
+            call,
+            output: result.data.output,
+            summary,
+            diff: result.data.diff.map(Into::into),
+            duration_ms: result.data.duration_ms,
+            status: result.data.status.into(),
+            start_line: result.start_line as u32,
+            end_line: result.end_line as u32,
+        }
+    }
 }
 
 /// Fingerprint match result
@@ -188,6 +519,8 @@ pub struct FingerprintMatch {
     pub matched: bool,
     pub captures: Option<Vec<String>>,
     pub line_index: Option<u32>,
+    /// Every line index where the pattern matched
+    pub line_indices: Vec<u32>,
 }
 
 /// Fingerprint hints
@@ -215,6 +548,12 @@ pub struct StateParser {
     inner: ClaudeCodeStateParser,
 }
 
+impl Default for StateParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[napi]
 impl StateParser {
     #[napi(constructor)]
@@ -252,11 +591,25 @@ impl StateParser {
                 })
                 .unwrap_or((None, None));
 
+            let evidence = result
+                .evidence
+                .into_iter()
+                .map(|e| StateEvidence {
+                    rule: e.rule,
+                    state: e.state.into(),
+                    lines: e.lines.into_iter().map(|i| i as u32).collect(),
+                    weight: e.weight,
+                    contribution: e.contribution,
+                })
+                .collect();
+
             StateResult {
                 state: result.state.into(),
                 confidence: result.confidence,
                 needs_trust_confirm,
                 confirm_type,
+                evidence,
+                version: result.version.map(|v| v.to_string()),
             }
         })
     }
@@ -266,6 +619,13 @@ impl StateParser {
 #[napi]
 pub struct ConfirmParser {
     inner: ClaudeCodeConfirmParser,
+    keys: semantic::KeyEncoder,
+}
+
+impl Default for ConfirmParser {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[napi]
@@ -274,9 +634,27 @@ impl ConfirmParser {
     pub fn new() -> Self {
         Self {
             inner: ClaudeCodeConfirmParser::new(),
+            keys: semantic::KeyEncoder::new(),
         }
     }
 
+    /// Encode responses for the terminal's input modes (default: bracketed
+    /// paste on, normal cursor keys)
+    #[napi]
+    pub fn set_input_modes(&mut self, application_cursor: bool, bracketed_paste: bool) {
+        self.keys = semantic::KeyEncoder::with_modes(semantic::InputModes {
+            application_cursor,
+            bracketed_paste,
+        });
+    }
+
+    /// Flag file writes outside `root` in risk reports
+    #[napi]
+    pub fn set_workspace(&mut self, root: String) {
+        self.inner = ClaudeCodeConfirmParser::new()
+            .with_risk_assessor(semantic::RiskAssessor::new().with_workspace(root));
+    }
+
     /// Detect and parse confirmation dialog
     #[napi]
     pub fn detect(&self, lines: Vec<String>) -> Option<ConfirmInfo> {
@@ -289,6 +667,9 @@ impl ConfirmParser {
                         key: o.key.to_string(),
                         label: o.label,
                         is_default: o.is_default,
+                        selected: o.selected,
+                        kind: o.kind.into(),
+                        scope: o.scope,
                     })
                     .collect()
             });
@@ -299,41 +680,201 @@ impl ConfirmParser {
                 params: t.params,
             });
 
+            let risk = info.risk.map(|r| RiskReport {
+                level: r.level.into(),
+                flags: r
+                    .flags
+                    .into_iter()
+                    .map(|f| RiskFlag {
+                        category: f.category.into(),
+                        level: f.level.into(),
+                        reason: f.reason,
+                    })
+                    .collect(),
+            });
+
+            let (bash, file) = match info.details {
+                Some(semantic::ConfirmDetails::Bash(b)) => (
+                    Some(BashConfirm {
+                        command: b.command,
+                        description: b.description,
+                        allow_prefix: b.allow_prefix,
+                        allow_directory: b.allow_directory,
+                    }),
+                    None,
+                ),
+                Some(semantic::ConfirmDetails::File(f)) => (
+                    None,
+                    Some(FileConfirm {
+                        operation: f.operation.into(),
+                        path: f.path,
+                        hunks: f.hunks.into_iter().map(Into::into).collect(),
+                    }),
+                ),
+                None => (None, None),
+            };
+
             ConfirmInfo {
                 confirm_type: info.confirm_type.into(),
                 prompt: info.prompt,
                 options,
                 tool,
                 raw_prompt: info.raw_prompt,
+                risk,
+                bash,
+                file,
+                feedback_entry: info.feedback_entry,
             }
         })
     }
 
-    /// Format a response for the terminal
+    /// Format a response to the dialog on screen, moving from the ❯ selection
+    ///
+    /// Fails when no dialog is detected or the selection cannot be determined.
     #[napi]
-    pub fn format_confirm(&self) -> String {
-        "\r".to_string()
+    pub fn format_response(
+        &self,
+        lines: Vec<String>,
+        action: ConfirmAction,
+        option: Option<u32>,
+        value: Option<String>,
+        kind: Option<ConfirmOptionKind>,
+    ) -> napi::Result<String> {
+        let response = semantic::ConfirmResponse {
+            action: action.into(),
+            option,
+            value,
+            kind: kind.map(Into::into),
+        };
+        self.respond(lines, &response)
     }
 
-    /// Format a deny response for the terminal
+    /// Format a confirm response, picking the allow-once option by kind
     #[napi]
-    pub fn format_deny(&self, confirm_type: ConfirmType) -> String {
-        match confirm_type {
-            ConfirmType::Options => "\x1b[B\x1b[B\r".to_string(),
-            ConfirmType::YesNo => "n\r".to_string(),
-        }
+    pub fn format_confirm(&self, lines: Vec<String>) -> napi::Result<String> {
+        self.respond(lines, &semantic::ConfirmResponse::confirm())
     }
 
-    /// Format a select response for the terminal
+    /// Format a deny response, picking the deny option by kind
     #[napi]
-    pub fn format_select(&self, option: u32) -> String {
-        if option > 1 {
-            let down_keys = "\x1b[B".repeat((option - 1) as usize);
-            format!("{}\r", down_keys)
-        } else {
-            "\r".to_string()
+    pub fn format_deny(&self, lines: Vec<String>) -> napi::Result<String> {
+        self.respond(lines, &semantic::ConfirmResponse::deny())
+    }
+
+    /// Format a select response, moving from the ❯ selection to `option`
+    #[napi]
+    pub fn format_select(&self, lines: Vec<String>, option: u32) -> napi::Result<String> {
+        self.respond(lines, &semantic::ConfirmResponse::select(option))
+    }
+
+    fn respond(
+        &self,
+        lines: Vec<String>,
+        response: &semantic::ConfirmResponse,
+    ) -> napi::Result<String> {
+        let context = semantic::ParserContext::new(lines);
+        let info = self
+            .inner
+            .detect_confirm(&context)
+            .ok_or_else(|| napi::Error::from_reason("no confirmation dialog detected"))?;
+        self.inner
+            .format_response_with(&info, response, &self.keys)
+            .map_err(|e| napi::Error::from_reason(e.to_string()))
+    }
+}
+
+/// Claude Code input actions
+#[napi]
+pub struct Input {
+    inner: semantic::ClaudeCodeInput,
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[napi]
+impl Input {
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        Self {
+            inner: semantic::ClaudeCodeInput::new(),
         }
     }
+
+    /// Encode for the terminal's input modes (default: bracketed paste on,
+    /// normal cursor keys)
+    #[napi]
+    pub fn set_input_modes(&mut self, application_cursor: bool, bracketed_paste: bool) {
+        self.inner = semantic::ClaudeCodeInput::with_key_encoder(semantic::KeyEncoder::with_modes(
+            semantic::InputModes {
+                application_cursor,
+                bracketed_paste,
+            },
+        ));
+    }
+
+    /// States an action is meant to be sent in
+    #[napi]
+    pub fn expected_states(&self, action: InputAction) -> Vec<State> {
+        action
+            .with_text(String::new())
+            .expected_states()
+            .iter()
+            .map(|s| (*s).into())
+            .collect()
+    }
+
+    /// Encode an action, failing when it is not meant for `state`
+    #[napi]
+    pub fn encode(
+        &self,
+        action: InputAction,
+        state: State,
+        text: Option<String>,
+    ) -> napi::Result<String> {
+        self.inner
+            .encode_checked(&action.with_text(text.unwrap_or_default()), state.into())
+            .map_err(|e| napi::Error::from_reason(e.to_string()))
+    }
+
+    /// Type a prompt and press Enter (expects Idle)
+    #[napi]
+    pub fn submit(&self, prompt: String) -> String {
+        self.inner.submit(prompt)
+    }
+
+    /// Esc to stop the running request (expects Thinking or ToolRunning)
+    #[napi]
+    pub fn interrupt(&self) -> String {
+        self.inner.interrupt()
+    }
+
+    /// Ctrl+C twice to quit (expects Idle with an empty prompt)
+    #[napi]
+    pub fn exit(&self) -> String {
+        self.inner.exit()
+    }
+
+    /// Shift+Tab to cycle permission modes (expects Idle)
+    #[napi]
+    pub fn cycle_mode(&self) -> String {
+        self.inner.cycle_mode()
+    }
+
+    /// Run a slash command, leading `/` optional (expects Idle)
+    #[napi]
+    pub fn slash_command(&self, command: String) -> String {
+        self.inner.slash_command(command)
+    }
+
+    /// Esc twice to open the rewind list (expects Idle with an empty prompt)
+    #[napi]
+    pub fn rewind(&self) -> String {
+        self.inner.rewind()
+    }
 }
 
 /// Claude Code status parser
@@ -342,6 +883,12 @@ pub struct StatusParser {
     inner: ClaudeCodeStatusParser,
 }
 
+impl Default for StatusParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[napi]
 impl StatusParser {
     #[napi(constructor)]
@@ -378,6 +925,12 @@ pub struct TitleParser {
     inner: ClaudeCodeTitleParser,
 }
 
+impl Default for TitleParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[napi]
 impl TitleParser {
     #[napi(constructor)]
@@ -413,6 +966,12 @@ pub struct ToolOutputParser {
     inner: ClaudeCodeToolOutputParser,
 }
 
+impl Default for ToolOutputParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[napi]
 impl ToolOutputParser {
     #[napi(constructor)]
@@ -429,17 +988,80 @@ impl ToolOutputParser {
         self.inner.can_parse(&context)
     }
 
-    /// Parse tool output
+    /// Parse tool output (the first block)
     #[napi]
     pub fn parse(&self, lines: Vec<String>) -> Option<ToolOutput> {
         let context = semantic::ParserContext::new(lines);
+        self.inner.parse(&context).map(Into::into)
+    }
 
-        self.inner.parse(&context).map(|result| ToolOutput {
-            tool_name: result.data.tool_name,
-            params: result.data.params,
-            output: result.data.output,
-            duration_ms: result.data.duration_ms,
-            status: result.data.status.into(),
+    /// Parse every tool block, top to bottom
+    #[napi]
+    pub fn parse_all(&self, lines: Vec<String>) -> Vec<ToolOutput> {
+        let context = semantic::ParserContext::new(lines);
+        self.inner
+            .parse_all(&context)
+            .into_iter()
+            .map(Into::into)
+            .collect()
+    }
+}
+
+/// Policy decision for a confirmation dialog
+#[napi(object)]
+pub struct PolicyVerdict {
+    /// `allow`, `deny`, `ask`, `option` or `choose`
+    pub decision: String,
+    /// Option number (for `option`)
+    pub option: Option<u32>,
+    /// Option kind (for `choose`)
+    pub kind: Option<ConfirmOptionKind>,
+    /// Index of the matching rule (unset when the default applied)
+    pub rule_index: Option<u32>,
+    /// Label of the matching rule
+    pub rule: Option<String>,
+}
+
+/// Ordered permission rules deciding confirmation dialogs
+#[napi]
+pub struct ConfirmPolicy {
+    inner: semantic::ConfirmPolicy,
+    parser: ClaudeCodeConfirmParser,
+}
+
+#[napi]
+impl ConfirmPolicy {
+    /// Load a policy file (`.toml` or JSON)
+    #[napi(factory)]
+    pub fn from_file(path: String) -> napi::Result<Self> {
+        let inner = semantic::ConfirmPolicy::from_path(path)
+            .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+        Ok(Self {
+            inner,
+            parser: ClaudeCodeConfirmParser::new(),
+        })
+    }
+
+    /// Decide the confirmation dialog on screen, if any
+    #[napi]
+    pub fn evaluate(&self, lines: Vec<String>) -> Option<PolicyVerdict> {
+        let context = semantic::ParserContext::new(lines);
+        let info = self.parser.detect_confirm(&context)?;
+        let verdict = self.inner.evaluate(&info);
+
+        let (decision, option, kind) = match verdict.decision {
+            semantic::PolicyDecision::Allow => ("allow", None, None),
+            semantic::PolicyDecision::Deny => ("deny", None, None),
+            semantic::PolicyDecision::Ask => ("ask", None, None),
+            semantic::PolicyDecision::Option(n) => ("option", Some(n), None),
+            semantic::PolicyDecision::Choose(k) => ("choose", None, Some(k.into())),
+        };
+        Some(PolicyVerdict {
+            decision: decision.to_string(),
+            option,
+            kind,
+            rule_index: verdict.rule_index.map(|i| i as u32),
+            rule: verdict.rule,
         })
     }
 }
@@ -450,6 +1072,12 @@ pub struct Registry {
     inner: FingerprintRegistry,
 }
 
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[napi]
 impl Registry {
     /// Create a new registry with default Claude Code fingerprints
@@ -460,6 +1088,14 @@ impl Registry {
         }
     }
 
+    /// Load a fingerprint pack file (`.toml` or JSON), overriding or disabling built-ins
+    #[napi]
+    pub fn load_pack(&mut self, path: String) -> napi::Result<()> {
+        self.inner
+            .load_pack(path)
+            .map_err(|e| napi::Error::from_reason(e.to_string()))
+    }
+
     /// Extract fingerprints from terminal lines
     #[napi]
     pub fn extract(&self, lines: Vec<String>) -> FingerprintResult {
@@ -477,6 +1113,7 @@ impl Registry {
                         matched: m.matched,
                         captures: m.captures,
                         line_index: m.line_index.map(|i| i as u32),
+                        line_indices: m.line_indices.iter().map(|&i| i as u32).collect(),
                     },
                 )
             })
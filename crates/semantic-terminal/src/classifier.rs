@@ -0,0 +1,248 @@
+//! Fingerprint-driven state classifier
+//!
+//! Scores every candidate state from the `state_weights` of the fingerprints
+//! that matched a frame, so new UI strings only need a new fingerprint.
+
+use super::fingerprint::{default_registry, FingerprintRegistry, FingerprintResult};
+use super::types::{
+    score_evidence, Evidence, ParserContext, State, StateDetectionResult, StateScore,
+};
+
+/// Default minimum score for a state to be reported
+pub const DEFAULT_MIN_CONFIDENCE: f64 = 0.6;
+
+/// Weighted state classifier over a fingerprint registry
+///
+/// For each matched fingerprint, every `(state, weight)` pair becomes a
+/// piece of [`Evidence`]: positive weights are scaled by the fingerprint's
+/// confidence, negative weights suppress the state. Each state's score is
+/// computed like [`StateDetectionResult::from_evidence`], and the best state
+/// is reported when it reaches the minimum confidence.
+///
+/// `Starting` is only a candidate while the context's current state is
+/// `Starting`, since no state leads back to it.
+#[derive(Debug, Clone)]
+pub struct StateClassifier {
+    registry: FingerprintRegistry,
+    min_confidence: f64,
+}
+
+impl Default for StateClassifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateClassifier {
+    /// Create a classifier over the default Claude Code fingerprints
+    pub fn new() -> Self {
+        Self::with_registry(default_registry())
+    }
+
+    /// Create a classifier over a custom registry
+    pub fn with_registry(registry: FingerprintRegistry) -> Self {
+        Self {
+            registry,
+            min_confidence: DEFAULT_MIN_CONFIDENCE,
+        }
+    }
+
+    /// Set the minimum score for a state to be reported
+    pub fn with_min_confidence(mut self, min_confidence: f64) -> Self {
+        self.min_confidence = min_confidence;
+        self
+    }
+
+    /// Get the fingerprint registry
+    pub fn registry(&self) -> &FingerprintRegistry {
+        &self.registry
+    }
+
+    /// Get the fingerprint registry for modification
+    pub fn registry_mut(&mut self) -> &mut FingerprintRegistry {
+        &mut self.registry
+    }
+
+    /// Collect evidence from all matched fingerprints
+    ///
+    /// Ordered by fingerprint priority (descending), then ID.
+    pub fn evidence(&self, context: &ParserContext) -> Vec<Evidence> {
+        self.evidence_from(&self.registry.extract_cached(context))
+    }
+
+    /// Score every candidate state, best first
+    pub fn scores(&self, context: &ParserContext) -> Vec<StateScore> {
+        let mut evidence = self.evidence(context);
+        self.score_all(context, &mut evidence)
+    }
+
+    /// Classify the frame
+    pub fn classify(&self, context: &ParserContext) -> Option<StateDetectionResult> {
+        let extracted = self.registry.extract_cached(context);
+        let mut evidence = self.evidence_from(&extracted);
+        let scores = self.score_all(context, &mut evidence);
+
+        let top = scores.first().copied()?;
+        if top.score < self.min_confidence {
+            return None;
+        }
+
+        let mut result = StateDetectionResult::from_evidence(top.state, evidence)
+            .with_version(extracted.version);
+        if let Some(runner_up) = scores.get(1).filter(|s| s.score > 0.0) {
+            result = result.with_runner_up(*runner_up);
+        }
+        Some(result)
+    }
+
+    fn evidence_from(&self, result: &FingerprintResult) -> Vec<Evidence> {
+        let mut matched: Vec<_> = result
+            .matches
+            .values()
+            .filter(|m| m.matched)
+            .filter_map(|m| self.registry.get(&m.fingerprint_id).map(|fp| (fp, m)))
+            .collect();
+        matched.sort_by(|(a, _), (b, _)| b.priority.cmp(&a.priority).then(a.id.cmp(&b.id)));
+
+        let mut evidence = Vec::new();
+        for (fp, m) in matched {
+            for &(state, weight) in &fp.state_weights {
+                let weight = if weight > 0.0 {
+                    weight * fp.confidence
+                } else {
+                    weight
+                };
+                evidence.push(Evidence::new(
+                    fp.id.as_str(),
+                    state,
+                    m.line_indices.clone(),
+                    weight,
+                ));
+            }
+        }
+        evidence
+    }
+
+    fn score_all(&self, context: &ParserContext, evidence: &mut [Evidence]) -> Vec<StateScore> {
+        let mut scores: Vec<StateScore> = State::ALL
+            .iter()
+            .filter(|&&s| s != State::Starting || context.current_state == Some(State::Starting))
+            .map(|&state| StateScore {
+                state,
+                score: score_evidence(state, evidence),
+            })
+            .collect();
+        // Stable sort keeps State::ALL order on ties
+        scores.sort_by(|a, b| b.score.total_cmp(&a.score));
+        scores
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fingerprint::{
+        Fingerprint, FingerprintCategory, FingerprintPattern, FingerprintType,
+    };
+
+    fn make_context(lines: &[&str]) -> ParserContext<'static> {
+        ParserContext::new(lines.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[test]
+    fn test_scores_rank_candidates() {
+        let classifier = StateClassifier::new();
+        let scores = classifier.scores(&make_context(&["· Pondering… (esc to interrupt)"]));
+
+        assert_eq!(scores[0].state, State::Thinking);
+        assert_eq!(scores[1].state, State::ToolRunning);
+        assert!(scores[0].score > scores[1].score);
+        assert!(scores.iter().all(|s| s.state != State::Starting));
+    }
+
+    #[test]
+    fn test_runner_up() {
+        let classifier = StateClassifier::new();
+        let result = classifier
+            .classify(&make_context(&["Error: build failed", "❯ "]))
+            .unwrap();
+
+        assert_eq!(result.state, State::Idle);
+        let runner_up = result.runner_up.unwrap();
+        assert_eq!(runner_up.state, State::Error);
+        assert!(runner_up.score < result.confidence);
+    }
+
+    #[test]
+    fn test_suppression() {
+        let classifier = StateClassifier::new();
+        // Spinner visible: the prompt line does not make it idle
+        let result = classifier
+            .classify(&make_context(&["❯ ", "· Pondering… (esc to interrupt)"]))
+            .unwrap();
+        assert_eq!(result.state, State::Thinking);
+
+        let suppressor = result
+            .evidence
+            .iter()
+            .find(|e| e.rule == "claude-code.statusbar.running" && e.state == State::Idle)
+            .unwrap();
+        assert_eq!(suppressor.weight, -1.0);
+    }
+
+    #[test]
+    fn test_evidence_lists_every_matching_line() {
+        let classifier = StateClassifier::new();
+        let result = classifier
+            .classify(&make_context(&[
+                "Error: build failed",
+                "fixing",
+                "Error: tests failed",
+                "❯ ",
+            ]))
+            .unwrap();
+        let error = result
+            .evidence
+            .iter()
+            .find(|e| e.state == State::Error)
+            .unwrap();
+        assert_eq!(error.lines, [0, 2]);
+    }
+
+    #[test]
+    fn test_min_confidence() {
+        let classifier = StateClassifier::new();
+        // "Tool:" alone is weak evidence
+        assert!(classifier
+            .classify(&make_context(&["Tool: Read"]))
+            .is_none());
+
+        let classifier = StateClassifier::new().with_min_confidence(0.4);
+        let result = classifier.classify(&make_context(&["Tool: Read"])).unwrap();
+        assert_eq!(result.state, State::ToolRunning);
+    }
+
+    #[test]
+    fn test_new_fingerprint_changes_detection() {
+        let mut classifier = StateClassifier::new();
+        let context = make_context(&["Compacting conversation…"]);
+        assert!(classifier.classify(&context).is_none());
+
+        classifier.registry_mut().register(Fingerprint {
+            id: "custom.compacting".into(),
+            fingerprint_type: FingerprintType::String,
+            category: FingerprintCategory::Statusbar,
+            pattern: FingerprintPattern::String("Compacting conversation".into()),
+            confidence: 0.9,
+            priority: 90,
+            source: "custom".into(),
+            versions: crate::VersionRange::any(),
+            state_weights: vec![(State::Thinking, 1.0), (State::Idle, -1.0)],
+        });
+
+        let result = classifier.classify(&context).unwrap();
+        assert_eq!(result.state, State::Thinking);
+        assert_eq!(result.evidence[0].rule, "custom.compacting");
+        assert_eq!(result.evidence[0].lines, vec![0]);
+    }
+}
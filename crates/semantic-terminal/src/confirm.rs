@@ -3,19 +3,31 @@
 //! Parses Claude Code tool confirmation dialogs.
 
 use std::collections::HashMap;
+use std::ops::Range;
 
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use super::diff::{is_numbered_line, parse_listing, parse_numbered};
+use super::fingerprint::{default_registry, FingerprintRegistry};
+use super::keys::{Key, KeyEncoder};
+use super::risk::RiskAssessor;
 use super::types::{
-    ConfirmAction, ConfirmInfo, ConfirmKey, ConfirmOption, ConfirmParser, ConfirmResponse,
-    ConfirmType, ParserContext, ParserMeta, ToolInfo,
+    BashConfirm, ConfirmAction, ConfirmDetails, ConfirmInfo, ConfirmKey, ConfirmOption,
+    ConfirmOptionKind, ConfirmParser, ConfirmResponse, ConfirmResponseError, ConfirmType,
+    FileConfirm, FileOperation, ParserContext, ParserMeta, ToolInfo,
 };
 
-/// Regex patterns for confirm parsing
-static OPTION_CONFIRM_PATTERN: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?mi)^[\s❯>]*1\.\s*(Yes|Allow)").unwrap());
+/// Fingerprint of an options dialog ("1. Yes" ... "Esc to cancel")
+const OPTIONS_DIALOG_FINGERPRINT: &str = "claude-code.confirm.options-dialog";
+
+/// Fingerprint of the open "tell Claude what to do differently" field
+const FEEDBACK_INPUT_FINGERPRINT: &str = "claude-code.confirm.feedback-input";
+
+/// Fingerprint of a Y/n style prompt
+const YES_NO_FINGERPRINT: &str = "claude-code.confirm.yes-no";
 
+/// Regex patterns for confirm parsing
 static YES_NO_CONFIRM_PATTERN: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"(?i)\[Y/n\]|\(yes/no\)|Allow\?|Do you want to proceed").unwrap());
 
@@ -27,9 +39,34 @@ static TOOL_INFO_PATTERN: Lazy<Regex> =
 static PARAM_PATTERN: Lazy<Regex> =
     Lazy::new(|| Regex::new(r#"(\w+):\s*("[^"]*"|[^,)]+)"#).unwrap());
 
-/// Option line pattern: number. label (with optional leading ❯ or > and spaces)
+/// Option line pattern: number. label (with optional leading ❯ or >, spaces and box border)
 static OPTION_LINE_PATTERN: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"^[\s❯>]*(\d+)\.\s*(.+)$").unwrap());
+    Lazy::new(|| Regex::new(r"^[\s│❯>]*(\d+)\.\s*(.+?)[\s│]*$").unwrap());
+
+/// Selection marker at the start of an option line
+static SELECTED_OPTION_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[\s│]*[❯>]").unwrap());
+
+/// "don't ask again for <scope>" in an always-allow option, minus a trailing key hint
+static ALLOW_SCOPE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)don[’']t ask again for (.+?)(?:\s*\([^)]*\))?$").unwrap()
+});
+
+/// Header of a Bash command approval box
+const BASH_HEADER: &str = "Bash command";
+
+/// "don't ask again for <prefix> commands in <dir>" option of a Bash dialog
+static BASH_SCOPE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)don[’']t ask again for (.+?) commands in (.+?)(?:\s*\([^)]*\))?$").unwrap()
+});
+
+/// File dialog header, with the path on the same line in newer versions
+static FILE_HEADER_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*(Edit|Create|Overwrite) file(?:\s+(.+?))?\s*$").unwrap());
+
+/// File dialog question: "Do you want to make this edit to foo.rs?"
+static FILE_QUESTION_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)Do you want to (make this edit to|create|overwrite) (.+?)\?").unwrap()
+});
 
 /// Y/n prompt cleanup pattern
 static YN_CLEANUP_PATTERN: Lazy<Regex> =
@@ -40,8 +77,14 @@ static YN_CLEANUP_PATTERN: Lazy<Regex> =
 /// Parses confirmation dialogs and formats responses:
 /// - Options-style: 1. Yes, 2. ..., 3. No (use arrow keys + Enter)
 /// - Y/n style: [Y/n] or (yes/no) prompts
+///
+/// Dialogs are recognized by the `claude-code.confirm.options-dialog` and
+/// `claude-code.confirm.yes-no` fingerprints of the registry.
 pub struct ClaudeCodeConfirmParser {
     meta: ParserMeta,
+    registry: FingerprintRegistry,
+    risk: RiskAssessor,
+    keys: KeyEncoder,
 }
 
 impl Default for ClaudeCodeConfirmParser {
@@ -53,6 +96,11 @@ impl Default for ClaudeCodeConfirmParser {
 impl ClaudeCodeConfirmParser {
     /// Create a new Claude Code confirm parser
     pub fn new() -> Self {
+        Self::with_registry(default_registry())
+    }
+
+    /// Create a confirm parser over a custom fingerprint registry
+    pub fn with_registry(registry: FingerprintRegistry) -> Self {
         Self {
             meta: ParserMeta {
                 name: "claude-code-confirm".to_string(),
@@ -60,17 +108,16 @@ impl ClaudeCodeConfirmParser {
                 priority: 100,
                 version: "1.0.0".to_string(),
             },
+            registry,
+            risk: RiskAssessor::new(),
+            keys: KeyEncoder::new(),
         }
     }
 
-    /// Check for options-style confirmation
-    fn is_option_confirm(&self, text: &str) -> bool {
-        OPTION_CONFIRM_PATTERN.is_match(text) && text.contains("Esc to cancel")
-    }
-
-    /// Check for Y/n style confirmation
-    fn is_yes_no_confirm(&self, text: &str) -> bool {
-        YES_NO_CONFIRM_PATTERN.is_match(text)
+    /// Use a risk assessor (e.g. one with a workspace root) for detected dialogs
+    pub fn with_risk_assessor(mut self, risk: RiskAssessor) -> Self {
+        self.risk = risk;
+        self
     }
 
     /// Parse tool info from confirmation text
@@ -108,18 +155,27 @@ impl ClaudeCodeConfirmParser {
         })
     }
 
-    /// Parse options from text
+    /// Parse the options of the dialog's option block (see [`option_block`])
+    ///
+    /// Repeated numbers are kept; responses to such a dialog fail with
+    /// [`ConfirmResponseError::DuplicateOption`].
     fn parse_options(&self, text: &str) -> Option<Vec<ConfirmOption>> {
+        let lines: Vec<&str> = text.lines().collect();
         let mut options = Vec::new();
 
-        for line in text.lines() {
+        for line in &lines[option_block(&lines)?] {
             if let Some(caps) = OPTION_LINE_PATTERN.captures(line) {
                 if let (Some(num_match), Some(label_match)) = (caps.get(1), caps.get(2)) {
                     if let Ok(num) = num_match.as_str().parse::<u32>() {
+                        let label = label_match.as_str().trim().to_string();
+                        let (kind, scope) = classify_option(&label);
                         options.push(ConfirmOption {
                             key: ConfirmKey::Number(num),
-                            label: label_match.as_str().trim().to_string(),
+                            label,
                             is_default: num == 1,
+                            selected: SELECTED_OPTION_PATTERN.is_match(line),
+                            kind,
+                            scope,
                         });
                     }
                 }
@@ -134,33 +190,439 @@ impl ClaudeCodeConfirmParser {
     }
 
     /// Extract the main prompt/question
+    ///
+    /// The paragraph right above the option block, or ending at the last
+    /// question line when no options are visible. Transcript text further up
+    /// is not part of the dialog.
     fn extract_prompt(&self, text: &str) -> String {
+        let lines: Vec<&str> = text.lines().map(strip_box).collect();
+        let end = match option_block(&lines) {
+            Some(block) => block.start,
+            None => match lines.iter().rposition(|l| is_question(l)) {
+                Some(question) => question + 1,
+                None => return String::new(),
+            },
+        };
+        let start = lines[..end]
+            .iter()
+            .rposition(|l| is_rule(l))
+            .map_or(0, |i| i + 1);
+
         let mut prompt_lines = Vec::new();
+        for line in &lines[start..end] {
+            // Handle Y/n type prompts - extract text before the prompt indicator
+            let cleaned = YN_CLEANUP_PATTERN.replace(line, "");
+            let trimmed = cleaned.trim();
+            if !trimmed.is_empty() {
+                prompt_lines.push(trimmed.to_string());
+            }
+        }
 
-        for line in text.lines() {
-            // Stop at options
-            if OPTION_LINE_PATTERN.is_match(line) {
-                break;
+        prompt_lines.join("\n")
+    }
+
+    /// Encode responses for specific input modes (default: Claude Code's)
+    pub fn with_key_encoder(mut self, keys: KeyEncoder) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Keys that answer the dialog with `response`
+    ///
+    /// Claude Code marks the current selection with ❯; options are reached
+    /// with arrow keys relative to it and chosen with Enter. Numbers are not
+    /// typed, as other dialogs (e.g. the feedback field) may intercept them.
+    pub fn response_keys(
+        &self,
+        info: &ConfirmInfo,
+        response: &ConfirmResponse,
+    ) -> Result<Vec<Key>, ConfirmResponseError> {
+        if let Some(number) = repeated_option(info) {
+            return Err(ConfirmResponseError::DuplicateOption(number));
+        }
+        match (response.action, info.confirm_type) {
+            (ConfirmAction::Confirm, ConfirmType::YesNo) => Ok(vec![Key::Enter]),
+            (ConfirmAction::Deny, ConfirmType::YesNo) => {
+                Ok(vec![Key::Text("n".to_string()), Key::Enter])
+            }
+            (ConfirmAction::Confirm, ConfirmType::Options) => {
+                let target = info
+                    .kind_index(ConfirmOptionKind::AllowOnce)
+                    .ok_or(ConfirmResponseError::NoOptionOfKind(ConfirmOptionKind::AllowOnce))?;
+                self.move_to(info, target)
+            }
+            (ConfirmAction::Deny, ConfirmType::Options) => {
+                // "No, and tell Claude ..." also denies; feedback is optional
+                let target = info
+                    .kind_index(ConfirmOptionKind::Deny)
+                    .or_else(|| info.kind_index(ConfirmOptionKind::DenyWithFeedback))
+                    .ok_or(ConfirmResponseError::NoOptionOfKind(ConfirmOptionKind::Deny))?;
+                self.move_to(info, target)
             }
+            (ConfirmAction::Choose, ConfirmType::YesNo) => match response.kind {
+                Some(ConfirmOptionKind::AllowOnce) => Ok(vec![Key::Enter]),
+                Some(ConfirmOptionKind::Deny) => Ok(vec![Key::Text("n".to_string()), Key::Enter]),
+                Some(kind) => Err(ConfirmResponseError::NoOptionOfKind(kind)),
+                None => Err(ConfirmResponseError::MissingArgument(ConfirmAction::Choose)),
+            },
+            (ConfirmAction::Choose, ConfirmType::Options) => {
+                let kind = response
+                    .kind
+                    .ok_or(ConfirmResponseError::MissingArgument(ConfirmAction::Choose))?;
+                let target = info
+                    .kind_index(kind)
+                    .ok_or(ConfirmResponseError::NoOptionOfKind(kind))?;
+                self.move_to(info, target)
+            }
+            (ConfirmAction::Select, _) => {
+                let option = response
+                    .option
+                    .ok_or(ConfirmResponseError::MissingArgument(ConfirmAction::Select))?;
+                let target = info
+                    .option_index(option)
+                    .ok_or(ConfirmResponseError::UnknownOption(option))?;
+                self.move_to(info, target)
+            }
+            (ConfirmAction::DenyWithFeedback, confirm_type) => {
+                let text = response.value.as_deref().ok_or(
+                    ConfirmResponseError::MissingArgument(ConfirmAction::DenyWithFeedback),
+                )?;
+                let text = Key::Text(text.trim_end_matches(['\r', '\n']).to_string());
+                if info.feedback_entry {
+                    return Ok(vec![text, Key::Enter]);
+                }
+                let kind = ConfirmOptionKind::DenyWithFeedback;
+                let target = match confirm_type {
+                    ConfirmType::Options => info.kind_index(kind),
+                    ConfirmType::YesNo => None,
+                }
+                .ok_or(ConfirmResponseError::NoOptionOfKind(kind))?;
+                // Enter on the option opens the field; text typed before it
+                // is open could be read as dialog keys, so it goes in a
+                // second response once `feedback_entry` is seen
+                self.move_to(info, target)
+            }
+            (ConfirmAction::Input, _) => {
+                // Type custom value and press Enter
+                let value = response.value.as_deref().unwrap_or("");
+                Ok(vec![Key::Text(value.to_string()), Key::Enter])
+            }
+        }
+    }
 
-            // Handle Y/n type prompts - extract text before the prompt indicator
-            if YES_NO_CONFIRM_PATTERN.is_match(line) {
-                let cleaned = YN_CLEANUP_PATTERN.replace(line, "");
-                let trimmed = cleaned.trim();
-                if !trimmed.is_empty() {
-                    prompt_lines.push(trimmed.to_string());
+    /// Format a response for terminal input modes other than the parser's
+    /// (e.g. those a [`Screen`](super::Screen) tracked)
+    pub fn format_response_with(
+        &self,
+        info: &ConfirmInfo,
+        response: &ConfirmResponse,
+        keys: &KeyEncoder,
+    ) -> Result<String, ConfirmResponseError> {
+        Ok(keys.encode_all(&self.response_keys(info, response)?))
+    }
+
+    /// Arrow keys from the selected option to `target`, then Enter
+    fn move_to(&self, info: &ConfirmInfo, target: usize) -> Result<Vec<Key>, ConfirmResponseError> {
+        let selected = info
+            .selected_index()
+            .ok_or(ConfirmResponseError::UnknownSelection)?;
+        let (key, count) = if target > selected {
+            (Key::Down, target - selected)
+        } else {
+            (Key::Up, selected - target)
+        };
+        let mut keys = vec![key; count];
+        keys.push(Key::Enter);
+        Ok(keys)
+    }
+
+    /// Parse a "Bash command" approval box
+    ///
+    /// ```text
+    /// Bash command
+    ///
+    ///   git log --oneline -5
+    ///
+    ///   Show recent commits
+    ///
+    /// Do you want to proceed?
+    /// ```
+    ///
+    /// Lines between the header and the question are the command (possibly
+    /// several lines), then Claude's description when the last line is set
+    /// apart by a blank line or less indentation. Box borders (`│`) of older
+    /// versions are ignored.
+    fn parse_bash(
+        &self,
+        context: &ParserContext,
+        options: Option<&[ConfirmOption]>,
+    ) -> Option<BashConfirm> {
+        let lines: Vec<&str> = context.lines().iter().map(strip_box).collect();
+        let header = lines.iter().rposition(|l| l.trim() == BASH_HEADER)?;
+        let body: Vec<&str> = lines[header + 1..]
+            .iter()
+            .map(|l| l.trim_end())
+            .take_while(|l| {
+                let t = l.trim_start();
+                !t.starts_with("Do you want") && !OPTION_LINE_PATTERN.is_match(l)
+            })
+            .collect();
+
+        let content: Vec<&str> = {
+            let start = body.iter().position(|l| !l.is_empty())?;
+            let end = body.iter().rposition(|l| !l.is_empty())?;
+            body[start..=end].to_vec()
+        };
+        let indent_of = |l: &str| l.len() - l.trim_start().len();
+        let (command_lines, description) = match content.split_last() {
+            Some((last, rest))
+                if rest.last().is_some_and(|l| l.is_empty())
+                    || rest.first().is_some_and(|l| indent_of(last) < indent_of(l)) =>
+            {
+                let end = rest.iter().rposition(|l| !l.is_empty()).map_or(0, |i| i + 1);
+                (&rest[..end], Some(last.trim().to_string()))
+            }
+            _ => (&content[..], None),
+        };
+
+        // Drop the box indentation but keep the command's own indentation
+        let indent = command_lines
+            .iter()
+            .filter(|l| !l.is_empty())
+            .map(|l| indent_of(l))
+            .min()
+            .unwrap_or(0);
+        let command = command_lines
+            .iter()
+            .map(|l| l.get(indent..).unwrap_or(""))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let scope = options
+            .into_iter()
+            .flatten()
+            .find_map(|o| BASH_SCOPE_PATTERN.captures(&o.label));
+        Some(BashConfirm {
+            command,
+            description,
+            allow_prefix: scope.as_ref().map(|c| c[1].trim_matches('`').to_string()),
+            allow_directory: scope.as_ref().map(|c| c[2].trim().to_string()),
+        })
+    }
+
+    /// Parse an "Edit file" / "Create file" / "Overwrite file" approval box
+    ///
+    /// ```text
+    /// Edit file
+    /// src/main.rs
+    ///   10   fn main() {
+    ///   11 -     println!("hi");
+    ///   11 +     println!("hello");
+    ///   12   }
+    /// Do you want to make this edit to main.rs?
+    /// ```
+    ///
+    /// Newer versions put the path on the header line. Without a path line
+    /// the file name from the question is used.
+    fn parse_file(&self, context: &ParserContext) -> Option<FileConfirm> {
+        let lines: Vec<&str> = context
+            .lines()
+            .iter()
+            .map(|l| strip_box(l).trim_end())
+            .collect();
+        let question = lines
+            .iter()
+            .rposition(|l| FILE_QUESTION_PATTERN.is_match(l))?;
+        let question_caps = FILE_QUESTION_PATTERN.captures(lines[question])?;
+        let header = lines[..question]
+            .iter()
+            .rposition(|l| FILE_HEADER_PATTERN.is_match(l));
+        let header_caps = header.and_then(|h| FILE_HEADER_PATTERN.captures(lines[h]));
+
+        let operation = match header_caps.as_ref().map(|c| c.get(1).unwrap().as_str()) {
+            Some("Create") => FileOperation::Create,
+            Some("Overwrite") => FileOperation::Overwrite,
+            Some(_) => FileOperation::Edit,
+            None => match question_caps[1].to_lowercase().as_str() {
+                "create" => FileOperation::Create,
+                "overwrite" => FileOperation::Overwrite,
+                _ => FileOperation::Edit,
+            },
+        };
+
+        let mut body = &lines[header.map_or(0, |h| h + 1)..question];
+        let mut path = header_caps
+            .as_ref()
+            .and_then(|c| c.get(2))
+            .map(|m| m.as_str().to_string());
+        if path.is_none() && header.is_some() {
+            if let Some(i) = body.iter().position(|l| !is_rule(l)) {
+                if !is_numbered_line(body[i]) {
+                    path = Some(body[i].trim().to_string());
+                    body = &body[i + 1..];
                 }
-                break;
             }
+        }
+        let path = path.unwrap_or_else(|| question_caps[2].trim().to_string());
 
-            let trimmed = line.trim();
-            if !trimmed.is_empty() {
-                prompt_lines.push(trimmed.to_string());
+        let hunks = match operation {
+            FileOperation::Create => parse_listing(body),
+            FileOperation::Edit | FileOperation::Overwrite => parse_numbered(body),
+        };
+        Some(FileConfirm {
+            operation,
+            path,
+            hunks,
+        })
+    }
+
+    /// Parse Bash or file dialog details, with the tool they stand for
+    fn parse_details(
+        &self,
+        context: &ParserContext,
+        options: Option<&[ConfirmOption]>,
+    ) -> Option<(ToolInfo, ConfirmDetails)> {
+        if let Some(bash) = self.parse_bash(context, options) {
+            let mut params = HashMap::from([("command".to_string(), bash.command.clone())]);
+            if let Some(description) = &bash.description {
+                params.insert("description".to_string(), description.clone());
             }
+            let tool = ToolInfo {
+                name: "Bash".to_string(),
+                mcp_server: None,
+                params,
+            };
+            return Some((tool, ConfirmDetails::Bash(bash)));
         }
 
-        prompt_lines.join("\n")
+        let file = self.parse_file(context)?;
+        let name = match file.operation {
+            FileOperation::Edit => "Edit",
+            FileOperation::Create | FileOperation::Overwrite => "Write",
+        };
+        let tool = ToolInfo {
+            name: name.to_string(),
+            mcp_server: None,
+            params: HashMap::from([("file_path".to_string(), file.path.clone())]),
+        };
+        Some((tool, ConfirmDetails::File(file)))
+    }
+}
+
+/// Line without the `│` borders of (possibly nested) dialog boxes
+fn strip_box(line: &str) -> &str {
+    let mut line = line;
+    while let Some(inner) = line.trim_start().strip_prefix('│') {
+        line = inner.trim_end().strip_suffix('│').unwrap_or(inner);
+    }
+    line
+}
+
+/// Line range of the dialog's options
+///
+/// The contiguous option lines around the ❯ selection marker or, without a
+/// marker, those following the last question line. Numbered lists earlier in
+/// the transcript are never included. Indented lines between two options
+/// (wrapped labels) do not break the block.
+fn option_block(lines: &[&str]) -> Option<Range<usize>> {
+    let is_option = |i: usize| OPTION_LINE_PATTERN.is_match(lines[i]);
+    let is_wrapped = |i: usize| {
+        let line = strip_box(lines[i]);
+        !is_rule(line) && line.starts_with("  ") && !is_question(line)
+    };
+
+    let anchor = match lines
+        .iter()
+        .rposition(|l| OPTION_LINE_PATTERN.is_match(l) && SELECTED_OPTION_PATTERN.is_match(l))
+    {
+        Some(i) => i,
+        None => {
+            let question = lines.iter().rposition(|l| is_question(l))?;
+            (question + 1..lines.len()).find(|&i| is_option(i))?
+        }
+    };
+
+    let mut start = anchor;
+    loop {
+        if start >= 1 && is_option(start - 1) {
+            start -= 1;
+        } else if start >= 2 && is_wrapped(start - 1) && is_option(start - 2) {
+            start -= 2;
+        } else {
+            break;
+        }
+    }
+    let mut end = anchor + 1;
+    loop {
+        if end < lines.len() && is_option(end) {
+            end += 1;
+        } else if end + 1 < lines.len() && is_wrapped(end) && is_option(end + 1) {
+            end += 2;
+        } else {
+            break;
+        }
+    }
+    Some(start..end)
+}
+
+/// First option number that appears more than once
+fn repeated_option(info: &ConfirmInfo) -> Option<u32> {
+    let mut seen = Vec::new();
+    info.options.iter().flatten().find_map(|o| match o.key {
+        ConfirmKey::Number(n) if seen.contains(&n) => Some(n),
+        ConfirmKey::Number(n) => {
+            seen.push(n);
+            None
+        }
+        _ => None,
+    })
+}
+
+/// Question line of a dialog ("Do you want to proceed?", "Continue? [Y/n]")
+fn is_question(line: &str) -> bool {
+    YES_NO_CONFIRM_PATTERN.is_match(line) || FILE_QUESTION_PATTERN.is_match(line)
+}
+
+/// Blank line or horizontal box rule (`╭───╮`, `╌╌╌`)
+fn is_rule(line: &str) -> bool {
+    line.chars()
+        .all(|c| c.is_whitespace() || matches!(c, '─' | '╌' | '┄' | '╭' | '╮' | '╰' | '╯'))
+}
+
+/// Classify an option label, returning its kind and "always" scope
+///
+/// - `Yes` / `Yes, allow this action` → allow once
+/// - `Yes, allow for this session` / `... don't ask again this session` → session
+/// - `Yes, and don't ask again for git status commands in /repo` → always,
+///   scoped to `git status commands in /repo`
+/// - `No, and tell Claude what to do differently (esc)` → deny with feedback
+/// - `No` / `No, deny this action` → deny
+fn classify_option(label: &str) -> (ConfirmOptionKind, Option<String>) {
+    let lower = label.to_lowercase();
+    if lower.starts_with("no") {
+        let kind = if lower.contains("tell claude") || lower.contains("differently") {
+            ConfirmOptionKind::DenyWithFeedback
+        } else {
+            ConfirmOptionKind::Deny
+        };
+        return (kind, None);
+    }
+    if !lower.starts_with("yes") {
+        return (ConfirmOptionKind::Other, None);
+    }
+    if lower.contains("session") {
+        return (ConfirmOptionKind::AllowForSession, None);
+    }
+    if let Some(caps) = ALLOW_SCOPE_PATTERN.captures(label) {
+        return (ConfirmOptionKind::AllowAlways, Some(caps[1].trim().to_string()));
+    }
+    if lower.contains("don't ask again")
+        || lower.contains("don’t ask again")
+        || lower.contains("always")
+    {
+        return (ConfirmOptionKind::AllowAlways, None);
     }
+    (ConfirmOptionKind::AllowOnce, None)
 }
 
 impl ConfirmParser for ClaudeCodeConfirmParser {
@@ -170,99 +632,84 @@ impl ConfirmParser for ClaudeCodeConfirmParser {
 
     fn detect_confirm(&self, context: &ParserContext) -> Option<ConfirmInfo> {
         let text = context.text();
+        let fingerprints = self.registry.extract_cached(context);
+        let matched = |id: &str| fingerprints.matches.get(id).is_some_and(|m| m.matched);
 
         // Check for options-style confirm (Claude Code tool usage)
         // Format: "❯ 1. Yes" or "  1. Yes" (with optional leading arrow/spaces)
-        if self.is_option_confirm(&text) {
-            let tool = self.parse_tool_info(&text);
-            let options = self.parse_options(&text);
+        // The feedback field may replace the options once opened
+        let feedback_entry = matched(FEEDBACK_INPUT_FINGERPRINT);
+        if matched(OPTIONS_DIALOG_FINGERPRINT) || feedback_entry {
+            let options = self.parse_options(text);
+            // Bash and file dialogs first: diff and command lines can look
+            // like an MCP "server - tool(params)" line
+            let (tool, details) = match self.parse_details(context, options.as_deref()) {
+                Some((tool, details)) => (Some(tool), Some(details)),
+                None => (self.parse_tool_info(text), None),
+            };
+            let risk = tool.as_ref().map(|t| self.risk.assess_tool(t));
 
             return Some(ConfirmInfo {
                 confirm_type: ConfirmType::Options,
-                prompt: self.extract_prompt(&text),
+                prompt: self.extract_prompt(text),
                 options,
                 tool,
-                raw_prompt: text,
+                raw_prompt: text.to_string(),
+                risk,
+                details,
+                feedback_entry,
             });
         }
 
         // Check for simple Y/n confirm
-        if self.is_yes_no_confirm(&text) {
+        if matched(YES_NO_FINGERPRINT) {
             return Some(ConfirmInfo {
                 confirm_type: ConfirmType::YesNo,
-                prompt: self.extract_prompt(&text),
+                prompt: self.extract_prompt(text),
                 options: Some(vec![
                     ConfirmOption {
                         key: ConfirmKey::Char("y".to_string()),
                         label: "Yes".to_string(),
                         is_default: true,
+                        selected: false,
+                        kind: ConfirmOptionKind::AllowOnce,
+                        scope: None,
                     },
                     ConfirmOption {
                         key: ConfirmKey::Char("n".to_string()),
                         label: "No".to_string(),
                         is_default: false,
+                        selected: false,
+                        kind: ConfirmOptionKind::Deny,
+                        scope: None,
                     },
                 ]),
                 tool: None,
-                raw_prompt: text,
+                raw_prompt: text.to_string(),
+                risk: None,
+                details: None,
+                feedback_entry: false,
             });
         }
 
         None
     }
 
-    fn format_response(&self, info: &ConfirmInfo, response: &ConfirmResponse) -> String {
-        // Claude Code confirmation dialog uses ❯ to mark current selection
-        // Navigate with arrow keys, confirm with Enter
-        // Cannot input numbers directly as they may be intercepted by other dialogs (e.g., feedback)
-        match response.action {
-            ConfirmAction::Confirm => {
-                // First option is selected, just press Enter
-                "\r".to_string()
-            }
-            ConfirmAction::Deny => {
-                match info.confirm_type {
-                    ConfirmType::Options => {
-                        // Move to third option (No) and press Enter: Down Down Enter
-                        // \x1b[B is the ANSI escape code for down arrow
-                        "\x1b[B\x1b[B\r".to_string()
-                    }
-                    ConfirmType::YesNo => {
-                        // Just type 'n' and Enter
-                        "n\r".to_string()
-                    }
-                }
-            }
-            ConfirmAction::Select => {
-                // Move to specified option and press Enter
-                if let Some(option) = response.option {
-                    if option > 1 {
-                        let down_keys = "\x1b[B".repeat((option - 1) as usize);
-                        format!("{}\r", down_keys)
-                    } else {
-                        "\r".to_string()
-                    }
-                } else {
-                    "\r".to_string()
-                }
-            }
-            ConfirmAction::Input => {
-                // Type custom value and press Enter
-                if let Some(ref value) = response.value {
-                    format!("{}\r", value)
-                } else {
-                    "\r".to_string()
-                }
-            }
-        }
+    fn format_response(
+        &self,
+        info: &ConfirmInfo,
+        response: &ConfirmResponse,
+    ) -> Result<String, ConfirmResponseError> {
+        self.format_response_with(info, response, &self.keys)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::RiskCategory;
 
-    fn make_context(lines: &[&str]) -> ParserContext {
+    fn make_context(lines: &[&str]) -> ParserContext<'static> {
         ParserContext::new(lines.iter().map(|s| s.to_string()).collect())
     }
 
@@ -374,38 +821,473 @@ mod tests {
         assert_eq!(prompt, "Continue?");
     }
 
-    #[test]
-    fn test_format_response_confirm() {
-        let parser = ClaudeCodeConfirmParser::new();
-
-        let info = ConfirmInfo {
+    /// Options dialog "1. Yes / 2. Yes, for this session / 3. No" with ❯ on `selected`
+    fn options_info(selected: u32) -> ConfirmInfo {
+        let labels = ["Yes", "Yes, allow for this session", "No, deny this action"];
+        ConfirmInfo {
             confirm_type: ConfirmType::Options,
             prompt: "Test".to_string(),
-            options: None,
+            options: Some(
+                (1..=3)
+                    .map(|n| {
+                        let label = labels[n as usize - 1];
+                        let (kind, scope) = classify_option(label);
+                        ConfirmOption {
+                            key: ConfirmKey::Number(n),
+                            label: label.to_string(),
+                            is_default: n == 1,
+                            selected: n == selected,
+                            kind,
+                            scope,
+                        }
+                    })
+                    .collect(),
+            ),
             tool: None,
             raw_prompt: "Test".to_string(),
-        };
+            risk: None,
+            details: None,
+            feedback_entry: false,
+        }
+    }
 
-        // Confirm action
+    #[test]
+    fn test_detect_selected_option() {
+        let parser = ClaudeCodeConfirmParser::new();
+
+        let context = make_context(&[
+            "Do you want to proceed?",
+            "  1. Yes",
+            "❯ 2. Yes, and don't ask again this session",
+            "  3. No, and tell Claude what to do differently (esc)",
+            "Esc to cancel",
+        ]);
+        let info = parser.detect_confirm(&context).unwrap();
+        assert_eq!(info.selected_index(), Some(1));
+        let options = info.options.as_ref().unwrap();
+        assert!(options[0].is_default && !options[0].selected);
+        assert!(options[1].selected);
+    }
+
+    #[test]
+    fn test_format_response_confirm() {
+        let parser = ClaudeCodeConfirmParser::new();
+
+        // First option is selected, just press Enter
         let response = ConfirmResponse::confirm();
-        assert_eq!(parser.format_response(&info, &response), "\r");
+        assert_eq!(parser.format_response(&options_info(1), &response).unwrap(), "\r");
+
+        // Cursor was moved down, go back up first
+        assert_eq!(
+            parser.format_response(&options_info(3), &response).unwrap(),
+            "\x1b[A\x1b[A\r"
+        );
     }
 
     #[test]
     fn test_format_response_deny_options() {
         let parser = ClaudeCodeConfirmParser::new();
 
-        let info = ConfirmInfo {
-            confirm_type: ConfirmType::Options,
-            prompt: "Test".to_string(),
-            options: None,
-            tool: None,
-            raw_prompt: "Test".to_string(),
+        // Deny action for options (move to "No" and Enter)
+        let response = ConfirmResponse::deny();
+        assert_eq!(
+            parser.format_response(&options_info(1), &response).unwrap(),
+            "\x1b[B\x1b[B\r"
+        );
+        assert_eq!(
+            parser.format_response(&options_info(2), &response).unwrap(),
+            "\x1b[B\r"
+        );
+        assert_eq!(parser.format_response(&options_info(3), &response).unwrap(), "\r");
+    }
+
+    #[test]
+    fn test_format_response_deny_with_feedback() {
+        let parser = ClaudeCodeConfirmParser::new();
+        let context = make_context(&[
+            "Bash command",
+            "  rm -rf build",
+            "Do you want to proceed?",
+            "❯ 1. Yes",
+            "  2. No, and tell Claude what to do differently (esc)",
+        ]);
+        let info = parser.detect_confirm(&context).unwrap();
+        assert!(!info.feedback_entry);
+
+        // Only move to the feedback option and open it; the text waits for
+        // the field
+        let response = ConfirmResponse::deny_with_feedback("use cargo clean\x1b");
+        assert_eq!(parser.format_response(&info, &response).unwrap(), "\x1b[B\r");
+
+        // Multi-line feedback is pasted so the newline doesn't submit
+        let open = ConfirmInfo {
+            feedback_entry: true,
+            ..info
         };
+        let response = ConfirmResponse::deny_with_feedback("first\r\nsecond\n");
+        assert_eq!(
+            parser.format_response(&open, &response).unwrap(),
+            "\x1b[200~first\nsecond\x1b[201~\r"
+        );
+
+        // No feedback option to pick
+        let response = ConfirmResponse::deny_with_feedback("no");
+        assert_eq!(
+            parser.format_response(&options_info(1), &response),
+            Err(ConfirmResponseError::NoOptionOfKind(
+                ConfirmOptionKind::DenyWithFeedback
+            ))
+        );
+    }
+
+    #[test]
+    fn test_detect_feedback_entry() {
+        let parser = ClaudeCodeConfirmParser::new();
+        let context = make_context(&[
+            "Bash command",
+            "  rm -rf build",
+            "Do you want to proceed?",
+            "  1. Yes",
+            "❯ 2. No, and tell Claude what to do differently (esc)",
+            "",
+            "  > Tell Claude what to do differently",
+        ]);
+        let info = parser.detect_confirm(&context).unwrap();
+        assert!(info.feedback_entry);
+
+        // Field already open: just type and submit
+        let response = ConfirmResponse::deny_with_feedback("use cargo clean\x1b");
+        assert_eq!(
+            parser.format_response(&info, &response).unwrap(),
+            "use cargo clean\r"
+        );
+    }
 
-        // Deny action for options (down down enter)
+    #[test]
+    fn test_detect_attaches_risk() {
+        let parser = ClaudeCodeConfirmParser::new();
+        let context = make_context(&[
+            "xjp-mcp - xjp_secret_get(key: \"test\")",
+            "❯ 1. Yes, allow this action",
+            "  2. No, deny this action",
+            "Esc to cancel",
+        ]);
+        let risk = parser.detect_confirm(&context).unwrap().risk.unwrap();
+        assert_eq!(risk.level, crate::RiskLevel::High);
+        assert!(risk.flags[0].reason.contains("xjp_secret_get"));
+    }
+
+    #[test]
+    fn test_detect_bash_confirm() {
+        let parser = ClaudeCodeConfirmParser::new();
+        let context = make_context(&[
+            " Bash command",
+            "",
+            "   git log --oneline -5",
+            "",
+            "   Show recent commits",
+            "",
+            " Do you want to proceed?",
+            " ❯ 1. Yes",
+            "   2. Yes, and don't ask again for git log commands in /Users/me/repo",
+            "   3. No, and tell Claude what to do differently (esc)",
+        ]);
+        let info = parser.detect_confirm(&context).unwrap();
+
+        let tool = info.tool.as_ref().unwrap();
+        assert_eq!(tool.name, "Bash");
+        assert_eq!(tool.params["command"], "git log --oneline -5");
+        assert_eq!(tool.params["description"], "Show recent commits");
+        let Some(ConfirmDetails::Bash(bash)) = &info.details else {
+            panic!("expected bash details");
+        };
+        assert_eq!(bash.allow_prefix.as_deref(), Some("git log"));
+        assert_eq!(bash.allow_directory.as_deref(), Some("/Users/me/repo"));
+    }
+
+    #[test]
+    fn test_dialog_ignores_numbered_transcript() {
+        let parser = ClaudeCodeConfirmParser::new();
+        let context = make_context(&[
+            "⏺ Plan:",
+            "  1. Update the parser",
+            "  2. Add tests",
+            "",
+            " Bash command",
+            "",
+            "   cargo test",
+            "",
+            "   Run the tests",
+            "",
+            " Do you want to proceed?",
+            " ❯ 1. Yes",
+            "   2. Yes, and don't ask again for cargo test commands in /repo",
+            "   3. No, and tell Claude what to do differently (esc)",
+        ]);
+        let info = parser.detect_confirm(&context).unwrap();
+        assert_eq!(info.prompt, "Do you want to proceed?");
+        assert_eq!(info.options.as_ref().unwrap().len(), 3);
+        assert_eq!(info.option_index(1), Some(0));
+        assert_eq!(info.option_index(3), Some(2));
+
+        let response = ConfirmResponse::select(3);
+        assert_eq!(parser.format_response(&info, &response).unwrap(), "\x1b[B\x1b[B\r");
+    }
+
+    #[test]
+    fn test_deny_ignores_numbered_transcript() {
+        let parser = ClaudeCodeConfirmParser::new();
+        let context = make_context(&[
+            "⏺ Checked the schema:",
+            "  1. Columns are unchanged",
+            "  2. No migration needed",
+            "",
+            " Bash command",
+            "",
+            "   cargo sqlx prepare",
+            "",
+            "   Refresh query metadata",
+            "",
+            " Do you want to proceed?",
+            " ❯ 1. Yes",
+            "   2. No, and tell Claude what to do differently (esc)",
+        ]);
+        let info = parser.detect_confirm(&context).unwrap();
+        assert_eq!(info.kind_index(ConfirmOptionKind::Deny), None);
+        assert_eq!(info.kind_index(ConfirmOptionKind::DenyWithFeedback), Some(1));
         let response = ConfirmResponse::deny();
-        assert_eq!(parser.format_response(&info, &response), "\x1b[B\x1b[B\r");
+        assert_eq!(parser.format_response(&info, &response).unwrap(), "\x1b[B\r");
+    }
+
+    #[test]
+    fn test_repeated_option_numbers_fail() {
+        let parser = ClaudeCodeConfirmParser::new();
+        let context = make_context(&[
+            "Do you want to proceed?",
+            "❯ 1. Yes",
+            "  1. Yes",
+            "  2. No",
+            "Esc to cancel",
+        ]);
+        let info = parser.detect_confirm(&context).unwrap();
+        assert_eq!(
+            parser.format_response(&info, &ConfirmResponse::deny()),
+            Err(ConfirmResponseError::DuplicateOption(1))
+        );
+    }
+
+    #[test]
+    fn test_detect_boxed_multiline_bash_confirm() {
+        let parser = ClaudeCodeConfirmParser::new();
+        let context = make_context(&[
+            "╭──────────────────────────────────────────────╮",
+            "│ Bash command                                 │",
+            "│                                              │",
+            "│   cat > notes.txt <<'EOF'                    │",
+            "│     indented line                            │",
+            "│   EOF                                        │",
+            "│  Write release notes                         │",
+            "│                                              │",
+            "│ Do you want to proceed?                      │",
+            "│ ❯ 1. Yes                                     │",
+            "│   2. No, and tell Claude what to do differently (esc) │",
+            "╰──────────────────────────────────────────────╯",
+        ]);
+        let info = parser.detect_confirm(&context).unwrap();
+        let Some(ConfirmDetails::Bash(bash)) = &info.details else {
+            panic!("expected bash details");
+        };
+        assert_eq!(bash.command, "cat > notes.txt <<'EOF'\n  indented line\nEOF");
+        assert_eq!(bash.description.as_deref(), Some("Write release notes"));
+        assert_eq!(bash.allow_prefix, None);
+
+        let options = info.options.as_ref().unwrap();
+        assert_eq!(options.len(), 2);
+        assert!(options[0].selected);
+        assert_eq!(options[1].kind, ConfirmOptionKind::DenyWithFeedback);
+    }
+
+    #[test]
+    fn test_multiline_bash_without_description() {
+        let parser = ClaudeCodeConfirmParser::new();
+        let context = make_context(&[
+            " Bash command",
+            "",
+            "   docker run --rm \\",
+            "     -v \"$PWD:/src\" \\",
+            "     rust:latest cargo test",
+            "",
+            " Do you want to proceed?",
+            " ❯ 1. Yes",
+            "   2. No, and tell Claude what to do differently (esc)",
+        ]);
+        let info = parser.detect_confirm(&context).unwrap();
+        let Some(ConfirmDetails::Bash(bash)) = &info.details else {
+            panic!("expected bash details");
+        };
+        assert_eq!(
+            bash.command,
+            "docker run --rm \\\n  -v \"$PWD:/src\" \\\n  rust:latest cargo test"
+        );
+        assert_eq!(bash.description, None);
+        assert!(!info.tool.as_ref().unwrap().params.contains_key("description"));
+    }
+
+    #[test]
+    fn test_detect_edit_file_confirm() {
+        let parser = ClaudeCodeConfirmParser::new();
+        let context = make_context(&[
+            "╭────────────────────────────────────────────────────────╮",
+            "│ Edit file                                              │",
+            "│ ╭────────────────────────────────────────────────────╮ │",
+            "│ │ src/main.rs                                        │ │",
+            "│ │                                                    │ │",
+            "│ │  10   fn main() {                                  │ │",
+            "│ │  11 -     println!(\"hi\");                          │ │",
+            "│ │  11 +     println!(\"hello\");                       │ │",
+            "│ │  12   }                                            │ │",
+            "│ ╰────────────────────────────────────────────────────╯ │",
+            "│ Do you want to make this edit to main.rs?              │",
+            "│ ❯ 1. Yes                                               │",
+            "│   2. Yes, allow all edits during this session (shift+tab) │",
+            "│   3. No, and tell Claude what to do differently (esc)  │",
+            "╰────────────────────────────────────────────────────────╯",
+        ]);
+        let info = parser.detect_confirm(&context).unwrap();
+        let Some(ConfirmDetails::File(file)) = &info.details else {
+            panic!("expected file details");
+        };
+        assert_eq!(file.operation, FileOperation::Edit);
+        assert_eq!(file.path, "src/main.rs");
+        assert_eq!(file.hunks.len(), 1);
+        let hunk = &file.hunks[0];
+        assert_eq!((hunk.old_start, hunk.new_start), (10, 10));
+        assert_eq!((hunk.added(), hunk.removed()), (1, 1));
+        assert_eq!(hunk.lines[2].text, "    println!(\"hello\");");
+
+        let tool = info.tool.as_ref().unwrap();
+        assert_eq!(tool.name, "Edit");
+        assert_eq!(tool.params.get("file_path").unwrap(), "src/main.rs");
+        assert_eq!(info.options.as_ref().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_detect_create_file_confirm() {
+        let parser = ClaudeCodeConfirmParser::new()
+            .with_risk_assessor(RiskAssessor::new().with_workspace("/home/me/repo"));
+        let context = make_context(&[
+            " Create file /tmp/hello.py",
+            "╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌",
+            "   1 def main():",
+            "   2     print(\"hi\")",
+            "╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌",
+            " Do you want to create hello.py?",
+            " ❯ 1. Yes",
+            "   2. Yes, allow all edits during this session (shift+tab)",
+            "   3. No, and tell Claude what to do differently (esc)",
+        ]);
+        let info = parser.detect_confirm(&context).unwrap();
+        let Some(ConfirmDetails::File(file)) = &info.details else {
+            panic!("expected file details");
+        };
+        assert_eq!(file.operation, FileOperation::Create);
+        assert_eq!(file.path, "/tmp/hello.py");
+        assert_eq!(file.hunks[0].added(), 2);
+        assert_eq!(file.hunks[0].lines[1].text, "    print(\"hi\")");
+        assert_eq!(info.tool.as_ref().unwrap().name, "Write");
+        // Writing outside the workspace is flagged
+        let risk = info.risk.as_ref().unwrap();
+        assert_eq!(risk.flags[0].category, RiskCategory::OutsideWorkspace);
+    }
+
+    #[test]
+    fn test_classify_options() {
+        let cases = [
+            ("Yes", ConfirmOptionKind::AllowOnce, None),
+            ("Yes, allow this action", ConfirmOptionKind::AllowOnce, None),
+            ("Yes, allow for this session", ConfirmOptionKind::AllowForSession, None),
+            (
+                "Yes, allow all edits during this session (shift+tab)",
+                ConfirmOptionKind::AllowForSession,
+                None,
+            ),
+            (
+                "Yes, and don't ask again for git status commands in /home/me/repo",
+                ConfirmOptionKind::AllowAlways,
+                Some("git status commands in /home/me/repo"),
+            ),
+            ("No, deny this action", ConfirmOptionKind::Deny, None),
+            (
+                "No, and tell Claude what to do differently (esc)",
+                ConfirmOptionKind::DenyWithFeedback,
+                None,
+            ),
+            ("Show details", ConfirmOptionKind::Other, None),
+        ];
+        for (label, kind, scope) in cases {
+            let (k, s) = classify_option(label);
+            assert_eq!(k, kind, "{}", label);
+            assert_eq!(s.as_deref(), scope, "{}", label);
+        }
+    }
+
+    #[test]
+    fn test_deny_by_kind() {
+        let parser = ClaudeCodeConfirmParser::new();
+
+        // Two options: "No" is option 2
+        let context = make_context(&["Allow edit?", "❯ 1. Yes", "  2. No", "Esc to cancel"]);
+        let info = parser.detect_confirm(&context).unwrap();
+        assert_eq!(
+            parser.format_response(&info, &ConfirmResponse::deny()).unwrap(),
+            "\x1b[B\r"
+        );
+
+        // Four options: deny picks the feedback option when there is no plain "No"
+        let context = make_context(&[
+            "Bash command",
+            "  git status",
+            "Do you want to proceed?",
+            "❯ 1. Yes",
+            "  2. Yes, and don't ask again for git status commands in /repo",
+            "  3. Yes, allow for this session",
+            "  4. No, and tell Claude what to do differently (esc)",
+            "Esc to cancel",
+        ]);
+        let info = parser.detect_confirm(&context).unwrap();
+        assert_eq!(
+            parser.format_response(&info, &ConfirmResponse::deny()).unwrap(),
+            "\x1b[B\x1b[B\x1b[B\r"
+        );
+        let response = ConfirmResponse::choose(ConfirmOptionKind::AllowAlways);
+        assert_eq!(parser.format_response(&info, &response).unwrap(), "\x1b[B\r");
+        let response = ConfirmResponse::choose(ConfirmOptionKind::Deny);
+        assert_eq!(
+            parser.format_response(&info, &response),
+            Err(ConfirmResponseError::NoOptionOfKind(ConfirmOptionKind::Deny))
+        );
+    }
+
+    #[test]
+    fn test_format_response_unknown_selection() {
+        let parser = ClaudeCodeConfirmParser::new();
+
+        // No ❯ marker: refuse rather than guess
+        let info = options_info(0);
+        assert_eq!(
+            parser.format_response(&info, &ConfirmResponse::confirm()),
+            Err(ConfirmResponseError::UnknownSelection)
+        );
+
+        // No parsed options: nothing to move to
+        let info = ConfirmInfo {
+            options: None,
+            ..options_info(1)
+        };
+        assert_eq!(
+            parser.format_response(&info, &ConfirmResponse::deny()),
+            Err(ConfirmResponseError::NoOptionOfKind(ConfirmOptionKind::Deny))
+        );
     }
 
     #[test]
@@ -418,36 +1300,39 @@ mod tests {
             options: None,
             tool: None,
             raw_prompt: "Test".to_string(),
+            risk: None,
+            details: None,
+            feedback_entry: false,
         };
 
         // Deny action for Y/n
         let response = ConfirmResponse::deny();
-        assert_eq!(parser.format_response(&info, &response), "n\r");
+        assert_eq!(parser.format_response(&info, &response).unwrap(), "n\r");
     }
 
     #[test]
     fn test_format_response_select() {
         let parser = ClaudeCodeConfirmParser::new();
+        let info = options_info(2);
 
-        let info = ConfirmInfo {
-            confirm_type: ConfirmType::Options,
-            prompt: "Test".to_string(),
-            options: None,
-            tool: None,
-            raw_prompt: "Test".to_string(),
-        };
+        // Select option 2 (no movement needed)
+        let response = ConfirmResponse::select(2);
+        assert_eq!(parser.format_response(&info, &response).unwrap(), "\r");
 
-        // Select option 1 (no movement needed)
+        // Select option 1 (one up)
         let response = ConfirmResponse::select(1);
-        assert_eq!(parser.format_response(&info, &response), "\r");
+        assert_eq!(parser.format_response(&info, &response).unwrap(), "\x1b[A\r");
 
-        // Select option 2 (one down)
-        let response = ConfirmResponse::select(2);
-        assert_eq!(parser.format_response(&info, &response), "\x1b[B\r");
-
-        // Select option 3 (two downs)
+        // Select option 3 (one down)
         let response = ConfirmResponse::select(3);
-        assert_eq!(parser.format_response(&info, &response), "\x1b[B\x1b[B\r");
+        assert_eq!(parser.format_response(&info, &response).unwrap(), "\x1b[B\r");
+
+        // Option not in the dialog
+        let response = ConfirmResponse::select(4);
+        assert_eq!(
+            parser.format_response(&info, &response),
+            Err(ConfirmResponseError::UnknownOption(4))
+        );
     }
 
     #[test]
@@ -460,12 +1345,15 @@ mod tests {
             options: None,
             tool: None,
             raw_prompt: "Test".to_string(),
+            risk: None,
+            details: None,
+            feedback_entry: false,
         };
 
         // Custom input
         let response = ConfirmResponse::input("custom value");
         assert_eq!(
-            parser.format_response(&info, &response),
+            parser.format_response(&info, &response).unwrap(),
             "custom value\r"
         );
     }
@@ -478,4 +1366,19 @@ mod tests {
         let result = parser.detect_confirm(&context);
         assert!(result.is_none());
     }
+
+    #[test]
+    fn test_option_confirm_needs_footer_after_options() {
+        let parser = ClaudeCodeConfirmParser::new();
+
+        // Footer from an unrelated, earlier dialog does not count
+        let mut lines = vec!["Esc to cancel", "❯ 1. Yes"];
+        lines.extend(["output"; 3]);
+        assert!(parser.detect_confirm(&make_context(&lines)).is_none());
+
+        let mut lines = vec!["❯ 1. Yes", "  2. No"];
+        lines.extend(["output"; 20]);
+        lines.push("Esc to cancel");
+        assert!(parser.detect_confirm(&make_context(&lines)).is_none());
+    }
 }
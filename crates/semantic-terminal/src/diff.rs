@@ -0,0 +1,433 @@
+//! Numbered diff display parsing
+//!
+//! Claude Code previews and reports file changes as numbered diff lines:
+//!
+//! ```text
+//!   10   fn main() {
+//!   11 -     println!("hi");
+//!   11 +     println!("hello");
+//!   12   }
+//! ```
+//!
+//! Removed lines carry their old line number, added and context lines their
+//! new one. Hunks are separated by `...` or by a jump in line numbers.
+//!
+//! [`FileDiff`] holds such a display for one file and turns it back into a
+//! unified diff when nothing was left out.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Diff line: number, sigil (`-`, `+` or space), content; blank context
+/// lines may lose their trailing spaces
+static DIFF_LINE_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*(\d+)(?: ([-+ ])(?: (.*))?)?$").unwrap());
+
+/// Numbered file line without a sigil (new file preview)
+static LISTING_LINE_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*(\d+)(?: (.*))?$").unwrap());
+
+/// Hunk separator
+static HUNK_SEPARATOR_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*(?:\.\.\.|…|⋮)\s*$").unwrap());
+
+/// Collapsed lines ("… +12 lines (ctrl+r to expand)")
+static TRUNCATION_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*(?:…|\.\.\.)\s*\+\d+ lines?\b").unwrap());
+
+/// Kind of a diff line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffLineKind {
+    /// Unchanged line shown for context
+    Context,
+    /// Line added by the change
+    Added,
+    /// Line removed by the change
+    Removed,
+}
+
+/// A line of a diff hunk
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    /// Line number in the old file (none for added lines)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_line: Option<u32>,
+    /// Line number in the new file (none for removed lines)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_line: Option<u32>,
+    /// Line content, without number and sigil
+    pub text: String,
+}
+
+/// A contiguous block of changed lines
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffHunk {
+    /// First old line number covered (0 for a new file)
+    pub old_start: u32,
+    /// First new line number covered (0 for a deleted file)
+    pub new_start: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+impl DiffHunk {
+    /// Number of added lines
+    pub fn added(&self) -> usize {
+        self.count(DiffLineKind::Added)
+    }
+
+    /// Number of removed lines
+    pub fn removed(&self) -> usize {
+        self.count(DiffLineKind::Removed)
+    }
+
+    fn count(&self, kind: DiffLineKind) -> usize {
+        self.lines.iter().filter(|l| l.kind == kind).count()
+    }
+}
+
+/// A change to one file as displayed by Claude Code
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDiff {
+    /// File path as shown
+    pub path: String,
+    /// Whether the file is new (shown as a numbered listing)
+    pub created: bool,
+    pub hunks: Vec<DiffHunk>,
+    /// Whether the display left lines out (collapsed, wrapped by the
+    /// terminal, or fewer lines than the result summary counted)
+    pub truncated: bool,
+}
+
+impl FileDiff {
+    /// Parse numbered diff lines shown for a change to `path`
+    pub fn parse<S: AsRef<str>>(path: impl Into<String>, lines: &[S]) -> Self {
+        Self {
+            path: path.into(),
+            created: false,
+            hunks: parse_numbered(lines),
+            truncated: is_truncated(lines, &DIFF_LINE_PATTERN),
+        }
+    }
+
+    /// Parse the numbered listing shown for a newly written `path`
+    pub fn parse_created<S: AsRef<str>>(path: impl Into<String>, lines: &[S]) -> Self {
+        Self {
+            path: path.into(),
+            created: true,
+            hunks: parse_listing(lines),
+            truncated: is_truncated(lines, &LISTING_LINE_PATTERN),
+        }
+    }
+
+    /// Number of added lines
+    pub fn added(&self) -> usize {
+        self.hunks.iter().map(DiffHunk::added).sum()
+    }
+
+    /// Number of removed lines
+    pub fn removed(&self) -> usize {
+        self.hunks.iter().map(DiffHunk::removed).sum()
+    }
+
+    /// Whether the hunks hold the whole change
+    pub fn is_complete(&self) -> bool {
+        !self.truncated && !self.hunks.is_empty()
+    }
+
+    /// Unified diff for `git apply`, if the display was complete
+    ///
+    /// Paths get `a/` and `b/` prefixes; a leading `/` or `./` is dropped, so
+    /// absolute paths apply from the filesystem root. Trailing whitespace the
+    /// terminal did not show can't be recovered.
+    pub fn to_unified(&self) -> Option<String> {
+        if !self.is_complete() {
+            return None;
+        }
+        let path = self.path.trim_start_matches("./").trim_start_matches('/');
+        let mut out = if self.created {
+            format!("--- /dev/null\n+++ b/{}\n", path)
+        } else {
+            format!("--- a/{}\n+++ b/{}\n", path, path)
+        };
+        for hunk in &self.hunks {
+            let old_count = hunk.lines.len() - hunk.added();
+            let new_count = hunk.lines.len() - hunk.removed();
+            // An empty side names the line before the change
+            let start = |start: u32, count: usize| match count {
+                0 => start.saturating_sub(1),
+                _ => start,
+            };
+            out.push_str(&format!(
+                "@@ -{},{} +{},{} @@\n",
+                start(hunk.old_start, old_count),
+                old_count,
+                start(hunk.new_start, new_count),
+                new_count
+            ));
+            for line in &hunk.lines {
+                let sigil = match line.kind {
+                    DiffLineKind::Context => ' ',
+                    DiffLineKind::Added => '+',
+                    DiffLineKind::Removed => '-',
+                };
+                out.push(sigil);
+                out.push_str(&line.text);
+                out.push('\n');
+            }
+        }
+        Some(out)
+    }
+}
+
+/// Hunk being built, with the next expected old/new line numbers
+struct OpenHunk {
+    hunk: DiffHunk,
+    next_old: u32,
+    next_new: u32,
+}
+
+/// Parse numbered diff lines into hunks
+///
+/// Lines that are neither diff lines nor separators (blank lines, wrapped
+/// text) are skipped; [`FileDiff`] marks displays with wrapped text as
+/// truncated.
+pub(crate) fn parse_numbered<S: AsRef<str>>(lines: &[S]) -> Vec<DiffHunk> {
+    let mut hunks = Vec::new();
+    let mut open: Option<OpenHunk> = None;
+    // Net lines added by earlier hunks, to place context lines in the old file
+    let mut offset: i64 = 0;
+
+    for line in lines {
+        let line = line.as_ref();
+        if HUNK_SEPARATOR_PATTERN.is_match(line) {
+            if let Some(done) = open.take() {
+                offset = i64::from(done.next_new) - i64::from(done.next_old);
+                hunks.push(done.hunk);
+            }
+            continue;
+        }
+        let Some(caps) = DIFF_LINE_PATTERN.captures(line) else {
+            continue;
+        };
+        let Ok(number) = caps[1].parse::<u32>() else {
+            continue;
+        };
+        let kind = match caps.get(2).map(|m| m.as_str()) {
+            Some("-") => DiffLineKind::Removed,
+            Some("+") => DiffLineKind::Added,
+            _ => DiffLineKind::Context,
+        };
+        let text = caps.get(3).map_or("", |m| m.as_str()).to_string();
+
+        let continues = open.as_ref().is_some_and(|o| match kind {
+            DiffLineKind::Removed => number == o.next_old,
+            _ => number == o.next_new,
+        });
+        if !continues {
+            if let Some(done) = open.take() {
+                offset = i64::from(done.next_new) - i64::from(done.next_old);
+                hunks.push(done.hunk);
+            }
+            let (old, new) = match kind {
+                DiffLineKind::Removed => (number, shift(number, offset)),
+                _ => (shift(number, -offset), number),
+            };
+            open = Some(OpenHunk {
+                hunk: DiffHunk {
+                    old_start: old,
+                    new_start: new,
+                    lines: Vec::new(),
+                },
+                next_old: old,
+                next_new: new,
+            });
+        }
+
+        let o = open.as_mut().expect("hunk opened above");
+        let (old_line, new_line) = match kind {
+            DiffLineKind::Context => (Some(o.next_old), Some(o.next_new)),
+            DiffLineKind::Removed => (Some(o.next_old), None),
+            DiffLineKind::Added => (None, Some(o.next_new)),
+        };
+        if old_line.is_some() {
+            o.next_old += 1;
+        }
+        if new_line.is_some() {
+            o.next_new += 1;
+        }
+        o.hunk.lines.push(DiffLine {
+            kind,
+            old_line,
+            new_line,
+            text,
+        });
+    }
+
+    hunks.extend(open.map(|o| o.hunk));
+    hunks
+}
+
+/// Parse a numbered listing of a new file into a single all-added hunk
+pub(crate) fn parse_listing<S: AsRef<str>>(lines: &[S]) -> Vec<DiffHunk> {
+    let lines: Vec<DiffLine> = lines
+        .iter()
+        .filter_map(|line| {
+            let caps = LISTING_LINE_PATTERN.captures(line.as_ref())?;
+            Some(DiffLine {
+                kind: DiffLineKind::Added,
+                old_line: None,
+                new_line: Some(caps[1].parse().ok()?),
+                text: caps.get(2).map_or("", |m| m.as_str()).to_string(),
+            })
+        })
+        .collect();
+    match lines.first().and_then(|l| l.new_line) {
+        Some(new_start) => vec![DiffHunk {
+            old_start: 0,
+            new_start,
+            lines,
+        }],
+        None => Vec::new(),
+    }
+}
+
+/// Whether lines were collapsed, or a line was wrapped
+///
+/// A wrapped line continues below its numbered line without a number, so a
+/// non-blank unnumbered line between two `numbered` lines means the text of
+/// one of them is incomplete.
+fn is_truncated<S: AsRef<str>>(lines: &[S], numbered: &Regex) -> bool {
+    if lines.iter().any(|l| TRUNCATION_PATTERN.is_match(l.as_ref())) {
+        return true;
+    }
+    let is_numbered = |l: &S| numbered.is_match(l.as_ref());
+    let (Some(first), Some(last)) = (
+        lines.iter().position(is_numbered),
+        lines.iter().rposition(is_numbered),
+    ) else {
+        return false;
+    };
+    lines[first..last].iter().any(|l| {
+        let line = l.as_ref();
+        !line.trim().is_empty() && !is_numbered(l) && !HUNK_SEPARATOR_PATTERN.is_match(line)
+    })
+}
+
+/// Whether a line looks like a numbered diff or listing line
+pub(crate) fn is_numbered_line(line: &str) -> bool {
+    LISTING_LINE_PATTERN.is_match(line)
+}
+
+fn shift(number: u32, offset: i64) -> u32 {
+    (i64::from(number) + offset).max(1) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_numbered_single_hunk() {
+        let hunks = parse_numbered(&[
+            "  10   fn main() {",
+            "  11 -     println!(\"hi\");",
+            "  11 +     println!(\"hello\");",
+            "  12 +     println!(\"world\");",
+            "  13   }",
+            "  14",
+        ]);
+        assert_eq!(hunks.len(), 1);
+        let hunk = &hunks[0];
+        assert_eq!((hunk.old_start, hunk.new_start), (10, 10));
+        assert_eq!((hunk.added(), hunk.removed()), (2, 1));
+        assert_eq!(hunk.lines[1].text, "    println!(\"hi\");");
+        assert_eq!(hunk.lines[1].old_line, Some(11));
+        assert_eq!(hunk.lines[3].new_line, Some(12));
+        // Context after the change: old 12, new 13
+        assert_eq!(hunk.lines[4].old_line, Some(12));
+        assert_eq!(hunk.lines[4].new_line, Some(13));
+        assert_eq!(hunk.lines[5].kind, DiffLineKind::Context);
+        assert_eq!(hunk.lines[5].text, "");
+    }
+
+    #[test]
+    fn test_parse_numbered_hunks_split_and_offset() {
+        let hunks = parse_numbered(&[
+            "   2   a",
+            "   3 + b",
+            "   4   c",
+            "  ...",
+            "  20   x",
+            "  20 - y",
+            "  40   z",
+        ]);
+        assert_eq!(hunks.len(), 3);
+        // One line added above, so new 20 is old 19
+        assert_eq!((hunks[1].old_start, hunks[1].new_start), (19, 20));
+        assert_eq!(hunks[1].lines[1].old_line, Some(20));
+        // Jump in numbering starts a new hunk, net offset back to 0
+        assert_eq!((hunks[2].old_start, hunks[2].new_start), (40, 40));
+    }
+
+    #[test]
+    fn test_parse_listing() {
+        let hunks = parse_listing(&["   1 fn main() {", "   2 ", "   3 }"]);
+        assert_eq!(hunks.len(), 1);
+        assert_eq!((hunks[0].old_start, hunks[0].new_start), (0, 1));
+        assert_eq!(hunks[0].added(), 3);
+        assert_eq!(hunks[0].lines[1].text, "");
+        assert!(parse_listing(&["no numbers"]).is_empty());
+    }
+
+    #[test]
+    fn test_file_diff_to_unified() {
+        let diff = FileDiff::parse(
+            "src/main.rs",
+            &[
+                "  10   fn main() {",
+                "  11 -     println!(\"hi\");",
+                "  11 +     println!(\"hello\");",
+                "  12   }",
+            ],
+        );
+        assert!(diff.is_complete());
+        assert_eq!(
+            diff.to_unified().unwrap(),
+            "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -10,3 +10,3 @@\n fn main() {\n\
+             -    println!(\"hi\");\n+    println!(\"hello\");\n }\n"
+        );
+
+        let created = FileDiff::parse_created("/tmp/a.txt", &["   1 one", "   2 two"]);
+        assert_eq!(
+            created.to_unified().unwrap(),
+            "--- /dev/null\n+++ b/tmp/a.txt\n@@ -0,0 +1,2 @@\n+one\n+two\n"
+        );
+
+        let collapsed =
+            FileDiff::parse_created("a.txt", &["   1 one", "… +40 lines (ctrl+r to expand)"]);
+        assert!(collapsed.truncated);
+        assert_eq!(collapsed.to_unified(), None);
+    }
+
+    #[test]
+    fn test_wrapped_line_truncates() {
+        let wrapped = [
+            "  10   fn main() {",
+            "  11 +     println!(\"a line too long for the",
+            "       terminal\");",
+            "  12   }",
+        ];
+        let diff = FileDiff::parse("src/main.rs", &wrapped);
+        assert!(diff.truncated);
+        assert_eq!(diff.to_unified(), None);
+        // Blank lines and separators between numbered lines are not wrapping
+        let diff = FileDiff::parse("src/main.rs", &["   2   a", "", "  ...", "  20 - y"]);
+        assert!(!diff.truncated);
+
+        let created = FileDiff::parse_created("a.txt", &["   1 one two", "three", "   2 four"]);
+        assert!(created.truncated);
+    }
+}
@@ -3,12 +3,16 @@
 //! This module provides a fingerprint-based pattern matching system for detecting
 //! various patterns in Claude Code CLI output.
 
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-use super::ParserContext;
+use super::matcher::CompiledMatcher;
+use super::{ClaudeCodeVersion, Lines, ParserContext, State, VersionRange};
 
 // ========== Types ==========
 
@@ -24,6 +28,8 @@ pub enum FingerprintType {
     String,
     /// Marker pattern (alias for enum, used for special markers)
     Marker,
+    /// Sequence of line patterns (multi-line shapes)
+    Sequence,
 }
 
 /// Category of fingerprint
@@ -57,13 +63,147 @@ pub enum FingerprintPattern {
     String(String),
     /// List of string patterns (for enum/marker types)
     Enum(Vec<String>),
+    /// Lines matching each step in order, with at most `max_gap` other lines
+    /// between consecutive steps (each step matches within a single line)
+    Sequence {
+        steps: Vec<FingerprintPattern>,
+        max_gap: usize,
+    },
+    /// Pattern that only matches within the last `lines` lines of the frame
+    Bottom {
+        lines: usize,
+        pattern: Box<FingerprintPattern>,
+    },
+    /// Pattern whose match is rejected when `unless` matches within `within`
+    /// lines of it (0 = the same line)
+    Unless {
+        pattern: Box<FingerprintPattern>,
+        unless: Box<FingerprintPattern>,
+        within: usize,
+    },
+}
+
+/// Line index and captures of a pattern match
+pub type PatternMatch = (usize, Option<Vec<String>>);
+
+impl FingerprintPattern {
+    /// Whether the pattern looks at more than one line at a time
+    pub fn is_composite(&self) -> bool {
+        matches!(
+            self,
+            Self::Sequence { .. } | Self::Bottom { .. } | Self::Unless { .. }
+        )
+    }
+
+    /// First match within `lines[range]`
+    ///
+    /// Regex matches capture their groups, enum matches capture the entry
+    /// that matched, and sequences concatenate the captures of their steps.
+    pub fn find_in(&self, lines: &Lines, range: Range<usize>) -> Option<PatternMatch> {
+        let range = range.start..range.end.min(lines.len());
+        match self {
+            Self::Regex(_) | Self::String(_) | Self::Enum(_) => range
+                .into_iter()
+                .find_map(|i| self.match_line(lines.get(i)?).map(|caps| (i, caps))),
+            Self::Sequence { steps, max_gap } => range.clone().find_map(|i| {
+                let mut captures = Vec::new();
+                match_steps(steps, *max_gap, lines, i, range.end, &mut captures)
+                    .then_some((i, Some(captures)))
+            }),
+            Self::Bottom {
+                lines: bottom,
+                pattern,
+            } => {
+                let start = range.start.max(lines.len().saturating_sub(*bottom));
+                pattern.find_in(lines, start..range.end)
+            }
+            Self::Unless {
+                pattern,
+                unless,
+                within,
+            } => {
+                let mut from = range.start;
+                while let Some((i, captures)) = pattern.find_in(lines, from..range.end) {
+                    let window = i.saturating_sub(*within)..(i + within + 1);
+                    if unless.find_in(lines, window).is_none() {
+                        return Some((i, captures));
+                    }
+                    from = i + 1;
+                }
+                None
+            }
+        }
+    }
+
+    /// Start lines of every match within `lines[range]`
+    pub fn find_all_in(&self, lines: &Lines, range: Range<usize>) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut from = range.start;
+        while let Some((i, _)) = self.find_in(lines, from..range.end) {
+            found.push(i);
+            from = i + 1;
+        }
+        found
+    }
+
+    /// Match a single-line pattern against one line
+    ///
+    /// Returns the captures on a match (`None` inside for string patterns).
+    fn match_line(&self, line: &str) -> Option<Option<Vec<String>>> {
+        match self {
+            Self::Regex(re) => re.captures(line).map(|caps| {
+                Some(
+                    caps.iter()
+                        .skip(1)
+                        .filter_map(|m| m.map(|m| m.as_str().to_string()))
+                        .collect(),
+                )
+            }),
+            Self::String(s) => line.contains(s.as_str()).then_some(None),
+            Self::Enum(patterns) => patterns
+                .iter()
+                .find(|p| line.contains(p.as_str()))
+                .map(|p| Some(vec![p.clone()])),
+            _ => None,
+        }
+    }
+}
+
+/// Match `steps` starting at line `at`, backtracking over gap choices
+fn match_steps(
+    steps: &[FingerprintPattern],
+    max_gap: usize,
+    lines: &Lines,
+    at: usize,
+    end: usize,
+    captures: &mut Vec<String>,
+) -> bool {
+    let Some((step, rest)) = steps.split_first() else {
+        return true;
+    };
+    let Some((_, caps)) = step.find_in(lines, at..at + 1) else {
+        return false;
+    };
+    let len = captures.len();
+    captures.extend(caps.unwrap_or_default());
+    if rest.is_empty() {
+        return true;
+    }
+    let last = (at + 1 + max_gap).min(end.saturating_sub(1));
+    for next in at + 1..=last {
+        if match_steps(rest, max_gap, lines, next, end, captures) {
+            return true;
+        }
+    }
+    captures.truncate(len);
+    false
 }
 
 /// A fingerprint definition for pattern matching
 #[derive(Debug, Clone)]
 pub struct Fingerprint {
     /// Unique identifier
-    pub id: &'static str,
+    pub id: String,
     /// Type of pattern matching
     pub fingerprint_type: FingerprintType,
     /// Category for grouping
@@ -75,11 +215,18 @@ pub struct Fingerprint {
     /// Priority (higher = checked first within category)
     pub priority: u32,
     /// Source identifier (e.g., "claude-code-v1.0")
-    pub source: &'static str,
+    pub source: String,
+    /// Claude Code versions this fingerprint applies to
+    pub versions: VersionRange,
+    /// How strongly a match votes for each state (-1.0 - 1.0)
+    ///
+    /// Positive weights (scaled by `confidence`) add support for a state;
+    /// negative weights suppress it, e.g. a visible spinner rules out `Idle`.
+    pub state_weights: Vec<(State, f64)>,
 }
 
 /// Result of matching a single fingerprint
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FingerprintMatch {
     /// The matched fingerprint
     pub fingerprint_id: String,
@@ -89,6 +236,8 @@ pub struct FingerprintMatch {
     pub captures: Option<Vec<String>>,
     /// Line index where match was found
     pub line_index: Option<usize>,
+    /// Every line index where the pattern matched (first is `line_index`)
+    pub line_indices: Vec<usize>,
 }
 
 /// Hints derived from fingerprint matches
@@ -106,6 +255,20 @@ pub struct FingerprintHints {
     pub has_error: bool,
 }
 
+impl FingerprintHints {
+    /// Derive hints from the categories that matched
+    fn from_categories(categories: &HashMap<FingerprintCategory, Vec<FingerprintMatch>>) -> Self {
+        let has = |category| categories.get(&category).is_some_and(|v| !v.is_empty());
+        Self {
+            has_spinner: has(FingerprintCategory::Spinner),
+            has_prompt: has(FingerprintCategory::Prompt),
+            has_tool_output: has(FingerprintCategory::Tool),
+            has_confirm_dialog: has(FingerprintCategory::Confirm),
+            has_error: has(FingerprintCategory::Error),
+        }
+    }
+}
+
 /// Result of fingerprint extraction
 #[derive(Debug, Clone)]
 pub struct FingerprintResult {
@@ -115,17 +278,39 @@ pub struct FingerprintResult {
     pub categories: HashMap<FingerprintCategory, Vec<FingerprintMatch>>,
     /// Derived hints for quick state detection
     pub hints: FingerprintHints,
+    /// Claude Code version whose fingerprints were active (`None` = all)
+    pub version: Option<ClaudeCodeVersion>,
 }
 
 // ========== Registry ==========
 
+/// Source of registry generations
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+fn next_generation() -> u64 {
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
 /// Registry for fingerprint patterns
-#[derive(Debug, Default)]
+#[derive(Debug, Clone)]
 pub struct FingerprintRegistry {
     /// All registered fingerprints
     fingerprints: HashMap<String, Fingerprint>,
     /// Fingerprints indexed by category
     by_category: HashMap<FingerprintCategory, Vec<String>>,
+    /// Pinned Claude Code version
+    version: Option<ClaudeCodeVersion>,
+    /// Combined matcher, built on first extract after a change
+    compiled: OnceCell<CompiledMatcher>,
+    /// Changes on every modification; clones share it, so contexts can
+    /// cache one scan for all parsers using the same registry
+    generation: u64,
+}
+
+impl Default for FingerprintRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl FingerprintRegistry {
@@ -134,12 +319,58 @@ impl FingerprintRegistry {
         Self {
             fingerprints: HashMap::new(),
             by_category: HashMap::new(),
+            version: None,
+            compiled: OnceCell::new(),
+            generation: next_generation(),
         }
     }
 
+    /// Invalidate the compiled matcher and cached scans
+    fn changed(&mut self) {
+        self.compiled = OnceCell::new();
+        self.generation = next_generation();
+    }
+
+    /// Pin the Claude Code version used to select fingerprints
+    pub fn with_version(mut self, version: ClaudeCodeVersion) -> Self {
+        self.set_version(Some(version));
+        self
+    }
+
+    /// Set or clear the pinned Claude Code version
+    pub fn set_version(&mut self, version: Option<ClaudeCodeVersion>) {
+        self.version = version;
+        self.changed();
+    }
+
+    /// Get the pinned Claude Code version
+    pub fn version(&self) -> Option<ClaudeCodeVersion> {
+        self.version
+    }
+
+    /// Version profile for a context
+    ///
+    /// The context's version hint wins, then the pinned version, then a
+    /// version parsed from a startup banner in the context's lines.
+    pub fn resolve_version(&self, context: &ParserContext) -> Option<ClaudeCodeVersion> {
+        context
+            .version()
+            .or(self.version)
+            .or_else(|| ClaudeCodeVersion::detect(context.lines()))
+    }
+
+    /// Fingerprints active for a version (all of them when unknown)
+    pub fn active(&self, version: Option<ClaudeCodeVersion>) -> Vec<&Fingerprint> {
+        self.fingerprints
+            .values()
+            .filter(|fp| version.is_none_or(|v| fp.versions.contains(v)))
+            .collect()
+    }
+
     /// Register a fingerprint
     pub fn register(&mut self, fp: Fingerprint) {
-        let id = fp.id.to_string();
+        self.changed();
+        let id = fp.id.clone();
         let category = fp.category;
 
         // Remove from old category if exists
@@ -179,6 +410,16 @@ impl FingerprintRegistry {
         }
     }
 
+    /// Remove a fingerprint by ID
+    pub fn unregister(&mut self, id: &str) -> Option<Fingerprint> {
+        let fp = self.fingerprints.remove(id)?;
+        self.changed();
+        if let Some(ids) = self.by_category.get_mut(&fp.category) {
+            ids.retain(|i| i != id);
+        }
+        Some(fp)
+    }
+
     /// Get a fingerprint by ID
     pub fn get(&self, id: &str) -> Option<&Fingerprint> {
         self.fingerprints.get(id)
@@ -197,11 +438,53 @@ impl FingerprintRegistry {
     }
 
     /// Extract fingerprints from parser context
+    ///
+    /// All fingerprints are compiled once into a combined matcher (see the
+    /// `matcher` module), so each line is scanned a single time.
     pub fn extract(&self, context: &ParserContext) -> FingerprintResult {
+        let version = self.resolve_version(context);
+        let compiled = self.compiled();
+
         let mut matches = HashMap::new();
         let mut categories: HashMap<FingerprintCategory, Vec<FingerprintMatch>> = HashMap::new();
+        for m in compiled.extract(context, version) {
+            if m.matched {
+                if let Some(fp) = self.fingerprints.get(&m.fingerprint_id) {
+                    categories.entry(fp.category).or_default().push(m.clone());
+                }
+            }
+            matches.insert(m.fingerprint_id.clone(), m);
+        }
 
-        for (id, fp) in &self.fingerprints {
+        FingerprintResult {
+            hints: FingerprintHints::from_categories(&categories),
+            matches,
+            categories,
+            version,
+        }
+    }
+
+    fn compiled(&self) -> &CompiledMatcher {
+        self.compiled
+            .get_or_init(|| CompiledMatcher::new(self.fingerprints.values()))
+    }
+
+    /// Extract fingerprints, reusing the scan cached on the context
+    ///
+    /// Parsers sharing a registry (or clones of it, like every
+    /// [`default_registry`]) scan a context only once.
+    pub fn extract_cached<'c>(&self, context: &'c ParserContext) -> Cow<'c, FingerprintResult> {
+        context.cached_fingerprints(self.generation, || self.extract(context))
+    }
+
+    /// Reference implementation of [`FingerprintRegistry::extract`], one fingerprint at a time
+    #[cfg(test)]
+    fn extract_naive(&self, context: &ParserContext) -> FingerprintResult {
+        let mut matches = HashMap::new();
+        let mut categories: HashMap<FingerprintCategory, Vec<FingerprintMatch>> = HashMap::new();
+        let version = self.resolve_version(context);
+
+        for fp in self.active(version) {
             let m = self.match_fingerprint(fp, context);
 
             if m.matched {
@@ -209,87 +492,52 @@ impl FingerprintRegistry {
                 cat_matches.push(m.clone());
             }
 
-            matches.insert(id.clone(), m);
+            matches.insert(fp.id.clone(), m);
         }
 
-        let hints = FingerprintHints {
-            has_spinner: categories.get(&FingerprintCategory::Spinner).map_or(false, |v| !v.is_empty()),
-            has_prompt: categories.get(&FingerprintCategory::Prompt).map_or(false, |v| !v.is_empty()),
-            has_tool_output: categories.get(&FingerprintCategory::Tool).map_or(false, |v| !v.is_empty()),
-            has_confirm_dialog: categories.get(&FingerprintCategory::Confirm).map_or(false, |v| !v.is_empty()),
-            has_error: categories.get(&FingerprintCategory::Error).map_or(false, |v| !v.is_empty()),
-        };
-
         FingerprintResult {
+            hints: FingerprintHints::from_categories(&categories),
             matches,
             categories,
-            hints,
+            version,
         }
     }
 
     /// Match a single fingerprint against context
+    #[cfg(test)]
     fn match_fingerprint(&self, fp: &Fingerprint, context: &ParserContext) -> FingerprintMatch {
-        for (i, line) in context.last_lines.iter().enumerate() {
-            match &fp.pattern {
-                FingerprintPattern::Regex(re) => {
-                    if let Some(caps) = re.captures(line) {
-                        let captures: Vec<String> = caps
-                            .iter()
-                            .skip(1)
-                            .filter_map(|m| m.map(|m| m.as_str().to_string()))
-                            .collect();
-                        return FingerprintMatch {
-                            fingerprint_id: fp.id.to_string(),
-                            matched: true,
-                            captures: Some(captures),
-                            line_index: Some(i),
-                        };
-                    }
-                }
-                FingerprintPattern::String(s) => {
-                    if line.contains(s) {
-                        return FingerprintMatch {
-                            fingerprint_id: fp.id.to_string(),
-                            matched: true,
-                            captures: None,
-                            line_index: Some(i),
-                        };
-                    }
-                }
-                FingerprintPattern::Enum(patterns) => {
-                    for p in patterns {
-                        if line.contains(p) {
-                            return FingerprintMatch {
-                                fingerprint_id: fp.id.to_string(),
-                                matched: true,
-                                captures: Some(vec![p.clone()]),
-                                line_index: Some(i),
-                            };
-                        }
-                    }
-                }
-            }
+        let lines = context.lines();
+        if let Some((i, captures)) = fp.pattern.find_in(lines, 0..lines.len()) {
+            return FingerprintMatch {
+                fingerprint_id: fp.id.clone(),
+                matched: true,
+                captures,
+                line_index: Some(i),
+                line_indices: fp.pattern.find_all_in(lines, 0..lines.len()),
+            };
         }
 
         // Also check full content for string patterns
         if let FingerprintPattern::String(s) = &fp.pattern {
-            if let Some(content) = &context.full_content {
-                if content.contains(s) {
+            if let Some(content) = context.full_content() {
+                if content.contains(s.as_str()) {
                     return FingerprintMatch {
-                        fingerprint_id: fp.id.to_string(),
+                        fingerprint_id: fp.id.clone(),
                         matched: true,
                         captures: None,
                         line_index: None,
+                        line_indices: Vec::new(),
                     };
                 }
             }
         }
 
         FingerprintMatch {
-            fingerprint_id: fp.id.to_string(),
+            fingerprint_id: fp.id.clone(),
             matched: false,
             captures: None,
             line_index: None,
+            line_indices: Vec::new(),
         }
     }
 
@@ -297,11 +545,16 @@ impl FingerprintRegistry {
     pub fn clear(&mut self) {
         self.fingerprints.clear();
         self.by_category.clear();
+        self.changed();
     }
 }
 
 // ========== Claude Code Fingerprints ==========
 
+/// The input prompt sits just above the footer, so older prompts in the
+/// transcript above don't count
+const PROMPT_BOTTOM_LINES: usize = 8;
+
 /// Pre-compiled regex patterns for Claude Code
 mod patterns {
     use once_cell::sync::Lazy;
@@ -312,6 +565,13 @@ mod patterns {
             .expect("Invalid statusbar regex")
     });
 
+    /// 2.x status bar, whose hint lists shortcuts, elapsed time and tokens:
+    /// "✶ Tempering… (esc to interrupt · ctrl+t to show todos · 32s · ↓ 1.2k tokens)"
+    pub static STATUSBAR_DETAILS_PATTERN: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^([·✻✽✶✳✢])\s+(\S+…?)\s*\((?:esc|ESC)\s+to\s+interrupt(?:\s*·\s*([^)]*))?\)")
+            .expect("Invalid statusbar details regex")
+    });
+
     pub static PROMPT_INPUT: Lazy<Regex> = Lazy::new(|| {
         Regex::new(r"^[❯>]\s*$").expect("Invalid prompt input regex")
     });
@@ -353,6 +613,33 @@ mod patterns {
         Regex::new(r"(?i)^\s*1\.\s+Yes,?\s").expect("Invalid confirm yes regex")
     });
 
+    pub static SELECTED_OPTION: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^[❯>]\s*\d+\.\s").expect("Invalid selected option regex")
+    });
+
+    pub static CONFIRM_FIRST_OPTION: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?i)^[\s│❯>]*1\.\s*(Yes|Allow)").expect("Invalid confirm first option regex")
+    });
+
+    pub static CONFIRM_DIALOG_END: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"Esc to cancel|^[\s│❯>]*\d+\.\s*No\b.*\(esc\)")
+            .expect("Invalid confirm dialog end regex")
+    });
+
+    pub static CONFIRM_FEEDBACK_INPUT: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?i)^[\s│]*(?:[>❯]\s*)?tell claude what to do\b")
+            .expect("Invalid confirm feedback input regex")
+    });
+
+    pub static CONFIRM_YES_NO: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?i)\[Y/n\]|\(yes/no\)|Allow\?|Do you want to proceed")
+            .expect("Invalid confirm yes/no regex")
+    });
+
+    pub static TOOL_RUNNING_MARKER: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"⏺.*│").expect("Invalid tool running marker regex")
+    });
+
     pub static CONFIRM_NO: Lazy<Regex> = Lazy::new(|| {
         Regex::new(r"(?i)^\s*\d+\.\s+No,?\s").expect("Invalid confirm no regex")
     });
@@ -367,11 +654,14 @@ mod patterns {
 }
 
 /// Create the default Claude Code fingerprints
+///
+/// Most apply to every Claude Code version; the status bar has one
+/// fingerprint per layout (before 2.0 and from 2.0 on).
 pub fn claude_code_fingerprints() -> Vec<Fingerprint> {
     vec![
         // ========== Spinners ==========
         Fingerprint {
-            id: "claude-code.spinner.status",
+            id: "claude-code.spinner.status".into(),
             fingerprint_type: FingerprintType::Enum,
             category: FingerprintCategory::Spinner,
             pattern: FingerprintPattern::Enum(vec![
@@ -379,10 +669,12 @@ pub fn claude_code_fingerprints() -> Vec<Fingerprint> {
             ]),
             confidence: 0.95,
             priority: 100,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![],
         },
         Fingerprint {
-            id: "claude-code.spinner.braille",
+            id: "claude-code.spinner.braille".into(),
             fingerprint_type: FingerprintType::Enum,
             category: FingerprintCategory::Spinner,
             pattern: FingerprintPattern::Enum(vec![
@@ -391,117 +683,189 @@ pub fn claude_code_fingerprints() -> Vec<Fingerprint> {
             ]),
             confidence: 0.95,
             priority: 100,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![],
         },
 
         // ========== Status Bar ==========
         Fingerprint {
-            id: "claude-code.statusbar.pattern",
+            id: "claude-code.statusbar.pattern".into(),
             fingerprint_type: FingerprintType::Regex,
             category: FingerprintCategory::Statusbar,
             pattern: FingerprintPattern::Regex(patterns::STATUSBAR_PATTERN.clone()),
             confidence: 0.95,
             priority: 95,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::before(ClaudeCodeVersion::new(2, 0, 0)),
+            state_weights: vec![],
+        },
+        Fingerprint {
+            id: "claude-code.statusbar.details".into(),
+            fingerprint_type: FingerprintType::Regex,
+            category: FingerprintCategory::Statusbar,
+            pattern: FingerprintPattern::Regex(patterns::STATUSBAR_DETAILS_PATTERN.clone()),
+            confidence: 0.95,
+            priority: 95,
+            source: "claude-code-v2.0".into(),
+            versions: VersionRange::at_least(ClaudeCodeVersion::new(2, 0, 0)),
+            state_weights: vec![],
         },
         Fingerprint {
-            id: "claude-code.statusbar.running",
+            id: "claude-code.statusbar.running".into(),
             fingerprint_type: FingerprintType::String,
             category: FingerprintCategory::Statusbar,
             pattern: FingerprintPattern::String("esc to interrupt".into()),
             confidence: 0.90,
             priority: 90,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![
+                (State::Thinking, 1.0),
+                (State::ToolRunning, 0.78),
+                (State::Idle, -1.0),
+            ],
         },
 
         // ========== Prompts ==========
         Fingerprint {
-            id: "claude-code.prompt.input",
+            id: "claude-code.prompt.input".into(),
             fingerprint_type: FingerprintType::Regex,
             category: FingerprintCategory::Prompt,
-            pattern: FingerprintPattern::Regex(patterns::PROMPT_INPUT.clone()),
+            pattern: FingerprintPattern::Bottom {
+                lines: PROMPT_BOTTOM_LINES,
+                pattern: Box::new(FingerprintPattern::Regex(patterns::PROMPT_INPUT.clone())),
+            },
             confidence: 0.90,
             priority: 90,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![(State::Idle, 1.0)],
         },
         Fingerprint {
-            id: "claude-code.prompt.with-text",
+            id: "claude-code.prompt.with-text".into(),
             fingerprint_type: FingerprintType::Regex,
             category: FingerprintCategory::Prompt,
-            pattern: FingerprintPattern::Regex(patterns::PROMPT_WITH_TEXT.clone()),
+            // Not the ❯ selection cursor of a numbered dialog option
+            pattern: FingerprintPattern::Bottom {
+                lines: PROMPT_BOTTOM_LINES,
+                pattern: Box::new(FingerprintPattern::Unless {
+                    pattern: Box::new(FingerprintPattern::Regex(patterns::PROMPT_WITH_TEXT.clone())),
+                    unless: Box::new(FingerprintPattern::Regex(patterns::SELECTED_OPTION.clone())),
+                    within: 0,
+                }),
+            },
             confidence: 0.85,
             priority: 85,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![(State::Idle, 1.0)],
         },
 
         // ========== Markers ==========
         Fingerprint {
-            id: "claude-code.marker.response",
+            id: "claude-code.marker.response".into(),
             fingerprint_type: FingerprintType::String,
             category: FingerprintCategory::Assistant,
             pattern: FingerprintPattern::String("⏺".into()),
             confidence: 0.95,
             priority: 90,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![],
         },
         Fingerprint {
-            id: "claude-code.marker.separator",
+            id: "claude-code.marker.separator".into(),
             fingerprint_type: FingerprintType::Regex,
             category: FingerprintCategory::Separator,
             pattern: FingerprintPattern::Regex(patterns::SEPARATOR.clone()),
             confidence: 0.90,
             priority: 80,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![],
         },
 
         // ========== Tool Output ==========
         Fingerprint {
-            id: "claude-code.tool.header",
+            id: "claude-code.tool.header".into(),
             fingerprint_type: FingerprintType::Regex,
             category: FingerprintCategory::Tool,
             pattern: FingerprintPattern::Regex(patterns::TOOL_HEADER.clone()),
             confidence: 0.95,
             priority: 92,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![],
         },
         Fingerprint {
-            id: "claude-code.tool.inline-header",
+            id: "claude-code.tool.inline-header".into(),
             fingerprint_type: FingerprintType::Regex,
             category: FingerprintCategory::Tool,
             pattern: FingerprintPattern::Regex(patterns::TOOL_INLINE_HEADER.clone()),
             confidence: 0.90,
             priority: 92,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![],
         },
         Fingerprint {
-            id: "claude-code.tool.param",
+            id: "claude-code.tool.param".into(),
             fingerprint_type: FingerprintType::Regex,
             category: FingerprintCategory::Tool,
             pattern: FingerprintPattern::Regex(patterns::TOOL_PARAM.clone()),
             confidence: 0.90,
             priority: 90,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![(State::ToolRunning, 0.55), (State::Thinking, -1.0)],
         },
         Fingerprint {
-            id: "claude-code.tool.output-line",
+            id: "claude-code.tool.output-line".into(),
             fingerprint_type: FingerprintType::Regex,
             category: FingerprintCategory::Tool,
             pattern: FingerprintPattern::Regex(patterns::TOOL_OUTPUT_LINE.clone()),
             confidence: 0.85,
             priority: 85,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![],
         },
         Fingerprint {
-            id: "claude-code.tool.inline-output-line",
+            id: "claude-code.tool.inline-output-line".into(),
             fingerprint_type: FingerprintType::Regex,
             category: FingerprintCategory::Tool,
             pattern: FingerprintPattern::Regex(patterns::TOOL_INLINE_OUTPUT.clone()),
             confidence: 0.85,
             priority: 85,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![],
         },
         Fingerprint {
-            id: "claude-code.tool.known-names",
+            id: "claude-code.tool.status-label".into(),
+            fingerprint_type: FingerprintType::String,
+            category: FingerprintCategory::Tool,
+            pattern: FingerprintPattern::String("Tool:".into()),
+            confidence: 0.90,
+            priority: 88,
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![(State::ToolRunning, 0.55), (State::Thinking, -1.0)],
+        },
+        Fingerprint {
+            id: "claude-code.tool.running-marker".into(),
+            fingerprint_type: FingerprintType::Regex,
+            category: FingerprintCategory::Tool,
+            pattern: FingerprintPattern::Regex(patterns::TOOL_RUNNING_MARKER.clone()),
+            confidence: 0.90,
+            priority: 88,
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![(State::ToolRunning, 0.55), (State::Thinking, -1.0)],
+        },
+        Fingerprint {
+            id: "claude-code.tool.known-names".into(),
             fingerprint_type: FingerprintType::Enum,
             category: FingerprintCategory::Tool,
             pattern: FingerprintPattern::Enum(vec![
@@ -512,71 +876,213 @@ pub fn claude_code_fingerprints() -> Vec<Fingerprint> {
             ]),
             confidence: 0.95,
             priority: 92,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![],
         },
 
         // ========== Confirm Dialog ==========
         Fingerprint {
-            id: "claude-code.confirm.numbered-option",
+            id: "claude-code.confirm.numbered-option".into(),
             fingerprint_type: FingerprintType::Regex,
             category: FingerprintCategory::Confirm,
             pattern: FingerprintPattern::Regex(patterns::CONFIRM_NUMBERED.clone()),
             confidence: 0.85,
             priority: 85,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![],
         },
         Fingerprint {
-            id: "claude-code.confirm.yes-option",
+            id: "claude-code.confirm.yes-option".into(),
             fingerprint_type: FingerprintType::Regex,
             category: FingerprintCategory::Confirm,
             pattern: FingerprintPattern::Regex(patterns::CONFIRM_YES.clone()),
             confidence: 0.90,
             priority: 88,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![],
         },
         Fingerprint {
-            id: "claude-code.confirm.no-option",
+            id: "claude-code.confirm.no-option".into(),
             fingerprint_type: FingerprintType::Regex,
             category: FingerprintCategory::Confirm,
             pattern: FingerprintPattern::Regex(patterns::CONFIRM_NO.clone()),
             confidence: 0.90,
             priority: 88,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![],
+        },
+
+        Fingerprint {
+            id: "claude-code.confirm.first-option".into(),
+            fingerprint_type: FingerprintType::Regex,
+            category: FingerprintCategory::Confirm,
+            pattern: FingerprintPattern::Regex(patterns::CONFIRM_FIRST_OPTION.clone()),
+            confidence: 0.85,
+            priority: 90,
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![],
+        },
+        Fingerprint {
+            id: "claude-code.confirm.options-dialog".into(),
+            fingerprint_type: FingerprintType::Sequence,
+            category: FingerprintCategory::Confirm,
+            // "1. Yes" option followed by the dialog footer, or by the
+            // "No, and tell Claude ... (esc)" option of dialogs without one
+            pattern: FingerprintPattern::Sequence {
+                steps: vec![
+                    FingerprintPattern::Regex(patterns::CONFIRM_FIRST_OPTION.clone()),
+                    FingerprintPattern::Regex(patterns::CONFIRM_DIALOG_END.clone()),
+                ],
+                max_gap: 12,
+            },
+            confidence: 0.95,
+            priority: 95,
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![(State::Confirming, 1.0)],
+        },
+        Fingerprint {
+            id: "claude-code.confirm.esc-to-cancel".into(),
+            fingerprint_type: FingerprintType::String,
+            category: FingerprintCategory::Confirm,
+            pattern: FingerprintPattern::String("Esc to cancel".into()),
+            confidence: 0.90,
+            priority: 90,
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![
+                (State::Idle, -1.0),
+                (State::Thinking, -1.0),
+                (State::ToolRunning, -1.0),
+            ],
+        },
+        Fingerprint {
+            id: "claude-code.confirm.yes-no".into(),
+            fingerprint_type: FingerprintType::Regex,
+            category: FingerprintCategory::Confirm,
+            pattern: FingerprintPattern::Regex(patterns::CONFIRM_YES_NO.clone()),
+            confidence: 0.95,
+            priority: 90,
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![
+                (State::Confirming, 1.0),
+                (State::Idle, -1.0),
+                (State::Thinking, -1.0),
+                (State::ToolRunning, -1.0),
+            ],
+        },
+        Fingerprint {
+            id: "claude-code.confirm.feedback-input".into(),
+            fingerprint_type: FingerprintType::Regex,
+            category: FingerprintCategory::Confirm,
+            // Inline "Tell Claude what to do differently" field opened by the
+            // deny-with-feedback option (not the option line itself)
+            pattern: FingerprintPattern::Regex(patterns::CONFIRM_FEEDBACK_INPUT.clone()),
+            confidence: 0.90,
+            priority: 92,
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![(State::Confirming, 1.0)],
+        },
+        Fingerprint {
+            id: "claude-code.confirm.trust-proceed".into(),
+            fingerprint_type: FingerprintType::String,
+            category: FingerprintCategory::Confirm,
+            pattern: FingerprintPattern::String("Yes, proceed".into()),
+            confidence: 0.90,
+            priority: 92,
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![],
+        },
+        Fingerprint {
+            id: "claude-code.confirm.trust-enter".into(),
+            fingerprint_type: FingerprintType::String,
+            category: FingerprintCategory::Confirm,
+            pattern: FingerprintPattern::String("Enter to confirm".into()),
+            confidence: 0.90,
+            priority: 92,
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![],
+        },
+        Fingerprint {
+            id: "claude-code.confirm.trust-dialog".into(),
+            fingerprint_type: FingerprintType::Sequence,
+            category: FingerprintCategory::Confirm,
+            pattern: FingerprintPattern::Sequence {
+                steps: vec![
+                    FingerprintPattern::String("Yes, proceed".into()),
+                    FingerprintPattern::String("Enter to confirm".into()),
+                ],
+                max_gap: 6,
+            },
+            confidence: 0.95,
+            priority: 95,
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![(State::Starting, 1.0)],
         },
 
         // ========== Error Markers ==========
         Fingerprint {
-            id: "claude-code.error.keywords",
+            id: "claude-code.error.keywords".into(),
             fingerprint_type: FingerprintType::Enum,
             category: FingerprintCategory::Error,
             pattern: FingerprintPattern::Enum(vec![
-                "Error:".into(), "error:".into(), "ERROR:".into(),
-                "✖".into(), "ENOENT".into(), "EPERM".into(), "EACCES".into(),
-                "failed".into(), "Failed".into(),
+                "Error:".into(), "error:".into(), "ERROR:".into(), "✖".into(),
             ]),
             confidence: 0.85,
             priority: 80,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![(State::Error, 0.82)],
         },
+        // Common in ordinary tool output ("3 tests failed"): too weak to
+        // report Error on their own
         Fingerprint {
-            id: "claude-code.error.stack-trace",
+            id: "claude-code.error.weak-keywords".into(),
+            fingerprint_type: FingerprintType::Enum,
+            category: FingerprintCategory::Error,
+            pattern: FingerprintPattern::Enum(vec![
+                "ENOENT".into(), "EPERM".into(), "EACCES".into(),
+                "failed".into(), "Failed".into(),
+            ]),
+            confidence: 0.85,
+            priority: 79,
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![(State::Error, 0.4)],
+        },
+        Fingerprint {
+            id: "claude-code.error.stack-trace".into(),
             fingerprint_type: FingerprintType::Regex,
             category: FingerprintCategory::Error,
             pattern: FingerprintPattern::Regex(patterns::ERROR_STACK_TRACE.clone()),
             confidence: 0.90,
             priority: 82,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![(State::Error, 0.8)],
         },
 
         // ========== Title Patterns ==========
         Fingerprint {
-            id: "claude-code.title.pattern",
+            id: "claude-code.title.pattern".into(),
             fingerprint_type: FingerprintType::Regex,
             category: FingerprintCategory::Statusbar,
             pattern: FingerprintPattern::Regex(patterns::TITLE_PATTERN.clone()),
             confidence: 0.90,
             priority: 85,
-            source: "claude-code-v1.0",
+            source: "claude-code-v1.0".into(),
+            versions: VersionRange::any(),
+            state_weights: vec![],
         },
     ]
 }
@@ -586,10 +1092,16 @@ pub static CLAUDE_CODE_FINGERPRINTS: Lazy<Vec<Fingerprint>> = Lazy::new(claude_c
 
 /// Create a new registry pre-loaded with Claude Code fingerprints
 pub fn default_registry() -> FingerprintRegistry {
+    DEFAULT_REGISTRY.clone()
+}
+
+/// Compiled default registry shared by [`default_registry`] clones
+static DEFAULT_REGISTRY: Lazy<FingerprintRegistry> = Lazy::new(|| {
     let mut registry = FingerprintRegistry::new();
     registry.register_all(claude_code_fingerprints());
+    registry.compiled();
     registry
-}
+});
 
 #[cfg(test)]
 mod tests {
@@ -666,6 +1178,183 @@ mod tests {
         assert!(result.hints.has_tool_output);
     }
 
+    fn versioned(id: &str, pattern: &str, versions: &str) -> Fingerprint {
+        Fingerprint {
+            id: id.into(),
+            fingerprint_type: FingerprintType::String,
+            category: FingerprintCategory::Statusbar,
+            pattern: FingerprintPattern::String(pattern.into()),
+            confidence: 0.9,
+            priority: 90,
+            source: "test".into(),
+            state_weights: vec![],
+            versions: versions.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_version_specific_fingerprints() {
+        let mut registry = FingerprintRegistry::new();
+        registry.register(versioned("v1.interrupt", "esc to interrupt", "<2.0.0"));
+        registry.register(versioned("v2.interrupt", "esc to interrupt", ">=2.0.0"));
+
+        let context = ParserContext::new(vec!["esc to interrupt".to_string()]);
+        let v1 = registry.extract(&context.clone().with_version(ClaudeCodeVersion::new(1, 0, 35)));
+        assert_eq!(v1.version, Some(ClaudeCodeVersion::new(1, 0, 35)));
+        assert!(v1.matches.contains_key("v1.interrupt"));
+        assert!(!v1.matches.contains_key("v2.interrupt"));
+
+        // Unknown version: every fingerprint is active
+        let all = registry.extract(&context);
+        assert_eq!(all.version, None);
+        assert_eq!(all.matches.len(), 2);
+    }
+
+    #[test]
+    fn test_version_resolution_order() {
+        let registry = default_registry().with_version(ClaudeCodeVersion::new(1, 0, 0));
+        let banner = ParserContext::new(vec!["Claude Code v2.0.14".to_string()]);
+        // Pinned version beats the banner, a context hint beats both
+        assert_eq!(registry.resolve_version(&banner), Some(ClaudeCodeVersion::new(1, 0, 0)));
+        let hinted = banner.with_version(ClaudeCodeVersion::new(1, 5, 0));
+        assert_eq!(registry.resolve_version(&hinted), Some(ClaudeCodeVersion::new(1, 5, 0)));
+
+        let registry = default_registry();
+        let banner = ParserContext::new(vec!["Claude Code v2.0.14".to_string()]);
+        assert_eq!(registry.resolve_version(&banner), Some(ClaudeCodeVersion::new(2, 0, 14)));
+    }
+
+    #[test]
+    fn test_builtin_statusbar_by_version() {
+        let registry = default_registry();
+        let status = "✶ Tempering… (esc to interrupt · ctrl+t to show todos · 32s · ↓ 1.2k tokens)";
+
+        let v2 = ParserContext::new(vec!["Claude Code v2.0.14".to_string(), status.to_string()]);
+        let result = registry.extract(&v2);
+        assert_eq!(result.version, Some(ClaudeCodeVersion::new(2, 0, 14)));
+        let details = &result.matches["claude-code.statusbar.details"];
+        assert!(details.matched);
+        assert_eq!(
+            details.captures.as_ref().unwrap()[2],
+            "ctrl+t to show todos · 32s · ↓ 1.2k tokens"
+        );
+        assert!(!result.matches.contains_key("claude-code.statusbar.pattern"));
+
+        let v1 = ParserContext::new(vec![
+            "Claude Code v1.0.88".to_string(),
+            "✻ Thinking… (esc to interrupt · thinking)".to_string(),
+        ]);
+        let result = registry.extract(&v1);
+        assert_eq!(result.version, Some(ClaudeCodeVersion::new(1, 0, 88)));
+        assert!(result.matches["claude-code.statusbar.pattern"].matched);
+        assert!(!result.matches.contains_key("claude-code.statusbar.details"));
+    }
+
+    fn assert_same_result(a: &FingerprintResult, b: &FingerprintResult) {
+        assert_eq!(a.matches, b.matches);
+        assert_eq!(a.version, b.version);
+        assert_eq!(
+            serde_json::to_value(&a.hints).unwrap(),
+            serde_json::to_value(&b.hints).unwrap()
+        );
+        for (category, matches) in &a.categories {
+            let mut ids: Vec<_> = matches.iter().map(|m| &m.fingerprint_id).collect();
+            let mut other: Vec<_> = b.categories[category].iter().map(|m| &m.fingerprint_id).collect();
+            ids.sort();
+            other.sort();
+            assert_eq!(ids, other);
+        }
+        assert_eq!(a.categories.len(), b.categories.len());
+    }
+
+    #[test]
+    fn test_single_pass_matches_naive() {
+        let registry = default_registry();
+        let frames: Vec<Vec<&str>> = vec![
+            vec![],
+            vec!["❯ "],
+            vec!["✻ Reading file… (esc to interrupt · thinking)", "❯ hello"],
+            vec!["⏺ Bash(git status)", "  ⎿  On branch main", "  │ command: ls"],
+            vec!["Do you want to proceed?", "❯ 1. Yes", "  2. No", "Esc to cancel"],
+            vec!["Error: boom", "    at foo (bar.js:1:2)", "✖ failed"],
+            vec!["────────", "· ✢ ✳ ✶", "Claude Code v2.0.14"],
+        ];
+        for frame in frames {
+            let context = ParserContext::new(frame.iter().map(|s| s.to_string()).collect());
+            assert_same_result(&registry.extract(&context), &registry.extract_naive(&context));
+
+            let context = context.with_full_content("│ Tool: Read\nEsc to cancel".to_string());
+            assert_same_result(&registry.extract(&context), &registry.extract_naive(&context));
+        }
+    }
+
+    /// Times both matchers on a 5000-line scrollback; numbers are in the `matcher` docs
+    ///
+    /// Run with `cargo test --release -p semantic-terminal -- --ignored --nocapture
+    /// scrollback`.
+    #[test]
+    #[ignore]
+    fn test_single_pass_is_faster_on_scrollback() {
+        let registry = default_registry();
+        let frame = [
+            "⏺ Bash(cargo test --workspace)",
+            "  ⎿  running 190 tests",
+            "     test result: ok. 190 passed; 0 failed",
+            "⏺ The tests pass. Next I'll update the README.",
+            "⏺ Update(README.md)",
+            "  ⎿  Updated README.md with 4 additions and 1 removal",
+            "",
+            "────────────────────────────────────────",
+        ];
+        let lines: Vec<String> = frame.iter().cycle().take(5000).map(|s| s.to_string()).collect();
+        let context = ParserContext::new(lines);
+        assert_same_result(&registry.extract(&context), &registry.extract_naive(&context));
+
+        let time = |extract: &dyn Fn() -> FingerprintResult| {
+            let start = std::time::Instant::now();
+            for _ in 0..20 {
+                std::hint::black_box(extract());
+            }
+            start.elapsed() / 20
+        };
+        let naive = time(&|| registry.extract_naive(&context));
+        let compiled = time(&|| registry.extract(&context));
+        println!("5000 lines: naive {naive:?}, compiled {compiled:?}");
+        assert!(compiled < naive);
+    }
+
+    #[test]
+    fn test_oversized_regex_set_falls_back() {
+        // Each regex compiles alone, but together they exceed the set size limit
+        assert!(regex::RegexSet::new([r"\w{40}\d{40}"; 40]).is_err());
+        let big = Regex::new(r"\w{40}\d{40}").unwrap();
+        let mut registry = default_registry();
+        for i in 0..40 {
+            let mut fp = versioned(&format!("custom.big.{i}"), "", "*");
+            fp.fingerprint_type = FingerprintType::Regex;
+            fp.pattern = FingerprintPattern::Regex(big.clone());
+            registry.register(fp);
+        }
+        let line = format!("{}{}", "a".repeat(40), "1".repeat(40));
+        let context = ParserContext::new(vec![line, "esc to interrupt".to_string()]);
+        let result = registry.extract(&context);
+        assert!(result.matches["custom.big.0"].matched);
+        assert_same_result(&result, &registry.extract_naive(&context));
+    }
+
+    #[test]
+    fn test_compiled_matcher_rebuilds_after_change() {
+        let mut registry = default_registry();
+        let context = ParserContext::new(vec!["Compacting conversation".to_string()]);
+        assert!(!registry.extract(&context).matches.contains_key("custom.compacting"));
+
+        registry.register(versioned("custom.compacting", "Compacting", "*"));
+        assert!(registry.extract(&context).matches["custom.compacting"].matched);
+
+        registry.unregister("custom.compacting");
+        assert!(!registry.extract(&context).matches.contains_key("custom.compacting"));
+    }
+
     #[test]
     fn test_fingerprint_hints_default() {
         let hints = FingerprintHints::default();
@@ -675,4 +1364,84 @@ mod tests {
         assert!(!hints.has_confirm_dialog);
         assert!(!hints.has_error);
     }
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_sequence_pattern() {
+        let pattern = FingerprintPattern::Sequence {
+            steps: vec![
+                FingerprintPattern::Regex(Regex::new(r"^⏺ (\w+)").unwrap()),
+                FingerprintPattern::String("⎿".into()),
+            ],
+            max_gap: 1,
+        };
+
+        let frame = Lines::from(lines(&["text", "⏺ Bash(ls)", "", "  ⎿  a.txt"]));
+        assert_eq!(
+            pattern.find_in(&frame, 0..frame.len()),
+            Some((1, Some(vec!["Bash".to_string()])))
+        );
+
+        // Gap too large
+        let frame = Lines::from(lines(&["⏺ Bash(ls)", "", "", "  ⎿  a.txt"]));
+        assert_eq!(pattern.find_in(&frame, 0..frame.len()), None);
+
+        // A later header can still start the sequence
+        let frame = Lines::from(lines(&["⏺ Read(a)", "", "", "⏺ Bash(ls)", "  ⎿  a.txt"]));
+        assert_eq!(pattern.find_in(&frame, 0..frame.len()).unwrap().0, 3);
+    }
+
+    #[test]
+    fn test_bottom_pattern() {
+        let registry = default_registry();
+        let mut frame = vec!["❯ "];
+        frame.extend(["output"; 10]);
+        let result = registry.extract(&ParserContext::new(lines(&frame)));
+        assert!(!result.matches["claude-code.prompt.input"].matched);
+
+        frame.push("❯ ");
+        let result = registry.extract(&ParserContext::new(lines(&frame)));
+        let m = &result.matches["claude-code.prompt.input"];
+        assert!(m.matched);
+        assert_eq!(m.line_index, Some(11));
+    }
+
+    #[test]
+    fn test_unless_pattern() {
+        let registry = default_registry();
+        // The ❯ selection cursor is not a prompt with text
+        let dialog = ParserContext::new(lines(&["Do you want to proceed?", "❯ 1. Yes", "  2. No"]));
+        assert!(!registry.extract(&dialog).matches["claude-code.prompt.with-text"].matched);
+
+        let prompt = ParserContext::new(lines(&["❯ fix the tests"]));
+        assert!(registry.extract(&prompt).matches["claude-code.prompt.with-text"].matched);
+
+        let pattern = FingerprintPattern::Unless {
+            pattern: Box::new(FingerprintPattern::String("❯".into())),
+            unless: Box::new(FingerprintPattern::String("⎿".into())),
+            within: 1,
+        };
+        let frame = Lines::from(lines(&["  ⎿  output", "❯ inside", "", "", "❯ outside"]));
+        assert_eq!(pattern.find_in(&frame, 0..frame.len()), Some((4, None)));
+    }
+
+    #[test]
+    fn test_extract_cached_shared_by_default_registries() {
+        let context =
+            ParserContext::new(lines(&["Do you want to proceed?", "❯ 1. Yes", "  2. No"]));
+        let first = default_registry().extract_cached(&context);
+        let second = default_registry().extract_cached(&context);
+        assert!(matches!(second, Cow::Borrowed(_)));
+        assert_eq!(first.matches.len(), second.matches.len());
+
+        // A modified registry does not reuse the cached scan
+        let mut custom = default_registry();
+        custom.unregister("claude-code.confirm.yes-no");
+        let result = custom.extract_cached(&context);
+        assert!(matches!(result, Cow::Owned(_)));
+        assert!(!result.matches.contains_key("claude-code.confirm.yes-no"));
+    }
 }
@@ -0,0 +1,231 @@
+//! Claude Code input actions
+//!
+//! Encodes what an orchestrator types into Claude Code besides confirmation
+//! answers: prompts, interrupts, mode changes, slash commands and exit. Each
+//! action names the states it is meant to be sent in, so callers can check
+//! the detected [`State`] before sending.
+
+use serde::{Deserialize, Serialize};
+
+use super::keys::{Key, KeyEncoder};
+use super::types::State;
+
+/// States with the prompt box focused
+const PROMPT_STATES: &[State] = &[State::Idle, State::Error];
+
+/// States with a running request (status bar shows "esc to interrupt")
+const BUSY_STATES: &[State] = &[State::Thinking, State::ToolRunning];
+
+/// An input action for Claude Code
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", content = "text", rename_all = "snake_case")]
+pub enum InputAction {
+    /// Type a prompt and press Enter (multi-line text is pasted)
+    ///
+    /// Expects `Idle` (or `Error`, which leaves the prompt usable). Text
+    /// starting with `/`, `!` or `#` switches Claude Code's input mode.
+    Submit(String),
+    /// Esc: stop the running request
+    ///
+    /// Expects `Thinking` or `ToolRunning`. In `Confirming`, Esc denies
+    /// the dialog instead; at the prompt it does nothing.
+    Interrupt,
+    /// Ctrl+C twice: quit Claude Code
+    ///
+    /// Expects `Idle` with an empty prompt: the first Ctrl+C shows "Press
+    /// Ctrl-C again to exit" and the second quits. With text typed, the first
+    /// only clears it; while busy it interrupts instead.
+    Exit,
+    /// Shift+Tab: cycle permission modes (default, accept edits, plan)
+    ///
+    /// Expects `Idle` or `Error`.
+    CycleMode,
+    /// Run a slash command, e.g. `compact` or `/model opus`
+    ///
+    /// Expects `Idle` or `Error`. The leading `/` is optional.
+    SlashCommand(String),
+    /// Esc twice: open the message list to rewind the conversation
+    ///
+    /// Expects `Idle` with an empty prompt (Esc-Esc clears typed text first).
+    Rewind,
+}
+
+impl InputAction {
+    /// States the action is meant to be sent in
+    pub fn expected_states(&self) -> &'static [State] {
+        match self {
+            InputAction::Interrupt => BUSY_STATES,
+            InputAction::Rewind | InputAction::Exit => &[State::Idle],
+            InputAction::Submit(_)
+            | InputAction::CycleMode
+            | InputAction::SlashCommand(_) => PROMPT_STATES,
+        }
+    }
+
+    /// Whether the action is meant to be sent in `state`
+    pub fn is_expected_in(&self, state: State) -> bool {
+        self.expected_states().contains(&state)
+    }
+
+    /// Keys that perform the action
+    pub fn keys(&self) -> Vec<Key> {
+        match self {
+            InputAction::Submit(text) => vec![Key::Text(text.clone()), Key::Enter],
+            InputAction::Interrupt => vec![Key::Esc],
+            InputAction::Exit => vec![Key::CtrlC, Key::CtrlC],
+            InputAction::CycleMode => vec![Key::ShiftTab],
+            InputAction::SlashCommand(command) => {
+                let command = command.trim();
+                let command = command.strip_prefix('/').unwrap_or(command);
+                vec![Key::Text(format!("/{}", command)), Key::Enter]
+            }
+            InputAction::Rewind => vec![Key::Esc, Key::Esc],
+        }
+    }
+}
+
+/// Why an input action was not encoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputError {
+    /// The action is not meant for the current state
+    UnexpectedState {
+        /// Current state
+        state: State,
+        /// States the action expects
+        expected: &'static [State],
+    },
+}
+
+impl std::fmt::Display for InputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputError::UnexpectedState { state, expected } => {
+                let expected: Vec<String> = expected.iter().map(|s| s.to_string()).collect();
+                write!(
+                    f,
+                    "input expects {} but state is {}",
+                    expected.join(" or "),
+                    state
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// Encodes input actions for Claude Code
+///
+/// Use [`ClaudeCodeInput::encode_checked`] with the detected state to avoid
+/// keys landing in the wrong place (e.g. Esc denying a confirmation).
+#[derive(Debug, Clone, Default)]
+pub struct ClaudeCodeInput {
+    keys: KeyEncoder,
+}
+
+impl ClaudeCodeInput {
+    /// Create an input encoder for Claude Code's default input modes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Encode for specific input modes (e.g. from `Session::key_encoder`)
+    pub fn with_key_encoder(keys: KeyEncoder) -> Self {
+        Self { keys }
+    }
+
+    /// Encode an action
+    pub fn encode(&self, action: &InputAction) -> String {
+        self.keys.encode_all(&action.keys())
+    }
+
+    /// Encode an action, failing when it is not meant for `state`
+    pub fn encode_checked(&self, action: &InputAction, state: State) -> Result<String, InputError> {
+        if !action.is_expected_in(state) {
+            return Err(InputError::UnexpectedState {
+                state,
+                expected: action.expected_states(),
+            });
+        }
+        Ok(self.encode(action))
+    }
+
+    /// Submit a prompt (see [`InputAction::Submit`])
+    pub fn submit(&self, prompt: impl Into<String>) -> String {
+        self.encode(&InputAction::Submit(prompt.into()))
+    }
+
+    /// Interrupt the running request (see [`InputAction::Interrupt`])
+    pub fn interrupt(&self) -> String {
+        self.encode(&InputAction::Interrupt)
+    }
+
+    /// Quit Claude Code (see [`InputAction::Exit`])
+    pub fn exit(&self) -> String {
+        self.encode(&InputAction::Exit)
+    }
+
+    /// Cycle permission modes (see [`InputAction::CycleMode`])
+    pub fn cycle_mode(&self) -> String {
+        self.encode(&InputAction::CycleMode)
+    }
+
+    /// Run a slash command (see [`InputAction::SlashCommand`])
+    pub fn slash_command(&self, command: impl Into<String>) -> String {
+        self.encode(&InputAction::SlashCommand(command.into()))
+    }
+
+    /// Open the rewind list (see [`InputAction::Rewind`])
+    pub fn rewind(&self) -> String {
+        self.encode(&InputAction::Rewind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InputModes;
+
+    #[test]
+    fn test_encode_actions() {
+        let input = ClaudeCodeInput::new();
+        assert_eq!(input.submit("fix the build"), "fix the build\r");
+        assert_eq!(
+            input.submit("line one\nline two"),
+            "\x1b[200~line one\nline two\x1b[201~\r"
+        );
+        assert_eq!(input.exit(), "\x03\x03");
+        assert_eq!(input.cycle_mode(), "\x1b[Z");
+        assert_eq!(input.slash_command("compact"), "/compact\r");
+        assert_eq!(input.slash_command("/model opus"), "/model opus\r");
+        assert_eq!(input.rewind(), "\x1b\x1b");
+    }
+
+    #[test]
+    fn test_encode_checked() {
+        let input = ClaudeCodeInput::with_key_encoder(KeyEncoder::with_modes(InputModes::reset()));
+        assert_eq!(
+            input.encode_checked(&InputAction::Interrupt, State::Thinking),
+            Ok("\x1b".to_string())
+        );
+        let err = input
+            .encode_checked(&InputAction::Submit("hi".into()), State::Confirming)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            InputError::UnexpectedState {
+                state: State::Confirming,
+                expected: &[State::Idle, State::Error],
+            }
+        );
+        assert!(InputAction::Rewind.is_expected_in(State::Idle));
+        assert!(!InputAction::Rewind.is_expected_in(State::Error));
+        assert_eq!(
+            input.encode_checked(&InputAction::Exit, State::Error),
+            Err(InputError::UnexpectedState {
+                state: State::Error,
+                expected: &[State::Idle],
+            })
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Terminal input encoding
+//!
+//! Turns typed keys into the bytes a terminal would send for them, honoring
+//! the input modes the application set: application cursor keys (DECCKM,
+//! mode 1) and bracketed paste (mode 2004). [`Screen`](super::Screen) tracks
+//! both from the PTY output.
+
+use serde::{Deserialize, Serialize};
+
+/// Bracketed paste markers
+const PASTE_START: &str = "\x1b[200~";
+const PASTE_END: &str = "\x1b[201~";
+
+/// Terminal input modes that change what keys send
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputModes {
+    /// Application cursor keys (DECCKM): arrows send `ESC O A` instead of `ESC [ A`
+    pub application_cursor: bool,
+    /// Bracketed paste: pasted text is wrapped in `ESC [200~` ... `ESC [201~`
+    pub bracketed_paste: bool,
+}
+
+impl Default for InputModes {
+    /// Modes of Claude Code's prompt: bracketed paste on, normal cursor keys
+    fn default() -> Self {
+        Self {
+            application_cursor: false,
+            bracketed_paste: true,
+        }
+    }
+}
+
+impl InputModes {
+    /// Modes of a terminal nothing has changed yet (all off)
+    pub const fn reset() -> Self {
+        Self {
+            application_cursor: false,
+            bracketed_paste: false,
+        }
+    }
+}
+
+/// A key (or text) to send to the terminal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Key {
+    Up,
+    Down,
+    Right,
+    Left,
+    Enter,
+    Esc,
+    Tab,
+    ShiftTab,
+    Backspace,
+    CtrlC,
+    /// Literal text
+    ///
+    /// Control characters are dropped. Multi-line text is sent as a bracketed
+    /// paste when the mode is on, so its newlines don't act as Enter.
+    Text(String),
+}
+
+/// Encodes keys for the current terminal input modes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyEncoder {
+    modes: InputModes,
+}
+
+impl KeyEncoder {
+    /// Create an encoder for Claude Code's default input modes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an encoder for specific input modes (e.g. `Screen::input_modes`)
+    pub fn with_modes(modes: InputModes) -> Self {
+        Self { modes }
+    }
+
+    /// Input modes in use
+    pub fn modes(&self) -> InputModes {
+        self.modes
+    }
+
+    /// Encode a single key
+    pub fn encode(&self, key: &Key) -> String {
+        let arrow = |c: char| {
+            if self.modes.application_cursor {
+                format!("\x1bO{}", c)
+            } else {
+                format!("\x1b[{}", c)
+            }
+        };
+        match key {
+            Key::Up => arrow('A'),
+            Key::Down => arrow('B'),
+            Key::Right => arrow('C'),
+            Key::Left => arrow('D'),
+            Key::Enter => "\r".to_string(),
+            Key::Esc => "\x1b".to_string(),
+            Key::Tab => "\t".to_string(),
+            Key::ShiftTab => "\x1b[Z".to_string(),
+            Key::Backspace => "\x7f".to_string(),
+            Key::CtrlC => "\x03".to_string(),
+            Key::Text(text) => self.encode_text(text),
+        }
+    }
+
+    /// Encode a sequence of keys into one string
+    pub fn encode_all<'a>(&self, keys: impl IntoIterator<Item = &'a Key>) -> String {
+        keys.into_iter().map(|k| self.encode(k)).collect()
+    }
+
+    /// Text with control characters dropped, pasted when it spans lines
+    ///
+    /// Without bracketed paste, newlines are sent as LF (Ctrl+J) rather than
+    /// CR, and tabs as spaces, so neither acts as Enter or completion.
+    fn encode_text(&self, text: &str) -> String {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let clean: String = text
+            .chars()
+            .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+            .collect();
+        if !clean.contains(['\n', '\t']) {
+            clean
+        } else if self.modes.bracketed_paste {
+            format!("{}{}{}", PASTE_START, clean, PASTE_END)
+        } else {
+            clean.replace('\t', " ")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_keys() {
+        let encoder = KeyEncoder::new();
+        assert_eq!(encoder.encode(&Key::Down), "\x1b[B");
+        assert_eq!(encoder.encode(&Key::ShiftTab), "\x1b[Z");
+        assert_eq!(
+            encoder.encode_all(&[Key::Up, Key::Up, Key::Enter]),
+            "\x1b[A\x1b[A\r"
+        );
+
+        let app = KeyEncoder::with_modes(InputModes {
+            application_cursor: true,
+            bracketed_paste: true,
+        });
+        assert_eq!(app.encode(&Key::Down), "\x1bOB");
+        assert_eq!(app.encode(&Key::Enter), "\r");
+    }
+
+    #[test]
+    fn test_encode_text() {
+        let encoder = KeyEncoder::new();
+        // Esc would cancel the dialog, so control characters are dropped
+        assert_eq!(encoder.encode(&Key::Text("ok\x1b\x07".into())), "ok");
+        assert_eq!(
+            encoder.encode(&Key::Text("a\r\nb".into())),
+            "\x1b[200~a\nb\x1b[201~"
+        );
+        // A paste end marker in the text can't end the paste early
+        assert_eq!(
+            encoder.encode(&Key::Text("x\n\x1b[201~y".into())),
+            "\x1b[200~x\n[201~y\x1b[201~"
+        );
+
+        let plain = KeyEncoder::with_modes(InputModes::reset());
+        assert_eq!(plain.encode(&Key::Text("a\nb\tc".into())), "a\nb c");
+    }
+}
@@ -3,22 +3,56 @@
 //! This module provides parsers for detecting terminal states and parsing
 //! confirmation dialogs from Claude Code CLI output.
 
+mod classifier;
 mod confirm;
+mod diff;
 pub mod fingerprint;
+mod input;
+mod keys;
+mod machine;
+mod matcher;
+mod pack;
+mod policy;
+mod risk;
+mod screen;
+mod session;
 mod state;
 mod status;
 mod title;
 mod tool;
+mod tool_call;
+mod transaction;
 mod types;
+mod version;
 
+pub use classifier::{StateClassifier, DEFAULT_MIN_CONFIDENCE};
 pub use confirm::ClaudeCodeConfirmParser;
+pub use diff::{DiffHunk, DiffLine, DiffLineKind, FileDiff};
 pub use fingerprint::{
     claude_code_fingerprints, default_registry, Fingerprint, FingerprintCategory,
     FingerprintHints, FingerprintMatch, FingerprintPattern, FingerprintRegistry,
     FingerprintResult, FingerprintType, CLAUDE_CODE_FINGERPRINTS,
 };
+pub use input::{ClaudeCodeInput, InputAction, InputError};
+pub use keys::{InputModes, Key, KeyEncoder};
+pub use machine::{StateMachine, StateMachineConfig, StateTransition};
+pub use pack::{
+    FingerprintDef, FingerprintPack, FingerprintPackError, FingerprintPackFormat, PackPattern,
+};
+pub use policy::{
+    ConfirmPolicy, ConfirmPolicyError, PolicyDecision, PolicyRuleDef, PolicyVerdict,
+};
+pub use risk::{RiskAssessor, RiskCategory, RiskFlag, RiskLevel, RiskReport};
+pub use screen::{Screen, DEFAULT_SCROLLBACK};
+pub use session::{Session, SessionEvent};
 pub use state::ClaudeCodeStateParser;
 pub use status::{ClaudeCodeStatusParser, SPINNER_CHARS};
 pub use title::{ClaudeCodeTitleParser, ALL_SPINNERS, BRAILLE_SPINNERS, OTHER_SPINNERS};
 pub use tool::{ClaudeCodeToolOutputParser, KNOWN_TOOLS};
+pub use tool_call::ToolCall;
+pub use transaction::{
+    ConfirmEvidence, ConfirmOutcome, ConfirmResolution, ConfirmTransaction,
+    DEFAULT_CONFIRM_TIMEOUT,
+};
 pub use types::*;
+pub use version::{ClaudeCodeVersion, VersionRange};
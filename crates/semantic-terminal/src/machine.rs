@@ -0,0 +1,450 @@
+//! Debounced state machine
+//!
+//! Smooths per-frame state detection with hysteresis, so a partially redrawn
+//! frame cannot flip Thinking→Idle→Thinking and fire "done" handlers.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::state::ClaudeCodeStateParser;
+use super::types::{ParserContext, State, StateDetectionResult, StateParser};
+
+/// Thresholds a detected state must pass before it is reported
+#[derive(Debug, Clone)]
+pub struct StateMachineConfig {
+    /// How long a new state must be seen continuously before switching
+    pub min_dwell: Duration,
+    /// How many consecutive frames must agree before switching
+    pub confirm_frames: u32,
+    /// Detections below this confidence are ignored
+    pub min_confidence: f64,
+    /// Per-target-state overrides for `min_dwell`
+    pub dwell_overrides: HashMap<State, Duration>,
+}
+
+impl Default for StateMachineConfig {
+    fn default() -> Self {
+        Self {
+            min_dwell: Duration::from_millis(150),
+            confirm_frames: 2,
+            min_confidence: 0.0,
+            dwell_overrides: HashMap::new(),
+        }
+    }
+}
+
+impl StateMachineConfig {
+    /// Config that reports every detected change immediately
+    pub fn immediate() -> Self {
+        Self {
+            min_dwell: Duration::ZERO,
+            confirm_frames: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Set the minimum dwell time for all states
+    pub fn with_min_dwell(mut self, dwell: Duration) -> Self {
+        self.min_dwell = dwell;
+        self
+    }
+
+    /// Set the minimum dwell time for switching into `state`
+    pub fn with_min_dwell_for(mut self, state: State, dwell: Duration) -> Self {
+        self.dwell_overrides.insert(state, dwell);
+        self
+    }
+
+    /// Set the number of consecutive agreeing frames
+    pub fn with_confirm_frames(mut self, frames: u32) -> Self {
+        self.confirm_frames = frames.max(1);
+        self
+    }
+
+    /// Set the minimum detection confidence
+    pub fn with_min_confidence(mut self, confidence: f64) -> Self {
+        self.min_confidence = confidence;
+        self
+    }
+
+    fn dwell_for(&self, state: State) -> Duration {
+        self.dwell_overrides
+            .get(&state)
+            .copied()
+            .unwrap_or(self.min_dwell)
+    }
+}
+
+/// A stable state transition
+#[derive(Debug, Clone)]
+pub struct StateTransition {
+    /// Previous stable state (None for the first detection)
+    pub from: Option<State>,
+    /// New stable state
+    pub to: State,
+    /// Whether `from → to` is in the state transition graph
+    pub legal: bool,
+    /// Time spent in the previous state, up to the first frame showing the new one
+    pub time_in_previous: Option<Duration>,
+    /// Detection result that confirmed the new state
+    pub result: StateDetectionResult,
+}
+
+/// State seen on recent frames but not yet confirmed
+#[derive(Debug, Clone)]
+struct Candidate {
+    result: StateDetectionResult,
+    first_seen: Instant,
+    frames: u32,
+}
+
+/// Debounced state machine on top of a state parser
+///
+/// A state is only reported once it has been detected on `confirm_frames`
+/// consecutive frames spanning at least `min_dwell`. The first detection is
+/// reported immediately, since there is nothing to debounce against.
+/// Transitions outside [`State::successors`] are still reported, flagged
+/// with `legal: false`. Durations count a confirmed state from the first
+/// frame it was seen on, so the debounce delay isn't added to the previous
+/// state.
+pub struct StateMachine {
+    parser: ClaudeCodeStateParser,
+    config: StateMachineConfig,
+    current: Option<State>,
+    entered_at: Option<Instant>,
+    candidate: Option<Candidate>,
+    durations: HashMap<State, Duration>,
+}
+
+impl Default for StateMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateMachine {
+    /// Create a state machine with the default thresholds
+    pub fn new() -> Self {
+        Self::with_config(StateMachineConfig::default())
+    }
+
+    /// Create a state machine with custom thresholds
+    pub fn with_config(config: StateMachineConfig) -> Self {
+        Self {
+            parser: ClaudeCodeStateParser::new(),
+            config,
+            current: None,
+            entered_at: None,
+            candidate: None,
+            durations: HashMap::new(),
+        }
+    }
+
+    /// Get the thresholds
+    pub fn config(&self) -> &StateMachineConfig {
+        &self.config
+    }
+
+    /// Current stable state
+    pub fn state(&self) -> Option<State> {
+        self.current
+    }
+
+    /// State seen on recent frames that has not passed the thresholds yet
+    pub fn pending(&self) -> Option<State> {
+        self.candidate.as_ref().map(|c| c.result.state)
+    }
+
+    /// Detect state in a frame and return the transition it confirmed (if any)
+    pub fn update(&mut self, context: &ParserContext) -> Option<StateTransition> {
+        self.update_at(context, Instant::now())
+    }
+
+    /// Detect state in a frame taken at `now`
+    pub fn update_at(&mut self, context: &ParserContext, now: Instant) -> Option<StateTransition> {
+        let result = match (context.current_state, self.current) {
+            (None, Some(state)) => self.parser.detect_state(&context.clone().with_state(state)),
+            _ => self.parser.detect_state(context),
+        };
+        self.observe(result, now)
+    }
+
+    /// Feed a detection result from any state parser
+    ///
+    /// `None` (nothing detected) breaks the run of agreeing frames.
+    pub fn observe(
+        &mut self,
+        result: Option<StateDetectionResult>,
+        now: Instant,
+    ) -> Option<StateTransition> {
+        let Some(result) = result.filter(|r| r.confidence >= self.config.min_confidence) else {
+            self.candidate = None;
+            return None;
+        };
+
+        if self.current == Some(result.state) {
+            self.candidate = None;
+            return None;
+        }
+
+        if self.current.is_none() {
+            return Some(self.commit(result, now));
+        }
+
+        let candidate = match self.candidate.take() {
+            Some(mut c) if c.result.state == result.state => {
+                c.frames += 1;
+                c.result = result;
+                c
+            }
+            _ => Candidate {
+                result,
+                first_seen: now,
+                frames: 1,
+            },
+        };
+
+        let dwell = now.saturating_duration_since(candidate.first_seen);
+        if candidate.frames >= self.config.confirm_frames
+            && dwell >= self.config.dwell_for(candidate.result.state)
+        {
+            return Some(self.commit(candidate.result, candidate.first_seen));
+        }
+
+        self.candidate = Some(candidate);
+        None
+    }
+
+    /// Time spent in `state` so far, including the ongoing visit
+    pub fn time_in_state(&self, state: State, now: Instant) -> Duration {
+        let mut total = self.durations.get(&state).copied().unwrap_or_default();
+        if let (Some(current), Some(entered)) = (self.current, self.entered_at) {
+            if current == state {
+                total += now.saturating_duration_since(entered);
+            }
+        }
+        total
+    }
+
+    /// Time spent in every visited state, including the ongoing visit
+    pub fn durations(&self, now: Instant) -> HashMap<State, Duration> {
+        State::ALL
+            .iter()
+            .map(|&s| (s, self.time_in_state(s, now)))
+            .filter(|(_, d)| !d.is_zero())
+            .collect()
+    }
+
+    /// Forget the current state and all accumulated durations
+    pub fn reset(&mut self) {
+        self.current = None;
+        self.entered_at = None;
+        self.candidate = None;
+        self.durations.clear();
+    }
+
+    /// Switch to `result`'s state, entered at `since`
+    fn commit(&mut self, result: StateDetectionResult, since: Instant) -> StateTransition {
+        let from = self.current;
+        let time_in_previous = match (from, self.entered_at) {
+            (Some(prev), Some(entered)) => {
+                let spent = since.saturating_duration_since(entered);
+                *self.durations.entry(prev).or_default() += spent;
+                Some(spent)
+            }
+            _ => None,
+        };
+
+        self.current = Some(result.state);
+        self.entered_at = Some(since);
+        self.candidate = None;
+
+        StateTransition {
+            from,
+            to: result.state,
+            legal: from.is_none_or(|f| f.can_transition_to(result.state)),
+            time_in_previous,
+            result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_context(lines: &[&str]) -> ParserContext<'static> {
+        ParserContext::new(lines.iter().map(|s| s.to_string()).collect())
+    }
+
+    fn idle() -> ParserContext<'static> {
+        make_context(&["❯ "])
+    }
+
+    fn thinking() -> ParserContext<'static> {
+        make_context(&["· Pondering… (esc to interrupt)"])
+    }
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn test_transition_graph() {
+        assert!(State::Starting.can_transition_to(State::Idle));
+        assert!(State::Idle.can_transition_to(State::Thinking));
+        assert!(State::Thinking.can_transition_to(State::Confirming));
+        assert!(State::Confirming.can_transition_to(State::ToolRunning));
+        assert!(!State::Idle.can_transition_to(State::Starting));
+        assert!(State::Starting.can_transition_to(State::Thinking));
+        assert!(!State::Idle.can_transition_to(State::Idle));
+    }
+
+    #[test]
+    fn test_first_detection_is_immediate() {
+        let mut machine = StateMachine::new();
+        let t0 = Instant::now();
+
+        let transition = machine.update_at(&idle(), t0).unwrap();
+        assert_eq!(transition.from, None);
+        assert_eq!(transition.to, State::Idle);
+        assert!(transition.legal);
+        assert_eq!(machine.state(), Some(State::Idle));
+    }
+
+    #[test]
+    fn test_flicker_is_suppressed() {
+        let mut machine = StateMachine::new();
+        let t0 = Instant::now();
+
+        machine.update_at(&thinking(), t0);
+        // A single partially redrawn frame looks idle
+        assert!(machine.update_at(&idle(), t0 + ms(10)).is_none());
+        assert_eq!(machine.pending(), Some(State::Idle));
+        assert!(machine.update_at(&thinking(), t0 + ms(20)).is_none());
+        assert_eq!(machine.pending(), None);
+        assert_eq!(machine.state(), Some(State::Thinking));
+    }
+
+    #[test]
+    fn test_stable_change_requires_frames_and_dwell() {
+        let mut machine = StateMachine::new();
+        let t0 = Instant::now();
+        machine.update_at(&thinking(), t0);
+
+        assert!(machine.update_at(&idle(), t0 + ms(100)).is_none());
+        // Two frames, but only 50ms apart
+        assert!(machine.update_at(&idle(), t0 + ms(150)).is_none());
+
+        let transition = machine.update_at(&idle(), t0 + ms(260)).unwrap();
+        assert_eq!(transition.from, Some(State::Thinking));
+        assert_eq!(transition.to, State::Idle);
+        // Idle began with its first frame, not when the debounce confirmed it
+        assert_eq!(transition.time_in_previous, Some(ms(100)));
+        assert_eq!(machine.time_in_state(State::Thinking, t0 + ms(300)), ms(100));
+        assert_eq!(machine.time_in_state(State::Idle, t0 + ms(300)), ms(200));
+    }
+
+    #[test]
+    fn test_dwell_override() {
+        let config = StateMachineConfig::immediate().with_min_dwell_for(State::Idle, ms(500));
+        let mut machine = StateMachine::with_config(config);
+        let t0 = Instant::now();
+        machine.update_at(&idle(), t0);
+
+        // Leaving Idle is immediate
+        assert!(machine.update_at(&thinking(), t0 + ms(10)).is_some());
+        // Returning to Idle waits for the override
+        assert!(machine.update_at(&idle(), t0 + ms(20)).is_none());
+        assert!(machine.update_at(&idle(), t0 + ms(400)).is_none());
+        assert!(machine.update_at(&idle(), t0 + ms(520)).is_some());
+    }
+
+    #[test]
+    fn test_nothing_detected_breaks_run() {
+        let mut machine =
+            StateMachine::with_config(StateMachineConfig::default().with_confirm_frames(2));
+        let t0 = Instant::now();
+        machine.update_at(&thinking(), t0);
+
+        machine.update_at(&idle(), t0 + ms(200));
+        machine.update_at(&make_context(&["random text"]), t0 + ms(300));
+        // Run restarts here
+        assert!(machine.update_at(&idle(), t0 + ms(400)).is_none());
+        assert!(machine.update_at(&idle(), t0 + ms(600)).is_some());
+    }
+
+    #[test]
+    fn test_launch_with_prompt() {
+        // `claude "prompt"` starts a turn without showing the idle prompt
+        let mut machine = StateMachine::with_config(StateMachineConfig::immediate());
+        let t0 = Instant::now();
+        machine.observe(Some(StateDetectionResult::new(State::Starting, 0.9)), t0);
+
+        let transition = machine.update_at(&thinking(), t0 + ms(5)).unwrap();
+        assert_eq!(transition.from, Some(State::Starting));
+        assert_eq!(transition.to, State::Thinking);
+        assert!(transition.legal);
+
+        let mut machine = StateMachine::with_config(StateMachineConfig::immediate());
+        machine.observe(Some(StateDetectionResult::new(State::Starting, 0.9)), t0);
+        let transition = machine
+            .observe(
+                Some(StateDetectionResult::new(State::ToolRunning, 0.9)),
+                t0 + ms(5),
+            )
+            .unwrap();
+        assert!(transition.legal);
+    }
+
+    #[test]
+    fn test_illegal_transition_is_flagged() {
+        let mut machine = StateMachine::with_config(StateMachineConfig::immediate());
+        let t0 = Instant::now();
+        machine.observe(Some(StateDetectionResult::new(State::Idle, 0.9)), t0);
+
+        let transition = machine
+            .observe(
+                Some(StateDetectionResult::new(State::Starting, 0.9)),
+                t0 + ms(5),
+            )
+            .unwrap();
+        assert_eq!(transition.to, State::Starting);
+        assert!(!transition.legal);
+    }
+
+    #[test]
+    fn test_min_confidence() {
+        let config = StateMachineConfig::immediate().with_min_confidence(0.8);
+        let mut machine = StateMachine::with_config(config);
+        let t0 = Instant::now();
+
+        assert!(machine
+            .observe(Some(StateDetectionResult::new(State::Error, 0.7)), t0)
+            .is_none());
+        assert_eq!(machine.state(), None);
+    }
+
+    #[test]
+    fn test_time_in_state() {
+        let mut machine = StateMachine::with_config(StateMachineConfig::immediate());
+        let t0 = Instant::now();
+
+        machine.update_at(&idle(), t0);
+        machine.update_at(&thinking(), t0 + ms(100));
+        machine.update_at(&idle(), t0 + ms(400));
+        machine.update_at(&thinking(), t0 + ms(450));
+
+        let now = t0 + ms(500);
+        assert_eq!(machine.time_in_state(State::Idle, now), ms(150));
+        assert_eq!(machine.time_in_state(State::Thinking, now), ms(350));
+        assert_eq!(machine.time_in_state(State::Error, now), Duration::ZERO);
+
+        let durations = machine.durations(now);
+        assert_eq!(durations.len(), 2);
+
+        machine.reset();
+        assert_eq!(machine.state(), None);
+        assert!(machine.durations(now).is_empty());
+    }
+}
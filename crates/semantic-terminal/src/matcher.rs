@@ -0,0 +1,293 @@
+//! Single-pass fingerprint matcher
+//!
+//! Compiles every fingerprint of a registry into one `RegexSet` (regex
+//! fingerprints) and one Aho-Corasick automaton (string and enum literals),
+//! so each line is scanned once per frame instead of once per pattern.
+//! Multi-line and positional patterns (sequences, bottom anchoring,
+//! exclusions) are evaluated separately after the line scan.
+//!
+//! If a combined automaton fails to build (e.g. the regex set exceeds the
+//! compiled size limit), its patterns are matched one at a time instead.
+//! Regexes with word boundaries (`\b`, `\B`) are always matched one at a
+//! time: a single Unicode word boundary keeps the whole set off the lazy DFA.
+//!
+//! On a 5000-line scrollback with the built-in fingerprints (release build,
+//! `test_single_pass_is_faster_on_scrollback`), a scan takes about 3 ms
+//! against about 10 ms matching one fingerprint at a time.
+
+use aho_corasick::AhoCorasick;
+use regex::RegexSet;
+use std::collections::HashMap;
+
+use super::fingerprint::{Fingerprint, FingerprintMatch, FingerprintPattern};
+use super::{ClaudeCodeVersion, ParserContext, VersionRange};
+
+/// Where a literal comes from: fingerprint index and position in its enum list
+#[derive(Debug, Clone, Copy)]
+struct LiteralOwner {
+    fingerprint: usize,
+    order: usize,
+}
+
+/// Fingerprints compiled into combined automata
+#[derive(Debug, Clone)]
+pub(crate) struct CompiledMatcher {
+    /// Fingerprint IDs in compile order
+    ids: Vec<String>,
+    /// Patterns, for captures after a combined match
+    patterns: Vec<FingerprintPattern>,
+    /// Version ranges, to select active fingerprints
+    versions: Vec<VersionRange>,
+    /// Regex fingerprints, indexed like `regex_owners` (`None` = match one at a time)
+    regex_set: Option<RegexSet>,
+    regex_owners: Vec<usize>,
+    /// Regex fingerprints kept out of the set, matched one at a time
+    single_regexes: Vec<usize>,
+    /// Unique string/enum literals, indexed like `literal_owners`
+    literal_patterns: Vec<String>,
+    /// Automaton over `literal_patterns` (`None` = match one at a time)
+    literals: Option<AhoCorasick>,
+    literal_owners: Vec<Vec<LiteralOwner>>,
+    /// Fingerprints of `String` type, which also match against full content
+    string_fingerprints: Vec<bool>,
+    /// Multi-line/positional fingerprints, evaluated one at a time
+    composite: Vec<usize>,
+}
+
+impl CompiledMatcher {
+    /// Compile fingerprints (in the order they should be reported)
+    pub(crate) fn new<'a>(fingerprints: impl IntoIterator<Item = &'a Fingerprint>) -> Self {
+        let mut ids = Vec::new();
+        let mut patterns = Vec::new();
+        let mut versions = Vec::new();
+        let mut regexes = Vec::new();
+        let mut regex_owners = Vec::new();
+        let mut single_regexes = Vec::new();
+        let mut literal_index: HashMap<String, usize> = HashMap::new();
+        let mut literal_patterns: Vec<String> = Vec::new();
+        let mut literal_owners: Vec<Vec<LiteralOwner>> = Vec::new();
+        let mut string_fingerprints = Vec::new();
+        let mut composite = Vec::new();
+
+        let mut add_literal = |literal: &str, owner: LiteralOwner| {
+            let index = *literal_index.entry(literal.to_string()).or_insert_with(|| {
+                literal_patterns.push(literal.to_string());
+                literal_owners.push(Vec::new());
+                literal_patterns.len() - 1
+            });
+            literal_owners[index].push(owner);
+        };
+
+        for (fingerprint, fp) in fingerprints.into_iter().enumerate() {
+            ids.push(fp.id.clone());
+            patterns.push(fp.pattern.clone());
+            versions.push(fp.versions);
+            string_fingerprints.push(matches!(fp.pattern, FingerprintPattern::String(_)));
+            match &fp.pattern {
+                FingerprintPattern::Regex(re) if has_word_boundary(re.as_str()) => {
+                    single_regexes.push(fingerprint);
+                }
+                FingerprintPattern::Regex(re) => {
+                    regexes.push(re.as_str().to_string());
+                    regex_owners.push(fingerprint);
+                }
+                FingerprintPattern::String(s) => {
+                    add_literal(
+                        s,
+                        LiteralOwner {
+                            fingerprint,
+                            order: 0,
+                        },
+                    );
+                }
+                FingerprintPattern::Enum(patterns) => {
+                    for (order, p) in patterns.iter().enumerate() {
+                        add_literal(p, LiteralOwner { fingerprint, order });
+                    }
+                }
+                _ => composite.push(fingerprint),
+            }
+        }
+
+        Self {
+            ids,
+            patterns,
+            versions,
+            regex_set: RegexSet::new(&regexes).ok(),
+            regex_owners,
+            single_regexes,
+            literals: AhoCorasick::new(&literal_patterns).ok(),
+            literal_patterns,
+            literal_owners,
+            string_fingerprints,
+            composite,
+        }
+    }
+
+    /// Match the fingerprints active for `version` (all when unknown)
+    pub(crate) fn extract(
+        &self,
+        context: &ParserContext,
+        version: Option<ClaudeCodeVersion>,
+    ) -> Vec<FingerprintMatch> {
+        let active: Vec<bool> = self
+            .versions
+            .iter()
+            .map(|range| version.is_none_or(|v| range.contains(v)))
+            .collect();
+        let mut found: Vec<Option<FingerprintMatch>> = vec![None; self.ids.len()];
+        // Best enum position seen on the current line, per fingerprint
+        let mut line_hits: Vec<Option<usize>> = vec![None; self.ids.len()];
+
+        // Every line is scanned: evidence reports all lines a fingerprint matched
+        for (i, line) in context.lines().iter().enumerate() {
+            for fp_index in self.regex_hits(line) {
+                if !active[fp_index] {
+                    continue;
+                }
+                if let Some(m) = found[fp_index].as_mut() {
+                    m.line_indices.push(i);
+                    continue;
+                }
+                let FingerprintPattern::Regex(re) = &self.patterns[fp_index] else {
+                    continue;
+                };
+                if let Some(caps) = re.captures(line) {
+                    let captures: Vec<String> = caps
+                        .iter()
+                        .skip(1)
+                        .filter_map(|m| m.map(|m| m.as_str().to_string()))
+                        .collect();
+                    found[fp_index] = Some(self.matched(fp_index, Some(captures), Some(i)));
+                }
+            }
+
+            let mut touched = Vec::new();
+            for literal in self.literal_hits(line) {
+                for owner in &self.literal_owners[literal] {
+                    if !active[owner.fingerprint] {
+                        continue;
+                    }
+                    let best = &mut line_hits[owner.fingerprint];
+                    if best.is_none() {
+                        touched.push(owner.fingerprint);
+                    }
+                    if best.is_none_or(|b| owner.order < b) {
+                        *best = Some(owner.order);
+                    }
+                }
+            }
+            for fp_index in touched {
+                let order = line_hits[fp_index].take().unwrap_or(0);
+                if let Some(m) = found[fp_index].as_mut() {
+                    m.line_indices.push(i);
+                    continue;
+                }
+                let captures = match &self.patterns[fp_index] {
+                    FingerprintPattern::Enum(patterns) => Some(vec![patterns[order].clone()]),
+                    _ => None,
+                };
+                found[fp_index] = Some(self.matched(fp_index, captures, Some(i)));
+            }
+        }
+
+        let lines = context.lines();
+        for &fp_index in self.composite.iter().filter(|i| active[**i]) {
+            let pattern = &self.patterns[fp_index];
+            if let Some((i, captures)) = pattern.find_in(lines, 0..lines.len()) {
+                let mut m = self.matched(fp_index, captures, Some(i));
+                m.line_indices = pattern.find_all_in(lines, 0..lines.len());
+                found[fp_index] = Some(m);
+            }
+        }
+
+        // String fingerprints also match anywhere in the full content
+        if let Some(content) = context.full_content() {
+            let needs_content = (0..self.ids.len())
+                .any(|i| active[i] && found[i].is_none() && self.string_fingerprints[i]);
+            if needs_content {
+                for literal in self.literal_hits(content) {
+                    for owner in &self.literal_owners[literal] {
+                        let fp_index = owner.fingerprint;
+                        if active[fp_index]
+                            && found[fp_index].is_none()
+                            && self.string_fingerprints[fp_index]
+                        {
+                            found[fp_index] = Some(self.matched(fp_index, None, None));
+                        }
+                    }
+                }
+            }
+        }
+
+        found
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| active[*i])
+            .map(|(i, m)| m.unwrap_or_else(|| self.unmatched(i)))
+            .collect()
+    }
+
+    /// Fingerprint indices of the regexes matching `line`
+    fn regex_hits(&self, line: &str) -> Vec<usize> {
+        let is_match = |fp_index: &usize| match &self.patterns[*fp_index] {
+            FingerprintPattern::Regex(re) => re.is_match(line),
+            _ => false,
+        };
+        let mut hits: Vec<usize> = match &self.regex_set {
+            Some(set) => set.matches(line).into_iter().map(|i| self.regex_owners[i]).collect(),
+            None => self.regex_owners.iter().copied().filter(is_match).collect(),
+        };
+        hits.extend(self.single_regexes.iter().copied().filter(is_match));
+        hits
+    }
+
+    /// Indices (into `literal_patterns`) of the literals found in `text`
+    fn literal_hits(&self, text: &str) -> Vec<usize> {
+        match &self.literals {
+            Some(automaton) => automaton
+                .find_overlapping_iter(text)
+                .map(|m| m.pattern().as_usize())
+                .collect(),
+            None => (0..self.literal_patterns.len())
+                .filter(|&i| text.contains(self.literal_patterns[i].as_str()))
+                .collect(),
+        }
+    }
+
+    fn matched(
+        &self,
+        fp_index: usize,
+        captures: Option<Vec<String>>,
+        line_index: Option<usize>,
+    ) -> FingerprintMatch {
+        FingerprintMatch {
+            fingerprint_id: self.ids[fp_index].clone(),
+            matched: true,
+            captures,
+            line_index,
+            line_indices: line_index.into_iter().collect(),
+        }
+    }
+
+    fn unmatched(&self, fp_index: usize) -> FingerprintMatch {
+        FingerprintMatch {
+            fingerprint_id: self.ids[fp_index].clone(),
+            matched: false,
+            captures: None,
+            line_index: None,
+            line_indices: Vec::new(),
+        }
+    }
+}
+
+/// Whether a regex source uses `\b` or `\B` (an escaped backslash doesn't count)
+fn has_word_boundary(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' && matches!(chars.next(), Some('b' | 'B')) {
+            return true;
+        }
+    }
+    false
+}
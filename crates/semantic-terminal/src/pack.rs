@@ -0,0 +1,547 @@
+//! Fingerprint packs - fingerprints loaded from JSON/TOML at runtime
+//!
+//! A pack lets new Claude Code UI strings be picked up without a crate
+//! release. Pack entries with the ID of a built-in fingerprint replace it,
+//! and `disable` removes fingerprints by ID.
+//!
+//! ```toml
+//! name = "claude-code-hotfix"
+//! disable = ["claude-code.confirm.no-option"]
+//!
+//! [[fingerprints]]
+//! id = "claude-code.statusbar.compacting"
+//! type = "string"
+//! category = "statusbar"
+//! pattern = "Compacting conversation"
+//! confidence = 0.9
+//! priority = 90
+//! state_weights = { thinking = 1.0, idle = -1.0 }
+//! versions = ">=2.0.0"
+//!
+//! # Options dialog: a numbered option followed by the footer, near the bottom
+//! [[fingerprints]]
+//! id = "claude-code.confirm.options-dialog"
+//! type = "sequence"
+//! category = "confirm"
+//! pattern = ['^[\s❯>]*1\.\s*Yes', 'Esc to cancel']
+//! max_gap = 12
+//! within_last = 30
+//! ```
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use super::fingerprint::{
+    Fingerprint, FingerprintCategory, FingerprintPattern, FingerprintRegistry, FingerprintType,
+};
+use super::{State, VersionRange};
+
+/// Source recorded for pack entries that don't set one
+const DEFAULT_PACK_SOURCE: &str = "pack";
+
+/// Serialization format of a fingerprint pack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FingerprintPackFormat {
+    Json,
+    Toml,
+}
+
+impl FingerprintPackFormat {
+    /// Guess the format from a file extension (`.toml`, otherwise JSON)
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Self::Toml,
+            _ => Self::Json,
+        }
+    }
+}
+
+/// Pattern of a pack entry: one string, or a list for enum/marker types
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PackPattern {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// A fingerprint definition inside a pack
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FingerprintDef {
+    /// Unique identifier (replaces a built-in fingerprint with the same ID)
+    pub id: String,
+    /// Type of pattern matching
+    #[serde(rename = "type")]
+    pub fingerprint_type: FingerprintType,
+    /// Category for grouping
+    pub category: FingerprintCategory,
+    /// Pattern to match
+    pub pattern: PackPattern,
+    /// Confidence level (0.0 - 1.0)
+    #[serde(default = "default_confidence")]
+    pub confidence: f64,
+    /// Priority (higher = checked first within category)
+    #[serde(default)]
+    pub priority: u32,
+    /// Source identifier (defaults to the pack's name)
+    #[serde(default)]
+    pub source: Option<String>,
+    /// How strongly a match votes for each state (-1.0 - 1.0)
+    #[serde(default)]
+    pub state_weights: HashMap<State, f64>,
+    /// Claude Code versions the fingerprint applies to (e.g. `">=2.0.0"`)
+    #[serde(default)]
+    pub versions: VersionRange,
+    /// Most lines allowed between steps of a `sequence` pattern
+    #[serde(default)]
+    pub max_gap: usize,
+    /// Only match within the last N lines of the frame
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub within_last: Option<usize>,
+    /// Regex that rejects a match when found near it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unless: Option<String>,
+    /// How many lines around a match `unless` checks (0 = the same line)
+    #[serde(default)]
+    pub unless_within: usize,
+}
+
+fn default_confidence() -> f64 {
+    1.0
+}
+
+/// A set of fingerprints loaded at runtime
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FingerprintPack {
+    /// Pack name, used as the default source of its fingerprints
+    #[serde(default)]
+    pub name: Option<String>,
+    /// IDs of fingerprints to remove from the registry
+    #[serde(default)]
+    pub disable: Vec<String>,
+    /// Fingerprints to add or override
+    #[serde(default)]
+    pub fingerprints: Vec<FingerprintDef>,
+}
+
+/// Error loading or applying a fingerprint pack
+#[derive(Debug)]
+pub enum FingerprintPackError {
+    /// The pack file could not be read
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The pack is not valid JSON/TOML or does not match the pack format
+    Parse(String),
+    /// A regex pattern failed to compile
+    InvalidRegex { id: String, source: regex::Error },
+    /// The pattern shape does not fit the fingerprint type
+    InvalidPattern { id: String, reason: String },
+    /// A confidence or state weight is out of range
+    InvalidWeight { id: String, reason: String },
+    /// The same ID is defined twice in one pack
+    DuplicateId(String),
+    /// `disable` names a fingerprint that is not registered
+    UnknownFingerprint(String),
+}
+
+impl std::fmt::Display for FingerprintPackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { path, source } => {
+                write!(f, "failed to read pack {}: {}", path.display(), source)
+            }
+            Self::Parse(msg) => write!(f, "invalid fingerprint pack: {}", msg),
+            Self::InvalidRegex { id, source } => {
+                write!(f, "fingerprint {}: invalid regex: {}", id, source)
+            }
+            Self::InvalidPattern { id, reason } => write!(f, "fingerprint {}: {}", id, reason),
+            Self::InvalidWeight { id, reason } => write!(f, "fingerprint {}: {}", id, reason),
+            Self::DuplicateId(id) => write!(f, "duplicate fingerprint id: {}", id),
+            Self::UnknownFingerprint(id) => write!(f, "cannot disable unknown fingerprint: {}", id),
+        }
+    }
+}
+
+impl std::error::Error for FingerprintPackError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::InvalidRegex { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl FingerprintDef {
+    /// Validate and compile into a [`Fingerprint`]
+    pub fn compile(&self, default_source: &str) -> Result<Fingerprint, FingerprintPackError> {
+        let invalid_pattern = |reason: &str| FingerprintPackError::InvalidPattern {
+            id: self.id.clone(),
+            reason: reason.to_string(),
+        };
+
+        let regex = |p: &str| {
+            Regex::new(p).map_err(|source| FingerprintPackError::InvalidRegex {
+                id: self.id.clone(),
+                source,
+            })
+        };
+
+        let mut pattern = match (self.fingerprint_type, &self.pattern) {
+            (FingerprintType::Regex, PackPattern::One(p)) => FingerprintPattern::Regex(regex(p)?),
+            (FingerprintType::String, PackPattern::One(p)) => {
+                if p.is_empty() {
+                    return Err(invalid_pattern("string pattern is empty"));
+                }
+                FingerprintPattern::String(p.clone())
+            }
+            (FingerprintType::Enum | FingerprintType::Marker, PackPattern::Many(ps)) => {
+                if ps.is_empty() || ps.iter().any(|p| p.is_empty()) {
+                    return Err(invalid_pattern("enum patterns must be non-empty strings"));
+                }
+                FingerprintPattern::Enum(ps.clone())
+            }
+            (FingerprintType::Sequence, PackPattern::Many(ps)) => {
+                if ps.is_empty() {
+                    return Err(invalid_pattern("sequence needs at least one step"));
+                }
+                let steps = ps
+                    .iter()
+                    .map(|p| regex(p).map(FingerprintPattern::Regex))
+                    .collect::<Result<_, _>>()?;
+                FingerprintPattern::Sequence {
+                    steps,
+                    max_gap: self.max_gap,
+                }
+            }
+            (FingerprintType::Regex | FingerprintType::String, PackPattern::Many(_)) => {
+                return Err(invalid_pattern("expected a single pattern string"));
+            }
+            (
+                FingerprintType::Enum | FingerprintType::Marker | FingerprintType::Sequence,
+                PackPattern::One(_),
+            ) => {
+                return Err(invalid_pattern("expected a list of pattern strings"));
+            }
+        };
+
+        if let Some(unless) = &self.unless {
+            pattern = FingerprintPattern::Unless {
+                pattern: Box::new(pattern),
+                unless: Box::new(FingerprintPattern::Regex(regex(unless)?)),
+                within: self.unless_within,
+            };
+        }
+        if let Some(lines) = self.within_last {
+            if lines == 0 {
+                return Err(invalid_pattern("within_last must be at least 1"));
+            }
+            pattern = FingerprintPattern::Bottom {
+                lines,
+                pattern: Box::new(pattern),
+            };
+        }
+
+        if !(0.0..=1.0).contains(&self.confidence) {
+            return Err(FingerprintPackError::InvalidWeight {
+                id: self.id.clone(),
+                reason: format!("confidence {} is outside 0.0 - 1.0", self.confidence),
+            });
+        }
+        if let Some((state, weight)) = self
+            .state_weights
+            .iter()
+            .find(|(_, w)| !(-1.0..=1.0).contains(*w))
+        {
+            return Err(FingerprintPackError::InvalidWeight {
+                id: self.id.clone(),
+                reason: format!("weight {} for {} is outside -1.0 - 1.0", weight, state),
+            });
+        }
+
+        // Keep a stable order regardless of map iteration
+        let state_weights = State::ALL
+            .iter()
+            .filter_map(|s| self.state_weights.get(s).map(|w| (*s, *w)))
+            .collect();
+
+        Ok(Fingerprint {
+            id: self.id.clone(),
+            fingerprint_type: self.fingerprint_type,
+            category: self.category,
+            pattern,
+            confidence: self.confidence,
+            priority: self.priority,
+            source: self
+                .source
+                .clone()
+                .unwrap_or_else(|| default_source.to_string()),
+            state_weights,
+            versions: self.versions,
+        })
+    }
+}
+
+impl FingerprintPack {
+    /// Parse a pack from a string
+    pub fn from_str(s: &str, format: FingerprintPackFormat) -> Result<Self, FingerprintPackError> {
+        match format {
+            FingerprintPackFormat::Json => {
+                serde_json::from_str(s).map_err(|e| FingerprintPackError::Parse(e.to_string()))
+            }
+            FingerprintPackFormat::Toml => {
+                toml::from_str(s).map_err(|e| FingerprintPackError::Parse(e.to_string()))
+            }
+        }
+    }
+
+    /// Read a pack from a file, choosing the format by extension
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, FingerprintPackError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|source| FingerprintPackError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_str(&content, FingerprintPackFormat::from_path(path))
+    }
+
+    /// Validate and compile all fingerprints of the pack
+    pub fn compile(&self) -> Result<Vec<Fingerprint>, FingerprintPackError> {
+        let source = self.name.as_deref().unwrap_or(DEFAULT_PACK_SOURCE);
+        let mut seen = HashSet::new();
+        self.fingerprints
+            .iter()
+            .map(|def| {
+                if !seen.insert(def.id.as_str()) {
+                    return Err(FingerprintPackError::DuplicateId(def.id.clone()));
+                }
+                def.compile(source)
+            })
+            .collect()
+    }
+}
+
+impl FingerprintRegistry {
+    /// Apply a pack: disable listed fingerprints, then add or override its fingerprints
+    ///
+    /// The registry is left unchanged if the pack fails validation.
+    pub fn apply_pack(&mut self, pack: &FingerprintPack) -> Result<(), FingerprintPackError> {
+        let fingerprints = pack.compile()?;
+        if let Some(id) = pack.disable.iter().find(|id| self.get(id).is_none()) {
+            return Err(FingerprintPackError::UnknownFingerprint(id.clone()));
+        }
+
+        for id in &pack.disable {
+            self.unregister(id);
+        }
+        self.register_all(fingerprints);
+        Ok(())
+    }
+
+    /// Load a pack file (`.toml` or JSON) into the registry
+    pub fn load_pack(&mut self, path: impl AsRef<Path>) -> Result<(), FingerprintPackError> {
+        self.apply_pack(&FingerprintPack::from_path(path)?)
+    }
+
+    /// Load a pack from a string into the registry
+    pub fn load_pack_str(
+        &mut self,
+        s: &str,
+        format: FingerprintPackFormat,
+    ) -> Result<(), FingerprintPackError> {
+        self.apply_pack(&FingerprintPack::from_str(s, format)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fingerprint::default_registry;
+    use crate::ParserContext;
+
+    const TOML_PACK: &str = r#"
+name = "hotfix"
+disable = ["claude-code.confirm.no-option"]
+
+[[fingerprints]]
+id = "hotfix.compacting"
+type = "string"
+category = "statusbar"
+pattern = "Compacting conversation"
+confidence = 0.9
+priority = 90
+state_weights = { thinking = 1.0, idle = -1.0 }
+
+[[fingerprints]]
+id = "hotfix.spinner"
+type = "enum"
+category = "spinner"
+pattern = ["◐", "◓"]
+"#;
+
+    #[test]
+    fn test_toml_pack() {
+        let mut registry = default_registry();
+        registry
+            .load_pack_str(TOML_PACK, FingerprintPackFormat::Toml)
+            .unwrap();
+
+        let fp = registry.get("hotfix.compacting").unwrap();
+        assert_eq!(fp.source, "hotfix");
+        assert_eq!(
+            fp.state_weights,
+            vec![(State::Idle, -1.0), (State::Thinking, 1.0)]
+        );
+        assert!(registry.get("claude-code.confirm.no-option").is_none());
+
+        let context = ParserContext::new(vec!["◓ Compacting conversation".to_string()]);
+        let result = registry.extract(&context);
+        assert!(result.hints.has_spinner);
+        assert!(result.matches["hotfix.compacting"].matched);
+    }
+
+    #[test]
+    fn test_json_pack_overrides_builtin() {
+        let json = r#"{
+            "fingerprints": [{
+                "id": "claude-code.prompt.input",
+                "type": "regex",
+                "category": "prompt",
+                "pattern": "^»\\s*$",
+                "confidence": 0.95,
+                "priority": 100,
+                "source": "claude-code-v2"
+            }]
+        }"#;
+        let mut registry = default_registry();
+        registry
+            .load_pack_str(json, FingerprintPackFormat::Json)
+            .unwrap();
+
+        let fp = registry.get("claude-code.prompt.input").unwrap();
+        assert_eq!(fp.source, "claude-code-v2");
+        assert_eq!(
+            registry
+                .get_by_category(FingerprintCategory::Prompt)
+                .iter()
+                .filter(|f| f.id == "claude-code.prompt.input")
+                .count(),
+            1
+        );
+
+        let result = registry.extract(&ParserContext::new(vec!["»".to_string()]));
+        assert!(result.matches["claude-code.prompt.input"].matched);
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        let json = r#"{"fingerprints": [
+            {"id": "bad", "type": "regex", "category": "error", "pattern": "(unclosed"}
+        ]}"#;
+        let mut registry = default_registry();
+        let err = registry
+            .load_pack_str(json, FingerprintPackFormat::Json)
+            .unwrap_err();
+        assert!(matches!(err, FingerprintPackError::InvalidRegex { ref id, .. } if id == "bad"));
+    }
+
+    #[test]
+    fn test_duplicate_id() {
+        let json = r#"{"fingerprints": [
+            {"id": "dup", "type": "string", "category": "error", "pattern": "a"},
+            {"id": "dup", "type": "string", "category": "error", "pattern": "b"}
+        ]}"#;
+        let err = FingerprintPack::from_str(json, FingerprintPackFormat::Json)
+            .unwrap()
+            .compile()
+            .unwrap_err();
+        assert!(matches!(err, FingerprintPackError::DuplicateId(ref id) if id == "dup"));
+    }
+
+    #[test]
+    fn test_pattern_shape_must_match_type() {
+        let json = r#"{"fingerprints": [
+            {"id": "x", "type": "enum", "category": "spinner", "pattern": "·"}
+        ]}"#;
+        let err = FingerprintPack::from_str(json, FingerprintPackFormat::Json)
+            .unwrap()
+            .compile()
+            .unwrap_err();
+        assert!(matches!(err, FingerprintPackError::InvalidPattern { .. }));
+    }
+
+    #[test]
+    fn test_failed_pack_leaves_registry_unchanged() {
+        let json = r#"{
+            "disable": ["claude-code.prompt.input", "missing.id"]
+        }"#;
+        let mut registry = default_registry();
+        let err = registry
+            .load_pack_str(json, FingerprintPackFormat::Json)
+            .unwrap_err();
+        assert!(
+            matches!(err, FingerprintPackError::UnknownFingerprint(ref id) if id == "missing.id")
+        );
+        assert!(registry.get("claude-code.prompt.input").is_some());
+    }
+
+    #[test]
+    fn test_load_pack_from_file() {
+        let path =
+            std::env::temp_dir().join(format!("fingerprint-pack-{}.toml", std::process::id()));
+        std::fs::write(&path, TOML_PACK).unwrap();
+
+        let mut registry = FingerprintRegistry::new();
+        let err = registry.load_pack(&path).unwrap_err();
+        // Built-ins are not registered, so the disable entry is unknown
+        assert!(matches!(err, FingerprintPackError::UnknownFingerprint(_)));
+
+        let mut registry = default_registry();
+        registry.load_pack(&path).unwrap();
+        assert!(registry.get("hotfix.spinner").is_some());
+        std::fs::remove_file(&path).unwrap();
+
+        let err = registry.load_pack(&path).unwrap_err();
+        assert!(matches!(err, FingerprintPackError::Io { .. }));
+    }
+
+    #[test]
+    fn test_sequence_pack() {
+        let toml = r#"
+[[fingerprints]]
+id = "custom.compacted"
+type = "sequence"
+category = "assistant"
+pattern = ['^⏺ Compact', '⎿\s+Compacted']
+max_gap = 1
+within_last = 5
+unless = "esc to interrupt"
+unless_within = 3
+"#;
+        let mut registry = FingerprintRegistry::new();
+        registry
+            .load_pack_str(toml, FingerprintPackFormat::Toml)
+            .unwrap();
+        let extract = |frame: &[&str]| {
+            let context = ParserContext::new(frame.iter().map(|s| s.to_string()).collect());
+            registry.extract(&context).matches["custom.compacted"].matched
+        };
+
+        assert!(extract(&["⏺ Compact summary", "  ⎿  Compacted (ctrl+r to see)", "❯ "]));
+        // Outside the last 5 lines
+        assert!(!extract(&["⏺ Compact summary", "  ⎿  Compacted", "", "", "", "", "❯ "]));
+        // Excluded by a nearby spinner
+        assert!(!extract(&["⏺ Compact summary", "  ⎿  Compacted", "· Compacting… (esc to interrupt)"]));
+
+        let bad = r#"{"fingerprints": [
+            {"id": "x", "type": "sequence", "category": "tool", "pattern": ["ok", "(bad"]}
+        ]}"#;
+        let err = registry
+            .load_pack_str(bad, FingerprintPackFormat::Json)
+            .unwrap_err();
+        assert!(matches!(err, FingerprintPackError::InvalidRegex { .. }));
+    }
+}
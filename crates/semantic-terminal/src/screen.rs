@@ -0,0 +1,915 @@
+//! Virtual terminal screen
+//!
+//! Renders raw PTY output (VT100/xterm escape sequences) into a character grid,
+//! so parsers see the screen the way a user would after in-place redraws.
+
+use std::collections::VecDeque;
+
+use unicode_width::UnicodeWidthChar;
+use vte::{Params, Parser, Perform};
+
+use super::keys::InputModes;
+use super::types::ParserContext;
+
+/// Default number of scrollback lines kept for the main screen
+pub const DEFAULT_SCROLLBACK: usize = 1000;
+
+/// Distance between default tab stops
+const TAB_WIDTH: usize = 8;
+
+/// A single grid cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cell {
+    /// Character displayed in this cell
+    ch: char,
+    /// Right half of a wide character (rendered by the cell to the left)
+    wide_spacer: bool,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            ch: ' ',
+            wide_spacer: false,
+        }
+    }
+}
+
+/// Cursor position (0-based)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Cursor {
+    row: usize,
+    col: usize,
+}
+
+/// One screen buffer (main or alternate)
+#[derive(Debug, Clone)]
+struct Grid {
+    rows: Vec<Vec<Cell>>,
+    cursor: Cursor,
+    saved_cursor: Option<Cursor>,
+    /// Top row of the scroll region (inclusive)
+    scroll_top: usize,
+    /// Bottom row of the scroll region (inclusive)
+    scroll_bottom: usize,
+    /// Set after printing into the last column; the next print wraps first
+    pending_wrap: bool,
+}
+
+impl Grid {
+    fn new(rows: usize, cols: usize) -> Self {
+        Self {
+            rows: vec![vec![Cell::default(); cols]; rows],
+            cursor: Cursor::default(),
+            saved_cursor: None,
+            scroll_top: 0,
+            scroll_bottom: rows - 1,
+            pending_wrap: false,
+        }
+    }
+
+    fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn width(&self) -> usize {
+        self.rows.first().map_or(0, |r| r.len())
+    }
+
+    fn blank_row(&self) -> Vec<Cell> {
+        vec![Cell::default(); self.width()]
+    }
+
+    /// Render a row as text, with trailing whitespace removed
+    fn row_text(row: &[Cell]) -> String {
+        let text: String = row
+            .iter()
+            .filter(|c| !c.wide_spacer)
+            .map(|c| c.ch)
+            .collect();
+        text.trim_end().to_string()
+    }
+
+    /// Clear the other half of a wide character that overlaps `col`
+    fn split_wide(&mut self, row: usize, col: usize) {
+        let width = self.width();
+        let line = &mut self.rows[row];
+        if line[col].wide_spacer && col > 0 {
+            line[col - 1] = Cell::default();
+        }
+        if col + 1 < width && line[col + 1].wide_spacer {
+            line[col + 1] = Cell::default();
+        }
+    }
+
+    fn clear_cells(&mut self, row: usize, from: usize, to: usize) {
+        let to = to.min(self.width());
+        if from >= to {
+            return;
+        }
+        self.split_wide(row, from);
+        self.split_wide(row, to - 1);
+        for cell in &mut self.rows[row][from..to] {
+            *cell = Cell::default();
+        }
+    }
+
+    /// Scroll the region up by `n` lines, returning the rows that left the top
+    fn scroll_up(&mut self, n: usize) -> Vec<Vec<Cell>> {
+        let (top, bottom) = (self.scroll_top, self.scroll_bottom);
+        let n = n.min(bottom + 1 - top);
+        let removed: Vec<Vec<Cell>> = self.rows.drain(top..top + n).collect();
+        for _ in 0..n {
+            let blank = self.blank_row();
+            self.rows.insert(bottom + 1 - n, blank);
+        }
+        removed
+    }
+
+    /// Scroll the region down by `n` lines
+    fn scroll_down(&mut self, n: usize) {
+        let (top, bottom) = (self.scroll_top, self.scroll_bottom);
+        let n = n.min(bottom + 1 - top);
+        self.rows.drain(bottom + 1 - n..=bottom);
+        for _ in 0..n {
+            let blank = self.blank_row();
+            self.rows.insert(top, blank);
+        }
+    }
+
+    fn resize(&mut self, rows: usize, cols: usize) {
+        for row in &mut self.rows {
+            row.resize(cols, Cell::default());
+            if let Some(last) = row.last_mut() {
+                // A wide character cut in half by the new width becomes blank
+                if last.ch.width().unwrap_or(1) > 1 && !last.wide_spacer {
+                    *last = Cell::default();
+                }
+            }
+        }
+        self.rows.resize(rows, vec![Cell::default(); cols]);
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+        self.cursor.row = self.cursor.row.min(rows - 1);
+        self.cursor.col = self.cursor.col.min(cols - 1);
+        self.pending_wrap = false;
+    }
+}
+
+/// Terminal state driven by the escape sequence parser
+struct ScreenState {
+    main: Grid,
+    alternate: Grid,
+    alternate_active: bool,
+    scrollback: VecDeque<String>,
+    scrollback_limit: usize,
+    title: Option<String>,
+    autowrap: bool,
+    /// Input modes set by the application (DECCKM, bracketed paste)
+    input_modes: InputModes,
+}
+
+impl ScreenState {
+    fn grid(&self) -> &Grid {
+        if self.alternate_active {
+            &self.alternate
+        } else {
+            &self.main
+        }
+    }
+
+    fn grid_mut(&mut self) -> &mut Grid {
+        if self.alternate_active {
+            &mut self.alternate
+        } else {
+            &mut self.main
+        }
+    }
+
+    fn push_scrollback(&mut self, rows: Vec<Vec<Cell>>) {
+        if self.scrollback_limit == 0 {
+            return;
+        }
+        for row in rows {
+            if self.scrollback.len() == self.scrollback_limit {
+                self.scrollback.pop_front();
+            }
+            self.scrollback.push_back(Grid::row_text(&row));
+        }
+    }
+
+    /// Scroll the active region up, keeping main-screen history in scrollback
+    fn scroll_up(&mut self, n: usize) {
+        let keep = !self.alternate_active && self.main.scroll_top == 0;
+        let removed = self.grid_mut().scroll_up(n);
+        if keep {
+            self.push_scrollback(removed);
+        }
+    }
+
+    fn linefeed(&mut self) {
+        let grid = self.grid_mut();
+        grid.pending_wrap = false;
+        if grid.cursor.row == grid.scroll_bottom {
+            self.scroll_up(1);
+        } else if grid.cursor.row + 1 < grid.height() {
+            grid.cursor.row += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        let grid = self.grid_mut();
+        grid.pending_wrap = false;
+        if grid.cursor.row == grid.scroll_top {
+            grid.scroll_down(1);
+        } else if grid.cursor.row > 0 {
+            grid.cursor.row -= 1;
+        }
+    }
+
+    fn print_char(&mut self, c: char) {
+        let width = match c.width() {
+            Some(w) if w > 0 => w,
+            // Zero-width characters (combining marks, variation selectors) are dropped
+            _ => return,
+        };
+        let autowrap = self.autowrap;
+        let cols = self.grid().width();
+        if width > cols {
+            return;
+        }
+
+        if self.grid().pending_wrap && autowrap {
+            self.grid_mut().cursor.col = 0;
+            self.linefeed();
+        }
+
+        // A wide character that does not fit in the last column wraps early
+        if self.grid().cursor.col + width > cols {
+            if autowrap {
+                let grid = self.grid_mut();
+                let row = grid.cursor.row;
+                let col = grid.cursor.col;
+                grid.clear_cells(row, col, cols);
+                grid.cursor.col = 0;
+                self.linefeed();
+            } else {
+                self.grid_mut().cursor.col = cols - width;
+            }
+        }
+
+        let grid = self.grid_mut();
+        let Cursor { row, col } = grid.cursor;
+        grid.split_wide(row, col);
+        grid.rows[row][col] = Cell {
+            ch: c,
+            wide_spacer: false,
+        };
+        if width == 2 {
+            grid.split_wide(row, col + 1);
+            grid.rows[row][col + 1] = Cell {
+                ch: ' ',
+                wide_spacer: true,
+            };
+        }
+
+        if col + width >= cols {
+            grid.cursor.col = cols - 1;
+            grid.pending_wrap = true;
+        } else {
+            grid.cursor.col = col + width;
+        }
+    }
+
+    fn set_cursor(&mut self, row: usize, col: usize) {
+        let grid = self.grid_mut();
+        grid.cursor.row = row.min(grid.height() - 1);
+        grid.cursor.col = col.min(grid.width() - 1);
+        grid.pending_wrap = false;
+    }
+
+    fn erase_in_display(&mut self, mode: usize) {
+        let grid = self.grid_mut();
+        let Cursor { row, col } = grid.cursor;
+        let (height, width) = (grid.height(), grid.width());
+        match mode {
+            0 => {
+                grid.clear_cells(row, col, width);
+                for r in row + 1..height {
+                    grid.clear_cells(r, 0, width);
+                }
+            }
+            1 => {
+                for r in 0..row {
+                    grid.clear_cells(r, 0, width);
+                }
+                grid.clear_cells(row, 0, col + 1);
+            }
+            2 => {
+                for r in 0..height {
+                    grid.clear_cells(r, 0, width);
+                }
+            }
+            3 => self.scrollback.clear(),
+            _ => {}
+        }
+    }
+
+    fn erase_in_line(&mut self, mode: usize) {
+        let grid = self.grid_mut();
+        let Cursor { row, col } = grid.cursor;
+        let width = grid.width();
+        match mode {
+            0 => grid.clear_cells(row, col, width),
+            1 => grid.clear_cells(row, 0, col + 1),
+            2 => grid.clear_cells(row, 0, width),
+            _ => {}
+        }
+    }
+
+    fn insert_lines(&mut self, n: usize) {
+        let grid = self.grid_mut();
+        let row = grid.cursor.row;
+        if row < grid.scroll_top || row > grid.scroll_bottom {
+            return;
+        }
+        let saved_top = grid.scroll_top;
+        grid.scroll_top = row;
+        grid.scroll_down(n);
+        grid.scroll_top = saved_top;
+        grid.cursor.col = 0;
+    }
+
+    fn delete_lines(&mut self, n: usize) {
+        let grid = self.grid_mut();
+        let row = grid.cursor.row;
+        if row < grid.scroll_top || row > grid.scroll_bottom {
+            return;
+        }
+        let saved_top = grid.scroll_top;
+        grid.scroll_top = row;
+        grid.scroll_up(n);
+        grid.scroll_top = saved_top;
+        grid.cursor.col = 0;
+    }
+
+    fn insert_chars(&mut self, n: usize) {
+        let grid = self.grid_mut();
+        let Cursor { row, col } = grid.cursor;
+        let width = grid.width();
+        let n = n.min(width - col);
+        grid.split_wide(row, col);
+        let line = &mut grid.rows[row];
+        line.truncate(width - n);
+        for _ in 0..n {
+            line.insert(col, Cell::default());
+        }
+        if line[width - 1].ch.width().unwrap_or(1) > 1 && !line[width - 1].wide_spacer {
+            line[width - 1] = Cell::default();
+        }
+    }
+
+    fn delete_chars(&mut self, n: usize) {
+        let grid = self.grid_mut();
+        let Cursor { row, col } = grid.cursor;
+        let width = grid.width();
+        let n = n.min(width - col);
+        grid.split_wide(row, col);
+        grid.split_wide(row, col + n - 1);
+        let line = &mut grid.rows[row];
+        line.drain(col..col + n);
+        line.resize(width, Cell::default());
+    }
+
+    fn set_scroll_region(&mut self, top: usize, bottom: usize) {
+        let grid = self.grid_mut();
+        let height = grid.height();
+        let bottom = if bottom == 0 {
+            height
+        } else {
+            bottom.min(height)
+        };
+        let top = top.max(1);
+        if top >= bottom {
+            return;
+        }
+        grid.scroll_top = top - 1;
+        grid.scroll_bottom = bottom - 1;
+        self.set_cursor(0, 0);
+    }
+
+    fn save_cursor(&mut self) {
+        let grid = self.grid_mut();
+        grid.saved_cursor = Some(grid.cursor);
+    }
+
+    fn restore_cursor(&mut self) {
+        let saved = self.grid().saved_cursor.unwrap_or_default();
+        self.set_cursor(saved.row, saved.col);
+    }
+
+    fn enter_alternate(&mut self, save_cursor: bool, clear: bool) {
+        if self.alternate_active {
+            return;
+        }
+        if save_cursor {
+            self.save_cursor();
+        }
+        self.alternate_active = true;
+        if clear {
+            let (height, width) = (self.main.height(), self.main.width());
+            self.alternate = Grid::new(height, width);
+        }
+    }
+
+    fn leave_alternate(&mut self, restore_cursor: bool) {
+        if !self.alternate_active {
+            return;
+        }
+        self.alternate_active = false;
+        if restore_cursor {
+            self.restore_cursor();
+        }
+    }
+
+    fn set_private_mode(&mut self, mode: u16, enabled: bool) {
+        match (mode, enabled) {
+            (1, _) => self.input_modes.application_cursor = enabled,
+            (7, _) => self.autowrap = enabled,
+            (47, true) => self.enter_alternate(false, false),
+            (47, false) => self.leave_alternate(false),
+            (1047, true) => self.enter_alternate(false, true),
+            (1047, false) => self.leave_alternate(false),
+            (1049, true) => self.enter_alternate(true, true),
+            (1049, false) => self.leave_alternate(true),
+            (2004, _) => self.input_modes.bracketed_paste = enabled,
+            _ => {}
+        }
+    }
+
+    fn reset(&mut self) {
+        let (height, width) = (self.main.height(), self.main.width());
+        self.main = Grid::new(height, width);
+        self.alternate = Grid::new(height, width);
+        self.alternate_active = false;
+        self.scrollback.clear();
+        self.title = None;
+        self.autowrap = true;
+        self.input_modes = InputModes::reset();
+    }
+}
+
+/// Get the first value of parameter `index`, substituting `default` for 0 or missing
+fn param(params: &[u16], index: usize, default: usize) -> usize {
+    match params.get(index) {
+        Some(&0) | None => default,
+        Some(&v) => v as usize,
+    }
+}
+
+impl Perform for ScreenState {
+    fn print(&mut self, c: char) {
+        self.print_char(c);
+    }
+
+    fn execute(&mut self, byte: u8) {
+        match byte {
+            // Backspace
+            0x08 => {
+                let grid = self.grid_mut();
+                grid.cursor.col = grid.cursor.col.saturating_sub(1);
+                grid.pending_wrap = false;
+            }
+            // Horizontal tab
+            0x09 => {
+                let grid = self.grid_mut();
+                let next = (grid.cursor.col / TAB_WIDTH + 1) * TAB_WIDTH;
+                grid.cursor.col = next.min(grid.width() - 1);
+            }
+            // Line feed, vertical tab, form feed
+            0x0A..=0x0C => self.linefeed(),
+            // Carriage return
+            0x0D => {
+                let grid = self.grid_mut();
+                grid.cursor.col = 0;
+                grid.pending_wrap = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], _ignore: bool, action: char) {
+        let ps: Vec<u16> = params
+            .iter()
+            .map(|p| p.first().copied().unwrap_or(0))
+            .collect();
+
+        if intermediates == b"?" {
+            match action {
+                'h' | 'l' => {
+                    for &mode in &ps {
+                        self.set_private_mode(mode, action == 'h');
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
+        if !intermediates.is_empty() {
+            return;
+        }
+
+        let Cursor { row, col } = self.grid().cursor;
+        match action {
+            // Cursor up, stopping at the top margin when inside the scroll region
+            'A' => {
+                let top = self.grid().scroll_top;
+                let limit = if row >= top { top } else { 0 };
+                let target = row.saturating_sub(param(&ps, 0, 1)).max(limit);
+                self.set_cursor(target, col);
+            }
+            // Cursor down, stopping at the bottom margin when inside the scroll region
+            'B' | 'e' => {
+                let grid = self.grid();
+                let limit = if row <= grid.scroll_bottom {
+                    grid.scroll_bottom
+                } else {
+                    grid.height() - 1
+                };
+                let target = (row + param(&ps, 0, 1)).min(limit);
+                self.set_cursor(target, col);
+            }
+            'C' | 'a' => self.set_cursor(row, col + param(&ps, 0, 1)),
+            'D' => self.set_cursor(row, col.saturating_sub(param(&ps, 0, 1))),
+            'E' => self.set_cursor(row + param(&ps, 0, 1), 0),
+            'F' => self.set_cursor(row.saturating_sub(param(&ps, 0, 1)), 0),
+            'G' | '`' => self.set_cursor(row, param(&ps, 0, 1) - 1),
+            'H' | 'f' => self.set_cursor(param(&ps, 0, 1) - 1, param(&ps, 1, 1) - 1),
+            'd' => self.set_cursor(param(&ps, 0, 1) - 1, col),
+            'J' => self.erase_in_display(ps.first().copied().unwrap_or(0) as usize),
+            'K' => self.erase_in_line(ps.first().copied().unwrap_or(0) as usize),
+            'L' => self.insert_lines(param(&ps, 0, 1)),
+            'M' => self.delete_lines(param(&ps, 0, 1)),
+            '@' => self.insert_chars(param(&ps, 0, 1)),
+            'P' => self.delete_chars(param(&ps, 0, 1)),
+            'X' => {
+                let n = param(&ps, 0, 1);
+                let grid = self.grid_mut();
+                grid.clear_cells(row, col, col + n);
+            }
+            'S' => self.scroll_up(param(&ps, 0, 1)),
+            'T' => self.grid_mut().scroll_down(param(&ps, 0, 1)),
+            'r' => {
+                self.set_scroll_region(param(&ps, 0, 1), ps.get(1).copied().unwrap_or(0) as usize)
+            }
+            's' => self.save_cursor(),
+            'u' => self.restore_cursor(),
+            _ => {}
+        }
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], _ignore: bool, byte: u8) {
+        if !intermediates.is_empty() {
+            return;
+        }
+        match byte {
+            b'7' => self.save_cursor(),
+            b'8' => self.restore_cursor(),
+            b'D' => self.linefeed(),
+            b'E' => {
+                self.grid_mut().cursor.col = 0;
+                self.linefeed();
+            }
+            b'M' => self.reverse_index(),
+            b'c' => self.reset(),
+            _ => {}
+        }
+    }
+
+    fn osc_dispatch(&mut self, params: &[&[u8]], _bell_terminated: bool) {
+        // OSC 0 (icon + title) and OSC 2 (title)
+        if let Some(&kind) = params.first() {
+            if kind == b"0" || kind == b"2" {
+                let title = params[1..].join(&b';');
+                self.title = Some(String::from_utf8_lossy(&title).into_owned());
+            }
+        }
+    }
+}
+
+/// Virtual terminal screen
+///
+/// Feeds raw PTY bytes through a VT100/xterm emulator and exposes the
+/// rendered grid:
+/// - Cursor movement, erase-line/erase-display, insert/delete lines and characters
+/// - Scroll regions (DECSTBM) with scrollback for the main screen
+/// - Alternate screen (modes 47, 1047, 1049)
+/// - Wide (CJK, emoji) characters
+/// - Window title (OSC 0/2)
+/// - Input modes (application cursor keys, bracketed paste) for encoding keys
+///
+/// Colors and other attributes are ignored, since parsers only see text.
+pub struct Screen {
+    parser: Parser,
+    state: ScreenState,
+}
+
+impl Screen {
+    /// Create a new screen with the given size
+    ///
+    /// Sizes of zero are clamped to one row/column.
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let (rows, cols) = (rows.max(1), cols.max(1));
+        Self {
+            parser: Parser::new(),
+            state: ScreenState {
+                main: Grid::new(rows, cols),
+                alternate: Grid::new(rows, cols),
+                alternate_active: false,
+                scrollback: VecDeque::new(),
+                scrollback_limit: DEFAULT_SCROLLBACK,
+                title: None,
+                autowrap: true,
+                input_modes: InputModes::reset(),
+            },
+        }
+    }
+
+    /// Set the maximum number of scrollback lines (0 disables scrollback)
+    pub fn with_scrollback(mut self, limit: usize) -> Self {
+        self.state.scrollback_limit = limit;
+        while self.state.scrollback.len() > limit {
+            self.state.scrollback.pop_front();
+        }
+        self
+    }
+
+    /// Feed a chunk of raw PTY output
+    ///
+    /// Chunks may split escape sequences and UTF-8 characters at any byte.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.parser.advance(&mut self.state, bytes);
+    }
+
+    /// Resize the screen
+    ///
+    /// Rows pushed off the top of the main screen to keep the cursor visible
+    /// are moved to scrollback.
+    pub fn resize(&mut self, rows: usize, cols: usize) {
+        let (rows, cols) = (rows.max(1), cols.max(1));
+        let overflow = (self.state.main.cursor.row + 1).saturating_sub(rows);
+        if overflow > 0 {
+            let removed: Vec<Vec<Cell>> = self.state.main.rows.drain(..overflow).collect();
+            self.state.push_scrollback(removed);
+            self.state.main.cursor.row -= overflow;
+        }
+        self.state.main.resize(rows, cols);
+        self.state.alternate.resize(rows, cols);
+    }
+
+    /// Screen size as (rows, cols)
+    pub fn size(&self) -> (usize, usize) {
+        let grid = self.state.grid();
+        (grid.height(), grid.width())
+    }
+
+    /// Cursor position as (row, col), 0-based
+    pub fn cursor_position(&self) -> (usize, usize) {
+        let cursor = self.state.grid().cursor;
+        (cursor.row, cursor.col)
+    }
+
+    /// Whether the alternate screen is active
+    pub fn is_alternate_screen(&self) -> bool {
+        self.state.alternate_active
+    }
+
+    /// Input modes the application has set, for a [`KeyEncoder`](super::KeyEncoder)
+    pub fn input_modes(&self) -> InputModes {
+        self.state.input_modes
+    }
+
+    /// Window title set via OSC 0/2 (if any)
+    pub fn title(&self) -> Option<&str> {
+        self.state.title.as_deref()
+    }
+
+    /// Visible rows, with trailing whitespace removed from each row
+    pub fn lines(&self) -> Vec<String> {
+        self.state
+            .grid()
+            .rows
+            .iter()
+            .map(|r| Grid::row_text(r))
+            .collect()
+    }
+
+    /// Lines that scrolled off the top of the main screen, oldest first
+    pub fn scrollback(&self) -> Vec<String> {
+        self.state.scrollback.iter().cloned().collect()
+    }
+
+    /// Parser context for the visible grid
+    ///
+    /// Blank rows below the last non-blank row are omitted.
+    pub fn context(&self) -> ParserContext<'static> {
+        let mut lines = self.lines();
+        trim_trailing_blank(&mut lines);
+        ParserContext::new(lines)
+    }
+
+    /// Parser context for the last `n` rows of content
+    ///
+    /// On the main screen this reaches back into scrollback when the visible
+    /// grid has fewer than `n` rows of content. Blank rows below the last
+    /// non-blank row are omitted before counting.
+    pub fn context_last(&self, n: usize) -> ParserContext<'static> {
+        let mut lines = self.lines();
+        trim_trailing_blank(&mut lines);
+        if !self.state.alternate_active && lines.len() < n {
+            let needed = n - lines.len();
+            let skip = self.state.scrollback.len().saturating_sub(needed);
+            let mut combined: Vec<String> =
+                self.state.scrollback.iter().skip(skip).cloned().collect();
+            combined.extend(lines);
+            lines = combined;
+        }
+        let skip = lines.len().saturating_sub(n);
+        ParserContext::new(lines.split_off(skip))
+    }
+}
+
+/// Drop blank rows at the end of a rendered grid
+fn trim_trailing_blank(lines: &mut Vec<String>) {
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen_with(rows: usize, cols: usize, input: &str) -> Screen {
+        let mut screen = Screen::new(rows, cols);
+        screen.feed(input.as_bytes());
+        screen
+    }
+
+    #[test]
+    fn test_plain_text_and_newlines() {
+        let screen = screen_with(5, 20, "hello\r\nworld");
+        assert_eq!(screen.lines()[0], "hello");
+        assert_eq!(screen.lines()[1], "world");
+        assert_eq!(screen.cursor_position(), (1, 5));
+        assert_eq!(screen.context().lines().to_vec(), vec!["hello", "world"]);
+    }
+
+    #[test]
+    fn test_in_place_redraw() {
+        // Spinner line redrawn with CR + erase line
+        let screen = screen_with(
+            5,
+            40,
+            "✻ Thinking… (esc to interrupt)\r\x1b[2K✶ Pondering… (esc to interrupt)",
+        );
+        assert_eq!(screen.lines()[0], "✶ Pondering… (esc to interrupt)");
+    }
+
+    #[test]
+    fn test_cursor_movement() {
+        let screen = screen_with(5, 20, "abcdef\x1b[3D\x1b[1mX\x1b[0m\x1b[2;4HY\x1b[1AZ");
+        assert_eq!(screen.lines()[0], "abcXZf");
+        assert_eq!(screen.lines()[1], "   Y");
+    }
+
+    #[test]
+    fn test_erase_display() {
+        let screen = screen_with(3, 10, "one\r\ntwo\r\nthree\x1b[2;2H\x1b[J");
+        assert_eq!(screen.lines(), vec!["one", "t", ""]);
+
+        let screen = screen_with(3, 10, "one\r\ntwo\x1b[2J\x1b[Hnew");
+        assert_eq!(screen.context().lines().to_vec(), vec!["new"]);
+    }
+
+    #[test]
+    fn test_scroll_pushes_scrollback() {
+        let screen = screen_with(2, 10, "a\r\nb\r\nc\r\nd");
+        assert_eq!(screen.lines(), vec!["c", "d"]);
+        assert_eq!(screen.scrollback(), vec!["a", "b"]);
+        assert_eq!(screen.context_last(3).lines().to_vec(), vec!["b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_scroll_region() {
+        // Rows 1-2 scroll, row 3 stays pinned like a status bar
+        let screen = screen_with(3, 10, "\x1b[3;1Hstatus\x1b[1;2r\x1b[1;1Ha\r\nb\r\nc");
+        assert_eq!(screen.lines(), vec!["b", "c", "status"]);
+        // Scrolling inside a region with top margin 0 still feeds scrollback
+        assert_eq!(screen.scrollback(), vec!["a"]);
+    }
+
+    #[test]
+    fn test_insert_delete_lines() {
+        let screen = screen_with(3, 10, "a\r\nb\r\nc\x1b[2;1H\x1b[L");
+        assert_eq!(screen.lines(), vec!["a", "", "b"]);
+
+        let screen = screen_with(3, 10, "a\r\nb\r\nc\x1b[1;1H\x1b[M");
+        assert_eq!(screen.lines(), vec!["b", "c", ""]);
+    }
+
+    #[test]
+    fn test_alternate_screen() {
+        let mut screen = screen_with(3, 10, "main");
+        screen.feed(b"\x1b[?1049h");
+        assert!(screen.is_alternate_screen());
+        assert!(screen.context().lines().is_empty());
+
+        screen.feed(b"full screen app");
+        screen.feed(b"\x1b[?1049l");
+        assert!(!screen.is_alternate_screen());
+        assert_eq!(screen.lines()[0], "main");
+        assert_eq!(screen.cursor_position(), (0, 4));
+    }
+
+    #[test]
+    fn test_wide_characters() {
+        let screen = screen_with(3, 10, "中文ab");
+        assert_eq!(screen.lines()[0], "中文ab");
+        assert_eq!(screen.cursor_position(), (0, 6));
+
+        // Overwriting half of a wide character blanks the other half
+        let screen = screen_with(3, 10, "中文\x1b[1;2Hx");
+        assert_eq!(screen.lines()[0], " x文");
+
+        // A wide character that does not fit wraps to the next line
+        let screen = screen_with(3, 5, "abcd中");
+        assert_eq!(screen.lines(), vec!["abcd", "中", ""]);
+    }
+
+    #[test]
+    fn test_autowrap() {
+        let screen = screen_with(3, 5, "abcdefg");
+        assert_eq!(screen.lines(), vec!["abcde", "fg", ""]);
+
+        // Pending wrap is cancelled by a carriage return
+        let screen = screen_with(3, 5, "abcde\rX");
+        assert_eq!(screen.lines()[0], "Xbcde");
+    }
+
+    #[test]
+    fn test_split_feed() {
+        let mut screen = Screen::new(3, 20);
+        let bytes = "ab\x1b[2K\rcd ⏺".as_bytes();
+        for chunk in bytes.chunks(1) {
+            screen.feed(chunk);
+        }
+        assert_eq!(screen.lines()[0], "cd ⏺");
+    }
+
+    #[test]
+    fn test_title() {
+        let screen = screen_with(3, 10, "\x1b]0;⠐ Initial Greeting\x07");
+        assert_eq!(screen.title(), Some("⠐ Initial Greeting"));
+
+        let screen = screen_with(3, 10, "\x1b]2;✳ Claude Code\x1b\\");
+        assert_eq!(screen.title(), Some("✳ Claude Code"));
+    }
+
+    #[test]
+    fn test_input_modes() {
+        let mut screen = Screen::new(4, 20);
+        assert_eq!(screen.input_modes(), InputModes::reset());
+        screen.feed(b"\x1b[?1h\x1b[?2004h");
+        let modes = screen.input_modes();
+        assert!(modes.application_cursor && modes.bracketed_paste);
+        screen.feed(b"\x1b[?1l");
+        assert!(!screen.input_modes().application_cursor);
+        screen.feed(b"\x1bc");
+        assert_eq!(screen.input_modes(), InputModes::reset());
+    }
+
+    #[test]
+    fn test_resize_keeps_cursor_visible() {
+        let mut screen = screen_with(4, 10, "a\r\nb\r\nc\r\nd");
+        screen.resize(2, 10);
+        assert_eq!(screen.lines(), vec!["c", "d"]);
+        assert_eq!(screen.scrollback(), vec!["a", "b"]);
+        assert_eq!(screen.size(), (2, 10));
+    }
+
+    #[test]
+    fn test_context_feeds_state_parser() {
+        use crate::{ClaudeCodeStateParser, State, StateParser};
+
+        let parser = ClaudeCodeStateParser::new();
+        let mut screen = screen_with(10, 40, "✻ Thinking… (esc to interrupt)\r\n");
+        let result = parser.detect_state(&screen.context()).unwrap();
+        assert_eq!(result.state, State::Thinking);
+
+        // The status line is erased in place and replaced by the prompt
+        screen.feed("\x1b[1A\x1b[2K❯ ".as_bytes());
+        let result = parser.detect_state(&screen.context()).unwrap();
+        assert_eq!(result.state, State::Idle);
+    }
+}
@@ -0,0 +1,920 @@
+//! Claude Code session tracker
+//!
+//! Runs all parsers over successive snapshots (or raw PTY bytes) and turns
+//! the differences between frames into a stream of semantic events.
+
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use super::confirm::ClaudeCodeConfirmParser;
+use super::input::ClaudeCodeInput;
+use super::keys::KeyEncoder;
+use super::screen::Screen;
+use super::state::ClaudeCodeStateParser;
+use super::status::ClaudeCodeStatusParser;
+use super::title::ClaudeCodeTitleParser;
+use super::tool::ClaudeCodeToolOutputParser;
+use super::transaction::{ConfirmOutcome, ConfirmResolution, ConfirmTransaction};
+use super::version::ClaudeCodeVersion;
+use super::types::{
+    ClaudeCodeStatus, ClaudeCodeTitle, ClaudeCodeToolOutput, ConfirmInfo, ConfirmParser,
+    ConfirmResponse, ConfirmResponseError, ParserContext, State, StateParser, StatusParser,
+    TitleParser, TitleParserContext, ToolOutputResult, ToolStatus,
+};
+
+/// Semantic event emitted by a [`Session`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SessionEvent {
+    /// Detected state changed
+    StateChanged {
+        /// Previous state (None before the first detection)
+        from: Option<State>,
+        /// New state
+        to: State,
+    },
+    /// A confirmation dialog appeared
+    ConfirmAppeared(ConfirmInfo),
+    /// The current confirmation dialog is no longer visible
+    ConfirmDismissed,
+    /// A new tool call appeared
+    ToolStarted(ClaudeCodeToolOutput),
+    /// The current tool call finished
+    ToolCompleted {
+        /// Last parsed tool output
+        tool: ClaudeCodeToolOutput,
+        /// Duration reported by Claude Code, or measured between frames
+        #[serde(skip_serializing_if = "Option::is_none")]
+        duration_ms: Option<f64>,
+    },
+    /// Status bar text appeared, changed or disappeared (spinner frames are ignored)
+    StatusTextChanged {
+        /// Previous status text
+        from: Option<String>,
+        /// New status bar (None when it disappeared)
+        to: Option<ClaudeCodeStatus>,
+    },
+    /// Terminal title task or processing flag changed (spinner frames are ignored)
+    TitleChanged(ClaudeCodeTitle),
+    /// Claude Code version was read from the startup banner
+    VersionDetected {
+        /// Detected version
+        version: ClaudeCodeVersion,
+    },
+    /// A response sent with [`Session::respond`] took effect (or timed out
+    /// with the dialog still open)
+    ConfirmResolved {
+        /// Dialog the response was sent to
+        info: ConfirmInfo,
+        /// Outcome and the frame evidence for it
+        resolution: Box<ConfirmResolution>,
+    },
+}
+
+/// Tool call currently being tracked
+#[derive(Debug, Clone)]
+struct TrackedTool {
+    tool: ClaudeCodeToolOutput,
+    /// Header line without the completion suffix
+    header: String,
+    /// Blocks on screen with this header
+    occurrences: usize,
+    started_at: Instant,
+    completed: bool,
+}
+
+impl TrackedTool {
+    /// Same call as `other`, whose header is `header` on `occurrences` blocks
+    ///
+    /// Params are not compared: box params render over several frames. The
+    /// same command run again shows as one more block with this header, or
+    /// (once the first scrolled away) as a completed call running anew.
+    fn is_same_call(&self, other: &ClaudeCodeToolOutput, header: &str, occurrences: usize) -> bool {
+        self.tool.tool_name == other.tool_name
+            && self.tool.mcp_server == other.mcp_server
+            && self.header == header
+            && occurrences <= self.occurrences
+            && !(self.tool.status == ToolStatus::Completed && other.status == ToolStatus::Running)
+    }
+}
+
+/// Header line of a tool block, without "(completed in 0.5s)"
+fn call_header(result: &ToolOutputResult) -> String {
+    let header = result.raw.lines().next().unwrap_or("").trim();
+    match header.find(" (completed in") {
+        Some(i) => header[..i].trim_end().to_string(),
+        None => header.to_string(),
+    }
+}
+
+/// Stateful Claude Code session tracker
+///
+/// Feed it snapshots with [`Session::update`] (or raw PTY output with
+/// [`Session::feed`] when created with [`Session::with_screen`]) and it
+/// returns the events that happened since the previous frame.
+pub struct Session {
+    state_parser: ClaudeCodeStateParser,
+    confirm_parser: ClaudeCodeConfirmParser,
+    tool_parser: ClaudeCodeToolOutputParser,
+    status_parser: ClaudeCodeStatusParser,
+    title_parser: ClaudeCodeTitleParser,
+    screen: Option<Screen>,
+    state: Option<State>,
+    confirm: Option<ConfirmInfo>,
+    tool: Option<TrackedTool>,
+    status: Option<ClaudeCodeStatus>,
+    title: Option<ClaudeCodeTitle>,
+    version: Option<ClaudeCodeVersion>,
+    transaction: Option<ConfirmTransaction>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    /// Create a session that is driven by snapshots
+    pub fn new() -> Self {
+        Self {
+            state_parser: ClaudeCodeStateParser::new(),
+            confirm_parser: ClaudeCodeConfirmParser::new(),
+            tool_parser: ClaudeCodeToolOutputParser::new(),
+            status_parser: ClaudeCodeStatusParser::new(),
+            title_parser: ClaudeCodeTitleParser::new(),
+            screen: None,
+            state: None,
+            confirm: None,
+            tool: None,
+            status: None,
+            title: None,
+            version: None,
+            transaction: None,
+        }
+    }
+
+    /// Create a session that renders raw PTY output on a virtual screen
+    pub fn with_screen(rows: usize, cols: usize) -> Self {
+        let mut session = Self::new();
+        session.screen = Some(Screen::new(rows, cols));
+        session
+    }
+
+    /// Set the initial state (e.g. `Starting` for a freshly spawned CLI)
+    pub fn with_initial_state(mut self, state: State) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// Set the Claude Code version instead of detecting it from the banner
+    pub fn with_version(mut self, version: ClaudeCodeVersion) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Claude Code version used to select fingerprints
+    pub fn version(&self) -> Option<ClaudeCodeVersion> {
+        self.version
+    }
+
+    /// Current state (if any has been detected)
+    pub fn state(&self) -> Option<State> {
+        self.state
+    }
+
+    /// Currently visible confirmation dialog
+    pub fn confirm(&self) -> Option<&ConfirmInfo> {
+        self.confirm.as_ref()
+    }
+
+    /// Current tool call (if any)
+    pub fn tool(&self) -> Option<&ClaudeCodeToolOutput> {
+        self.tool.as_ref().map(|t| &t.tool)
+    }
+
+    /// Current status bar
+    pub fn status(&self) -> Option<&ClaudeCodeStatus> {
+        self.status.as_ref()
+    }
+
+    /// Current terminal title
+    pub fn title(&self) -> Option<&ClaudeCodeTitle> {
+        self.title.as_ref()
+    }
+
+    /// Virtual screen (only for sessions created with [`Session::with_screen`])
+    pub fn screen(&self) -> Option<&Screen> {
+        self.screen.as_ref()
+    }
+
+    /// Mutable virtual screen, e.g. to resize it
+    pub fn screen_mut(&mut self) -> Option<&mut Screen> {
+        self.screen.as_mut()
+    }
+
+    /// Key encoder for the input modes the screen tracked
+    ///
+    /// Snapshot-driven sessions assume Claude Code's default modes.
+    pub fn key_encoder(&self) -> KeyEncoder {
+        self.screen
+            .as_ref()
+            .map_or_else(KeyEncoder::new, |s| KeyEncoder::with_modes(s.input_modes()))
+    }
+
+    /// Input actions encoded for the tracked input modes
+    pub fn input(&self) -> ClaudeCodeInput {
+        ClaudeCodeInput::with_key_encoder(self.key_encoder())
+    }
+
+    /// Format a response to the visible confirmation dialog
+    pub fn format_response(
+        &self,
+        response: &ConfirmResponse,
+    ) -> Result<String, ConfirmResponseError> {
+        let info = self
+            .confirm
+            .as_ref()
+            .ok_or(ConfirmResponseError::NoDialog)?;
+        self.confirm_parser
+            .format_response_with(info, response, &self.key_encoder())
+    }
+
+    /// Format a response to the visible dialog and watch for its effect
+    ///
+    /// Send the returned keys; later frames emit
+    /// [`SessionEvent::ConfirmResolved`] once the dialog closes, another one
+    /// appears, or the transaction times out with the dialog still open.
+    ///
+    /// A deny-with-feedback response resolves as
+    /// [`ConfirmOutcome::AwaitingFeedback`] once its field opens; respond
+    /// again with it to type the text.
+    pub fn respond(&mut self, response: ConfirmResponse) -> Result<String, ConfirmResponseError> {
+        self.respond_at(response, Instant::now())
+    }
+
+    /// Respond to the visible dialog, sending at `now`
+    pub fn respond_at(
+        &mut self,
+        response: ConfirmResponse,
+        now: Instant,
+    ) -> Result<String, ConfirmResponseError> {
+        let keys = self.format_response(&response)?;
+        let info = self.confirm.clone().ok_or(ConfirmResponseError::NoDialog)?;
+        self.transaction = Some(ConfirmTransaction::new(info, response).started_at(now));
+        Ok(keys)
+    }
+
+    /// Response awaiting its effect (see [`Session::respond`])
+    pub fn transaction(&self) -> Option<&ConfirmTransaction> {
+        self.transaction.as_ref()
+    }
+
+    /// Feed raw PTY output and return the resulting events
+    ///
+    /// Sessions without a screen ignore the bytes and return no events.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<SessionEvent> {
+        self.feed_at(bytes, Instant::now())
+    }
+
+    /// Feed raw PTY output received at `now`
+    pub fn feed_at(&mut self, bytes: &[u8], now: Instant) -> Vec<SessionEvent> {
+        let Some(screen) = self.screen.as_mut() else {
+            return Vec::new();
+        };
+        screen.feed(bytes);
+        let context = screen.context();
+        let title = screen.title().map(str::to_string);
+
+        let mut events = self.update_at(&context, now);
+        if let Some(title) = title {
+            events.extend(self.update_title(&title));
+        }
+        events
+    }
+
+    /// Process a snapshot and return the resulting events
+    pub fn update(&mut self, context: &ParserContext) -> Vec<SessionEvent> {
+        self.update_at(context, Instant::now())
+    }
+
+    /// Process a snapshot taken at `now`
+    pub fn update_at(&mut self, context: &ParserContext, now: Instant) -> Vec<SessionEvent> {
+        let mut events = Vec::new();
+
+        // Version: the banner scrolls away, so remember it once seen
+        if self.version.is_none() {
+            if let Some(version) = context
+                .version()
+                .or_else(|| ClaudeCodeVersion::detect(context.lines()))
+            {
+                events.push(SessionEvent::VersionDetected { version });
+                self.version = Some(version);
+            }
+        }
+
+        // Inject what the session knows into a borrowed view; all parsers run
+        // on the same context so cached per-frame work is shared
+        let inject_state = context.current_state.is_none() && self.state.is_some();
+        let inject_version = context.version().is_none() && self.version.is_some();
+        let view;
+        let context = if inject_state || inject_version {
+            let mut v = context.view();
+            v.current_state = v.current_state.or(self.state);
+            if let (None, Some(version)) = (v.version(), self.version) {
+                v = v.with_version(version);
+            }
+            view = v;
+            &view
+        } else {
+            context
+        };
+
+        // State
+        if let Some(result) = self.state_parser.detect_state(context) {
+            if self.state != Some(result.state) {
+                events.push(SessionEvent::StateChanged {
+                    from: self.state,
+                    to: result.state,
+                });
+                self.state = Some(result.state);
+            }
+        }
+
+        // Confirmation dialog
+        let confirm = self.confirm_parser.detect_confirm(context);
+        match (&self.confirm, confirm) {
+            (None, Some(new)) => {
+                events.push(SessionEvent::ConfirmAppeared(new.clone()));
+                self.confirm = Some(new);
+            }
+            (Some(old), Some(new)) => {
+                if !old.is_same_dialog(&new) {
+                    events.push(SessionEvent::ConfirmDismissed);
+                    events.push(SessionEvent::ConfirmAppeared(new.clone()));
+                }
+                self.confirm = Some(new);
+            }
+            (Some(_), None) => {
+                events.push(SessionEvent::ConfirmDismissed);
+                self.confirm = None;
+            }
+            (None, None) => {}
+        }
+
+        // Response sent with `respond`
+        if let Some(transaction) = &self.transaction {
+            let resolution = transaction.observe(context, self.confirm.as_ref(), self.state);
+            if resolution.outcome != ConfirmOutcome::StillOpen || transaction.is_expired(now) {
+                events.push(SessionEvent::ConfirmResolved {
+                    info: transaction.info().clone(),
+                    resolution: Box::new(resolution),
+                });
+                self.transaction = None;
+            }
+        }
+
+        // Tool calls
+        // The newest block is the one that can still be running
+        let mut blocks = self.tool_parser.parse_all(context);
+        let tool = blocks.pop().map(|newest| {
+            let header = call_header(&newest);
+            let occurrences = 1 + blocks.iter().filter(|b| call_header(b) == header).count();
+            (newest, header, occurrences)
+        });
+        self.update_tool(tool, now, &mut events);
+
+        // Status bar
+        let status = self.status_parser.parse(context);
+        let old_text = self.status.as_ref().map(|s| &s.status_text);
+        let new_text = status.as_ref().map(|s| &s.status_text);
+        if old_text != new_text {
+            events.push(SessionEvent::StatusTextChanged {
+                from: old_text.cloned(),
+                to: status.clone(),
+            });
+        }
+        self.status = status;
+
+        events
+    }
+
+    /// Process a terminal title and return the resulting events
+    pub fn update_title(&mut self, title: &str) -> Vec<SessionEvent> {
+        let context = TitleParserContext::new(title);
+        let Some(result) = self.title_parser.parse(&context) else {
+            return Vec::new();
+        };
+        let new = result.data;
+
+        let changed = self.title.as_ref().is_none_or(|old| {
+            old.task_name != new.task_name || old.is_processing != new.is_processing
+        });
+        self.title = Some(new.clone());
+
+        if changed {
+            vec![SessionEvent::TitleChanged(new)]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn update_tool(
+        &mut self,
+        tool: Option<(ToolOutputResult, String, usize)>,
+        now: Instant,
+        events: &mut Vec<SessionEvent>,
+    ) {
+        match tool {
+            Some((result, header, occurrences)) => {
+                let tool = result.data;
+                let same_call = self
+                    .tool
+                    .as_ref()
+                    .is_some_and(|t| t.is_same_call(&tool, &header, occurrences));
+                if !same_call {
+                    self.complete_tool(now, events);
+                    events.push(SessionEvent::ToolStarted(tool.clone()));
+                    self.tool = Some(TrackedTool {
+                        tool: tool.clone(),
+                        header,
+                        occurrences,
+                        started_at: now,
+                        completed: false,
+                    });
+                }
+                if let Some(tracked) = self.tool.as_mut() {
+                    tracked.tool = tool;
+                    tracked.occurrences = occurrences;
+                }
+                if self
+                    .tool
+                    .as_ref()
+                    .is_some_and(|t| t.tool.status == ToolStatus::Completed)
+                {
+                    self.complete_tool(now, events);
+                }
+            }
+            None => {
+                // The turn ended, so any tool still marked as running is done
+                if self.state == Some(State::Idle) {
+                    self.complete_tool(now, events);
+                }
+            }
+        }
+    }
+
+    /// Emit `ToolCompleted` for the tracked tool, once
+    fn complete_tool(&mut self, now: Instant, events: &mut Vec<SessionEvent>) {
+        if let Some(tracked) = self.tool.as_mut() {
+            if tracked.completed {
+                return;
+            }
+            tracked.completed = true;
+            let duration_ms = tracked.tool.duration_ms.or_else(|| {
+                Some(
+                    now.saturating_duration_since(tracked.started_at)
+                        .as_secs_f64()
+                        * 1000.0,
+                )
+            });
+            events.push(SessionEvent::ToolCompleted {
+                tool: tracked.tool.clone(),
+                duration_ms,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn make_context(lines: &[&str]) -> ParserContext<'static> {
+        ParserContext::new(lines.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[test]
+    fn test_state_changed() {
+        let mut session = Session::new();
+
+        let events = session.update(&make_context(&["❯ "]));
+        assert!(matches!(
+            events.as_slice(),
+            [SessionEvent::StateChanged {
+                from: None,
+                to: State::Idle
+            }]
+        ));
+
+        // Same frame again produces nothing
+        assert!(session.update(&make_context(&["❯ "])).is_empty());
+
+        let events = session.update(&make_context(&["· Pondering… (esc to interrupt)"]));
+        assert!(matches!(
+            events[0],
+            SessionEvent::StateChanged {
+                from: Some(State::Idle),
+                to: State::Thinking
+            }
+        ));
+        assert_eq!(session.state(), Some(State::Thinking));
+    }
+
+    #[test]
+    fn test_status_text_changed_ignores_spinner_frames() {
+        let mut session = Session::new();
+
+        let events = session.update(&make_context(&["· Pondering… (esc to interrupt)"]));
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, SessionEvent::StatusTextChanged { to: Some(s), .. } if s.status_text == "Pondering…")));
+
+        let events = session.update(&make_context(&["✻ Pondering… (esc to interrupt)"]));
+        assert!(events.is_empty());
+
+        let events = session.update(&make_context(&["✶ Brewing… (esc to interrupt)"]));
+        assert_eq!(events.len(), 1);
+
+        let events = session.update(&make_context(&["❯ "]));
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, SessionEvent::StatusTextChanged { to: None, .. })));
+    }
+
+    #[test]
+    fn test_confirm_appeared_and_dismissed() {
+        let mut session = Session::new();
+        let dialog = [
+            "xjp-mcp - xjp_secret_get(key: \"test\")",
+            "❯ 1. Yes, allow this action",
+            "  2. Yes, allow for this session",
+            "  3. No, deny this action",
+            "Esc to cancel",
+        ];
+
+        let events = session.update(&make_context(&dialog));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            SessionEvent::ConfirmAppeared(info) if info.tool.as_ref().unwrap().name == "xjp_secret_get"
+        )));
+        assert!(session.confirm().is_some());
+
+        // Selection moved, same dialog
+        let moved = [
+            "xjp-mcp - xjp_secret_get(key: \"test\")",
+            "  1. Yes, allow this action",
+            "❯ 2. Yes, allow for this session",
+            "  3. No, deny this action",
+            "Esc to cancel",
+        ];
+        assert!(session.update(&make_context(&moved)).is_empty());
+
+        let events = session.update(&make_context(&["❯ "]));
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, SessionEvent::ConfirmDismissed)));
+        assert!(session.confirm().is_none());
+    }
+
+    #[test]
+    fn test_consecutive_bash_dialogs_are_distinct() {
+        let mut session = Session::new();
+        let bash_dialog = |command: &str| {
+            make_context(&[
+                " Bash command",
+                "",
+                &format!("   {}", command),
+                "",
+                "   Run it",
+                "",
+                " Do you want to proceed?",
+                " ❯ 1. Yes",
+                "   2. No, and tell Claude what to do differently (esc)",
+            ])
+        };
+        session.update(&bash_dialog("cargo test"));
+        let events = session.update(&bash_dialog("rm -rf target"));
+        assert!(matches!(events[..], [
+            SessionEvent::ConfirmDismissed,
+            SessionEvent::ConfirmAppeared(ref info),
+        ] if info.tool.as_ref().unwrap().params["command"] == "rm -rf target"));
+    }
+
+    #[test]
+    fn test_tool_started_and_completed() {
+        let mut session = Session::new();
+        let start = Instant::now();
+
+        let events = session.update_at(&make_context(&["⏺ Bash", "  │ command: \"ls\""]), start);
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, SessionEvent::ToolStarted(tool) if tool.tool_name == "Bash")));
+
+        let events = session.update_at(
+            &make_context(&["⏺ Bash (completed in 0.5s)", "  │ command: \"ls\""]),
+            start + Duration::from_millis(700),
+        );
+        assert!(events.iter().any(|e| matches!(
+            e,
+            SessionEvent::ToolCompleted { duration_ms: Some(d), .. } if *d == 500.0
+        )));
+
+        // Completion is reported once
+        let events = session.update_at(
+            &make_context(&["⏺ Bash (completed in 0.5s)", "  │ command: \"ls\""]),
+            start + Duration::from_millis(800),
+        );
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_tracks_newest_tool_block() {
+        let mut session = Session::new();
+        let start = Instant::now();
+        let read = ["⏺ Read(src/lib.rs)", "  ⎿  Read 120 lines"];
+
+        session.update_at(&make_context(&read), start);
+        let frame = [read[0], read[1], "⏺ Bash(cargo test)", "  ⎿  Running…"];
+        let events = session.update_at(&make_context(&frame), start);
+        let command = Some(&serde_json::Value::from("cargo test"));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            SessionEvent::ToolStarted(tool) if tool.params.get("command") == command
+        )));
+
+        let frame = [
+            read[0],
+            read[1],
+            "⏺ Bash(cargo test)",
+            "  ⎿  test result: ok",
+            "⏺ Bash(cargo clippy)",
+        ];
+        let events = session.update_at(&make_context(&frame), start);
+        let started: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                SessionEvent::ToolStarted(tool) => tool.params.get("command"),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(started, [&serde_json::Value::from("cargo clippy")]);
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, SessionEvent::ToolCompleted { .. })));
+    }
+
+    #[test]
+    fn test_repeated_call_is_reported_again() {
+        let mut session = Session::new();
+        let start = Instant::now();
+        let started = |events: &[SessionEvent]| {
+            events
+                .iter()
+                .filter(|e| matches!(e, SessionEvent::ToolStarted(_)))
+                .count()
+        };
+
+        let first = ["⏺ Bash(cargo test)", "  ⎿  test result: ok"];
+        assert_eq!(started(&session.update_at(&make_context(&first), start)), 1);
+        let frame = [first[0], first[1], "⏺ Bash(cargo test)", "  ⎿  Running…"];
+        let events = session.update_at(&make_context(&frame), start);
+        assert_eq!(started(&events), 1);
+        assert!(matches!(events[0], SessionEvent::ToolCompleted { .. }));
+        assert!(session.update_at(&make_context(&frame), start).is_empty());
+
+        // The first block scrolled away: one block left, still the same call
+        let frame = ["⏺ Bash(cargo test)", "  ⎿  Running…", "     running 12 tests"];
+        assert!(session.update_at(&make_context(&frame), start).is_empty());
+
+        // Completed, then the same header running again in its place
+        let done = ["⏺ Bash (completed in 0.5s)", "  │ command: \"ls\""];
+        session.update_at(&make_context(&done), start);
+        let events = session.update_at(&make_context(&["⏺ Bash", "  │ command: \"ls\""]), start);
+        assert_eq!(started(&events), 1);
+    }
+
+    #[test]
+    fn test_box_params_rendering_is_one_call() {
+        let mut session = Session::new();
+
+        let events = session.update(&make_context(&["⏺ Edit"]));
+        assert_eq!(events.len(), 1);
+        let frame = ["⏺ Edit", "  │ file_path: \"/src/main.rs\""];
+        assert!(session.update(&make_context(&frame)).is_empty());
+        let frame = [frame[0], frame[1], "  │ old_string: \"a\""];
+        assert!(session.update(&make_context(&frame)).is_empty());
+    }
+
+    #[test]
+    fn test_tool_replaced_measures_duration() {
+        let mut session = Session::new();
+        let start = Instant::now();
+
+        session.update_at(&make_context(&["⏺ Bash(git status)"]), start);
+        let events = session.update_at(
+            &make_context(&["⏺ Read(src/lib.rs)"]),
+            start + Duration::from_millis(250),
+        );
+
+        assert!(matches!(
+            &events[0],
+            SessionEvent::ToolCompleted { tool, duration_ms: Some(d) }
+                if tool.tool_name == "Bash" && *d == 250.0
+        ));
+        assert!(matches!(
+            &events[1],
+            SessionEvent::ToolStarted(tool) if tool.tool_name == "Read"
+        ));
+    }
+
+    #[test]
+    fn test_title_changed() {
+        let mut session = Session::new();
+
+        let events = session.update_title("⠐ Initial Greeting");
+        assert!(matches!(
+            &events[..],
+            [SessionEvent::TitleChanged(title)] if title.is_processing
+        ));
+
+        // Spinner animation only
+        assert!(session.update_title("⠂ Initial Greeting").is_empty());
+
+        let events = session.update_title("✳ Initial Greeting");
+        assert!(matches!(
+            &events[..],
+            [SessionEvent::TitleChanged(title)] if !title.is_processing
+        ));
+    }
+
+    #[test]
+    fn test_version_detected_from_banner() {
+        let mut session = Session::new();
+        let events = session.update(&make_context(&[" ▐▛███▜▌   Claude Code v2.0.14", "❯ "]));
+        assert!(matches!(
+            events[0],
+            SessionEvent::VersionDetected { version } if version == ClaudeCodeVersion::new(2, 0, 14)
+        ));
+
+        // Banner scrolled away: the version is kept and not reported again
+        let events = session.update(&make_context(&["· Pondering… (esc to interrupt)"]));
+        assert!(!events
+            .iter()
+            .any(|e| matches!(e, SessionEvent::VersionDetected { .. })));
+        assert_eq!(session.version(), Some(ClaudeCodeVersion::new(2, 0, 14)));
+    }
+
+    #[test]
+    fn test_initial_state_enables_trust_detection() {
+        let mut session = Session::new().with_initial_state(State::Starting);
+        let events = session.update(&make_context(&[
+            "Do you trust this project?",
+            "Yes, proceed",
+            "Enter to confirm",
+        ]));
+        assert!(events
+            .iter()
+            .all(|e| !matches!(e, SessionEvent::StateChanged { .. })));
+        assert_eq!(session.state(), Some(State::Starting));
+    }
+
+    #[test]
+    fn test_feed_bytes() {
+        let mut session = Session::with_screen(10, 60);
+
+        let events = session.feed("\x1b]0;⠐ Task\x07✻ Thinking… (esc to interrupt)\r\n".as_bytes());
+        assert!(events.iter().any(|e| matches!(
+            e,
+            SessionEvent::StateChanged {
+                to: State::Thinking,
+                ..
+            }
+        )));
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, SessionEvent::TitleChanged(_))));
+
+        let events = session.feed("\x1b[1A\x1b[2K❯ ".as_bytes());
+        assert!(events.iter().any(|e| matches!(
+            e,
+            SessionEvent::StateChanged {
+                from: Some(State::Thinking),
+                to: State::Idle
+            }
+        )));
+
+        // Snapshot-only sessions ignore bytes
+        assert!(Session::new().feed(b"anything").is_empty());
+    }
+
+    #[test]
+    fn test_format_response_uses_screen_input_modes() {
+        let mut session = Session::with_screen(10, 60);
+        assert_eq!(
+            session.format_response(&ConfirmResponse::deny()),
+            Err(ConfirmResponseError::NoDialog)
+        );
+
+        // Application cursor keys on, as set by the CLI
+        session.feed(
+            "\x1b[?1h\x1b[?2004hxjp-mcp - xjp_secret_get(key: \"test\")\r\n\
+             ❯ 1. Yes\r\n  2. No, and tell Claude what to do differently (esc)\r\n"
+                .as_bytes(),
+        );
+        assert!(session.confirm().is_some());
+        assert_eq!(
+            session.format_response(&ConfirmResponse::deny()).unwrap(),
+            "\x1bOB\r"
+        );
+    }
+
+    #[test]
+    fn test_respond_emits_confirm_resolved() {
+        let mut session = Session::new();
+        let dialog = [
+            "xjp-mcp - xjp_secret_get(key: \"test\")",
+            "❯ 1. Yes",
+            "  2. No, and tell Claude what to do differently (esc)",
+        ];
+        let start = Instant::now();
+        session.update_at(&make_context(&dialog), start);
+        assert_eq!(session.respond_at(ConfirmResponse::confirm(), start).unwrap(), "\r");
+        assert!(session.transaction().is_some());
+
+        // Keys not processed yet: nothing until the timeout
+        assert!(session
+            .update_at(&make_context(&dialog), start + Duration::from_millis(100))
+            .is_empty());
+
+        let events = session.update_at(
+            &make_context(&[
+                "⏺ xjp-mcp - xjp_secret_get (MCP)(key: \"test\")",
+                "  ⎿  Running…",
+            ]),
+            start + Duration::from_millis(200),
+        );
+        assert!(events.iter().any(|e| matches!(
+            e,
+            SessionEvent::ConfirmResolved { resolution, .. }
+                if resolution.outcome == ConfirmOutcome::Accepted
+        )));
+        assert!(session.transaction().is_none());
+
+        // A dialog that never closes resolves as still open once expired
+        session.update_at(&make_context(&dialog), start);
+        session.respond_at(ConfirmResponse::confirm(), start).unwrap();
+        let events = session.update_at(&make_context(&dialog), start + Duration::from_secs(5));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            SessionEvent::ConfirmResolved { resolution, .. }
+                if resolution.outcome == ConfirmOutcome::StillOpen
+        )));
+    }
+
+    #[test]
+    fn test_deny_with_feedback_in_two_steps() {
+        let mut session = Session::new();
+        let mut dialog = vec![
+            "Bash command",
+            "  rm -rf build",
+            "Do you want to proceed?",
+            "❯ 1. Yes",
+            "  2. No, and tell Claude what to do differently (esc)",
+        ];
+        session.update(&make_context(&dialog));
+        let response = ConfirmResponse::deny_with_feedback("use cargo clean");
+        assert_eq!(session.respond(response.clone()).unwrap(), "\x1b[B\r");
+
+        dialog[3] = "  1. Yes";
+        dialog[4] = "❯ 2. No, and tell Claude what to do differently (esc)";
+        dialog.extend(["", "  > Tell Claude what to do differently"]);
+        let events = session.update(&make_context(&dialog));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            SessionEvent::ConfirmResolved { resolution, .. }
+                if resolution.outcome == ConfirmOutcome::AwaitingFeedback
+        )));
+        assert_eq!(session.respond(response).unwrap(), "use cargo clean\r");
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let event = SessionEvent::StateChanged {
+            from: Some(State::Idle),
+            to: State::Thinking,
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["event"], "state_changed");
+        assert_eq!(json["to"], "thinking");
+
+        let event = SessionEvent::StatusTextChanged {
+            from: Some("Pondering…".to_string()),
+            to: None,
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["event"], "status_text_changed");
+    }
+}
@@ -2,21 +2,14 @@
 //!
 //! Detects Claude Code CLI states from terminal output.
 
-use once_cell::sync::Lazy;
-use regex::Regex;
-
+use super::classifier::StateClassifier;
+use super::fingerprint::FingerprintRegistry;
 use super::types::{
     ConfirmType, ParserContext, ParserMeta, State, StateDetectionResult, StateMeta, StateParser,
 };
 
-/// Regex patterns for state detection
-static OPTION_CONFIRM_PATTERN: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?mi)^[\s❯>]*1\.\s*(Yes|Allow)").unwrap());
-
-static YES_NO_CONFIRM_PATTERN: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?i)\[Y/n\]|\(yes/no\)|Allow\?|Do you want to proceed").unwrap());
-
-static PROMPT_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[❯>]\s*").unwrap());
+/// Fingerprint that identifies Y/n style confirmations
+const YES_NO_FINGERPRINT: &str = "claude-code.confirm.yes-no";
 
 /// Claude Code state parser
 ///
@@ -27,8 +20,13 @@ static PROMPT_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[❯>]\s*").unwr
 /// - `tool_running`: Running a tool
 /// - `confirming`: Waiting for user confirmation
 /// - `error`: Error state
+///
+/// Detection is driven by the `state_weights` of the fingerprint registry
+/// (see [`StateClassifier`]); every fingerprint that fired is reported as
+/// evidence on the result.
 pub struct ClaudeCodeStateParser {
     meta: ParserMeta,
+    classifier: StateClassifier,
 }
 
 impl Default for ClaudeCodeStateParser {
@@ -40,6 +38,16 @@ impl Default for ClaudeCodeStateParser {
 impl ClaudeCodeStateParser {
     /// Create a new Claude Code state parser
     pub fn new() -> Self {
+        Self::with_classifier(StateClassifier::new())
+    }
+
+    /// Create a state parser over a custom fingerprint registry
+    pub fn with_registry(registry: FingerprintRegistry) -> Self {
+        Self::with_classifier(StateClassifier::with_registry(registry))
+    }
+
+    /// Create a state parser with a configured classifier
+    pub fn with_classifier(classifier: StateClassifier) -> Self {
         Self {
             meta: ParserMeta {
                 name: "claude-code-state".to_string(),
@@ -47,29 +55,39 @@ impl ClaudeCodeStateParser {
                 priority: 100,
                 version: "1.0.0".to_string(),
             },
+            classifier,
         }
     }
 
-    /// Check if text indicates running state (spinner visible)
-    fn is_running(&self, text: &str) -> bool {
-        text.contains("esc to interrupt")
-    }
-
-    /// Check for options-style confirmation
-    fn is_option_confirm(&self, text: &str) -> bool {
-        OPTION_CONFIRM_PATTERN.is_match(text) && text.contains("Esc to cancel")
-    }
-
-    /// Check for Y/n style confirmation
-    fn is_yes_no_confirm(&self, text: &str) -> bool {
-        YES_NO_CONFIRM_PATTERN.is_match(text)
+    /// Get the underlying classifier
+    pub fn classifier(&self) -> &StateClassifier {
+        &self.classifier
     }
 
-    /// Check if any line has a prompt indicator
-    fn has_prompt(&self, lines: &[String]) -> bool {
-        lines
-            .iter()
-            .any(|line| PROMPT_PATTERN.is_match(line.trim()))
+    /// Build metadata for the detected state
+    fn state_meta(&self, result: &StateDetectionResult) -> Option<StateMeta> {
+        match result.state {
+            // Trust dialog during startup (auto-confirm)
+            State::Starting => Some(StateMeta {
+                needs_trust_confirm: Some(true),
+                confirm_type: None,
+            }),
+            State::Confirming => {
+                let is_options = result
+                    .supporting_evidence()
+                    .any(|e| e.rule != YES_NO_FINGERPRINT);
+                let confirm_type = if is_options {
+                    ConfirmType::Options
+                } else {
+                    ConfirmType::YesNo
+                };
+                Some(StateMeta {
+                    needs_trust_confirm: None,
+                    confirm_type: Some(confirm_type),
+                })
+            }
+            _ => None,
+        }
     }
 }
 
@@ -79,78 +97,22 @@ impl StateParser for ClaudeCodeStateParser {
     }
 
     fn detect_state(&self, context: &ParserContext) -> Option<StateDetectionResult> {
-        let text = context.text();
-
-        // Check for trust dialog during startup (auto-confirm)
-        if context.current_state == Some(State::Starting)
-            && text.contains("Yes, proceed")
-            && text.contains("Enter to confirm")
-        {
-            return Some(
-                StateDetectionResult::new(State::Starting, 0.95).with_meta(StateMeta {
-                    needs_trust_confirm: Some(true),
-                    confirm_type: None,
-                }),
-            );
-        }
-
-        // Check for running state (spinner visible)
-        let is_running = self.is_running(&text);
-
-        // Check for confirmation dialog
-        // Note: ❯ and > are prompt indicators, need to match them before option number
-        let is_option_confirm = self.is_option_confirm(&text);
-        let is_yes_no_confirm = self.is_yes_no_confirm(&text);
-
-        if is_option_confirm || is_yes_no_confirm {
-            let confirm_type = if is_option_confirm {
-                ConfirmType::Options
-            } else {
-                ConfirmType::YesNo
-            };
-
-            return Some(
-                StateDetectionResult::new(State::Confirming, 0.95).with_meta(StateMeta {
-                    needs_trust_confirm: None,
-                    confirm_type: Some(confirm_type),
-                }),
-            );
-        }
-
-        // Check for busy state (running tools/thinking)
-        if is_running {
-            // Determine if thinking or tool_running
-            // Tool running: "Tool:" or spinner with vertical bar
-            if text.contains("Tool:") || (text.contains('⏺') && text.contains('│')) {
-                return Some(StateDetectionResult::new(State::ToolRunning, 0.85));
-            }
-            return Some(StateDetectionResult::new(State::Thinking, 0.9));
-        }
-
-        // Check for idle state (prompt visible, no running indicator)
-        // Match prompt: ❯ or > at start of line (with optional trailing space/content)
-        if self.has_prompt(&context.last_lines) && !is_running {
-            return Some(StateDetectionResult::new(State::Idle, 0.9));
-        }
-
-        // Check for error state
-        if text.contains("Error:") || text.contains("error:") || text.contains('✖') {
-            return Some(StateDetectionResult::new(State::Error, 0.7));
-        }
-
-        None
+        let mut result = self.classifier.classify(context)?;
+        result.meta = self.state_meta(&result);
+        Some(result)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ClaudeCodeVersion;
 
-    fn make_context(lines: &[&str]) -> ParserContext {
+    fn make_context(lines: &[&str]) -> ParserContext<'static> {
         ParserContext::new(lines.iter().map(|s| s.to_string()).collect())
     }
 
-    fn make_context_with_state(lines: &[&str], state: State) -> ParserContext {
+    fn make_context_with_state(lines: &[&str], state: State) -> ParserContext<'static> {
         ParserContext::new(lines.iter().map(|s| s.to_string()).collect()).with_state(state)
     }
 
@@ -289,6 +251,23 @@ mod tests {
         assert_eq!(result.unwrap().state, State::Error);
     }
 
+    #[test]
+    fn test_failed_in_output_is_not_an_error() {
+        let parser = ClaudeCodeStateParser::new();
+
+        let context = make_context(&[
+            "⏺ Bash(cargo test)",
+            "  ⎿  test result: FAILED. 3 tests failed",
+            "",
+            "❯ ",
+        ]);
+        assert_eq!(parser.detect_state(&context).unwrap().state, State::Idle);
+
+        // Weak keywords alone don't reach the reporting threshold
+        let context = make_context(&["3 tests failed", "ENOENT: no such file"]);
+        assert!(parser.detect_state(&context).is_none());
+    }
+
     #[test]
     fn test_no_detection() {
         let parser = ClaudeCodeStateParser::new();
@@ -297,4 +276,71 @@ mod tests {
         let result = parser.detect_state(&context);
         assert!(result.is_none());
     }
+
+    #[test]
+    fn test_evidence_explains_confidence() {
+        let parser = ClaudeCodeStateParser::new();
+
+        let context = make_context(&["some output", "❯ "]);
+        let result = parser.detect_state(&context).unwrap();
+        assert_eq!(result.evidence.len(), 1);
+        let evidence = &result.evidence[0];
+        assert_eq!(evidence.rule, "claude-code.prompt.input");
+        assert_eq!(evidence.lines, vec![1]);
+        assert!((evidence.contribution - result.confidence).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_evidence_for_tool_running() {
+        let parser = ClaudeCodeStateParser::new();
+
+        let context = make_context(&["⏺ Running command │ ls -la", "esc to interrupt"]);
+        let result = parser.detect_state(&context).unwrap();
+        assert_eq!(result.state, State::ToolRunning);
+
+        let fired: Vec<&str> = result
+            .supporting_evidence()
+            .map(|e| e.rule.as_str())
+            .collect();
+        assert_eq!(
+            fired,
+            vec!["claude-code.statusbar.running", "claude-code.tool.running-marker"]
+        );
+        let total: f64 = result.evidence.iter().map(|e| e.contribution).sum();
+        assert!((total - result.confidence).abs() < 1e-9);
+        // Status bar 0.9 * 0.78 and running marker 0.9 * 0.55, combined
+        // as 1 - (1 - 0.702) * (1 - 0.495)
+        assert!((result.confidence - 0.84951).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_competing_evidence_is_reported() {
+        let parser = ClaudeCodeStateParser::new();
+
+        // Prompt and error both visible: prompt wins, error is still listed
+        let context = make_context(&["Error: build failed", "❯ "]);
+        let result = parser.detect_state(&context).unwrap();
+        assert_eq!(result.state, State::Idle);
+
+        let error = result
+            .evidence
+            .iter()
+            .find(|e| e.rule == "claude-code.error.keywords")
+            .unwrap();
+        assert_eq!(error.state, State::Error);
+        assert_eq!(error.lines, vec![0]);
+        assert_eq!(error.contribution, 0.0);
+    }
+
+    #[test]
+    fn test_reports_version_profile() {
+        let parser = ClaudeCodeStateParser::new();
+
+        let context = make_context(&["Claude Code v2.0.14", "❯ "]);
+        let result = parser.detect_state(&context).unwrap();
+        assert_eq!(result.version, Some(ClaudeCodeVersion::new(2, 0, 14)));
+
+        let result = parser.detect_state(&make_context(&["❯ "])).unwrap();
+        assert_eq!(result.version, None);
+    }
 }
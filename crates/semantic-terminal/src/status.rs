@@ -82,14 +82,12 @@ impl StatusParser for ClaudeCodeStatusParser {
 
     fn can_parse(&self, context: &ParserContext) -> bool {
         context
-            .last_lines
-            .iter()
-            .any(|line| STATUS_PATTERN.is_match(line.trim()))
+            .trimmed_lines()
+            .any(|line| STATUS_PATTERN.is_match(line))
     }
 
     fn parse(&self, context: &ParserContext) -> Option<ClaudeCodeStatus> {
-        for line in &context.last_lines {
-            let trimmed = line.trim();
+        for trimmed in context.trimmed_lines() {
             if let Some(caps) = STATUS_PATTERN.captures(trimmed) {
                 let spinner = caps.get(1)?.as_str().to_string();
                 let status_text = caps.get(2)?.as_str().to_string();
@@ -114,7 +112,7 @@ impl StatusParser for ClaudeCodeStatusParser {
 mod tests {
     use super::*;
 
-    fn make_context(lines: &[&str]) -> ParserContext {
+    fn make_context(lines: &[&str]) -> ParserContext<'static> {
         ParserContext::new(lines.iter().map(|s| s.to_string()).collect())
     }
 
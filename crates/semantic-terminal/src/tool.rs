@@ -6,19 +6,30 @@ use once_cell::sync::Lazy;
 use regex::Regex;
 use std::collections::HashMap;
 
+use super::diff::FileDiff;
 use super::types::{
-    ClaudeCodeToolOutput, ParserContext, ParserMeta, ToolOutputParser, ToolOutputResult,
-    ToolStatus,
+    ClaudeCodeToolOutput, Lines, ParserContext, ParserMeta, ToolOutputParser, ToolOutputResult,
+    ToolResultSummary, ToolStatus,
 };
 
+/// Tool name in a header: "Bash", "mcp__github__create_issue", or the MCP
+/// display form "xjp-mcp - xjp_secret_get (MCP)"
+const TOOL_NAME: &str =
+    r"(?:(?P<server>\S+)\s+-\s+(?P<mcp>\w+)\s*\(MCP\)|(?P<name>mcp__[\w-]+|\w+))";
+
 /// Tool header patterns:
 /// - Box style: "⏺ Bash" or "⏺ Bash (completed in 0.5s)"
-static TOOL_HEADER_BOX_PATTERN: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"^⏺\s+(\w+)(?:\s+\(completed\s+in\s+([\d.]+)s?\))?$").unwrap());
+static TOOL_HEADER_BOX_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r"^⏺\s+{}(?:\s+\(completed\s+in\s+(?P<secs>[\d.]+)s?\))?$",
+        TOOL_NAME
+    ))
+    .unwrap()
+});
 
 /// Tool header inline style: "⏺ Bash(git status)" or "⏺ Search(pattern: \"*.ts\")"
 static TOOL_HEADER_INLINE_PATTERN: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"^⏺\s+(\w+)\((.*)\)$").unwrap());
+    Lazy::new(|| Regex::new(&format!(r"^⏺\s+{}\s*\((?P<args>.*)\)$", TOOL_NAME)).unwrap());
 
 /// Tool parameter line pattern: │ key: value
 /// Example: "  │ command: \"git status\""
@@ -30,6 +41,22 @@ static PARAM_LINE_PATTERN: Lazy<Regex> =
 static INLINE_OUTPUT_LINE_PATTERN: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^\s*⎿\s*(.+)$").unwrap());
 
+/// Result summary lines, after the "(ctrl+r to expand)" hint is removed
+static READ_SUMMARY_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^Read (\d+) lines?$").unwrap());
+static WROTE_SUMMARY_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^Wrote (\d+) lines? to (.+)$").unwrap());
+static UPDATED_SUMMARY_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^Updated (.+?) with (?:(\d+) additions?)?(?: and )?(?:(\d+) removals?)?$").unwrap()
+});
+static FOUND_SUMMARY_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^Found (\d+) (file|line|match)(?:s|es)?$").unwrap());
+static ERROR_SUMMARY_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"^Error: (.+)$").unwrap());
+static EXIT_CODE_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\bexit code:? (-?\d+)").unwrap());
+static EXPAND_HINT_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\s*\([^()]*to expand\)$").unwrap());
+
 /// Known tool names
 pub const KNOWN_TOOLS: &[&str] = &[
     "Bash",
@@ -48,6 +75,27 @@ pub const KNOWN_TOOLS: &[&str] = &[
     "TodoWrite",
 ];
 
+/// Built-in tools the transcript shows under another name: (shown, tool)
+///
+/// `⏺ Update(src/x.rs)` is an Edit; the first entry for a shown name is the
+/// tool [`ToolCall`](super::ToolCall) decodes it as.
+pub(crate) const DISPLAY_NAMES: &[(&str, &str)] = &[
+    ("Update", "Edit"),
+    ("Update", "MultiEdit"),
+    ("Fetch", "WebFetch"),
+];
+
+/// Tool a transcript name stands for (the name itself when not renamed)
+pub(crate) fn displayed_tool(name: &str) -> &str {
+    DISPLAY_NAMES
+        .iter()
+        .find(|(shown, _)| *shown == name)
+        .map_or(name, |(_, tool)| tool)
+}
+
+/// Inline arguments in `key: value` form (not `https://...` or `C:\...`)
+static KEY_VALUE_ARGS_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^\w+:(?:\s|")"#).unwrap());
+
 /// Tool style detected from header
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ToolStyle {
@@ -107,7 +155,7 @@ impl ClaudeCodeToolOutputParser {
         }
 
         // If it looks like key: value pairs, parse loosely
-        if trimmed.contains(':') {
+        if KEY_VALUE_ARGS_PATTERN.is_match(trimmed) {
             let parts = self.split_args(trimmed);
             let mut out = HashMap::new();
 
@@ -155,7 +203,7 @@ impl ClaudeCodeToolOutputParser {
         let mut current = String::new();
         let mut in_string: Option<char> = None;
 
-        for (i, ch) in args.chars().enumerate() {
+        for (i, ch) in args.char_indices() {
             if let Some(quote_char) = in_string {
                 if ch == quote_char && !args[..i].ends_with('\\') {
                     in_string = None;
@@ -190,138 +238,245 @@ impl ClaudeCodeToolOutputParser {
 
     /// Check if a tool name is known
     fn is_known_tool(&self, name: &str) -> bool {
-        KNOWN_TOOLS.contains(&name)
+        KNOWN_TOOLS.contains(&displayed_tool(name))
     }
-}
 
-impl ToolOutputParser for ClaudeCodeToolOutputParser {
-    fn meta(&self) -> &ParserMeta {
-        &self.meta
+    /// Parse a result summary line ("Read 120 lines", "Error: Exit code 1")
+    fn parse_summary(&self, line: &str) -> Option<ToolResultSummary> {
+        let line = EXPAND_HINT_PATTERN.replace(line.trim(), "");
+        let line = line.as_ref();
+
+        if let Some(caps) = READ_SUMMARY_PATTERN.captures(line) {
+            return Some(ToolResultSummary::Read {
+                lines: caps[1].parse().ok()?,
+            });
+        }
+        if let Some(caps) = WROTE_SUMMARY_PATTERN.captures(line) {
+            return Some(ToolResultSummary::Wrote {
+                lines: caps[1].parse().ok()?,
+                path: caps[2].to_string(),
+            });
+        }
+        if let Some(caps) = UPDATED_SUMMARY_PATTERN.captures(line) {
+            let count = |i: usize| caps.get(i).and_then(|m| m.as_str().parse().ok());
+            let (additions, removals) = (count(2), count(3));
+            if additions.is_none() && removals.is_none() {
+                return None;
+            }
+            return Some(ToolResultSummary::Updated {
+                path: caps[1].to_string(),
+                additions: additions.unwrap_or(0),
+                removals: removals.unwrap_or(0),
+            });
+        }
+        if let Some(caps) = FOUND_SUMMARY_PATTERN.captures(line) {
+            let unit = match &caps[2] {
+                "match" => "matches".to_string(),
+                unit => format!("{}s", unit),
+            };
+            return Some(ToolResultSummary::Found {
+                count: caps[1].parse().ok()?,
+                unit,
+            });
+        }
+        if let Some(caps) = ERROR_SUMMARY_PATTERN.captures(line) {
+            let message = caps[1].trim().to_string();
+            return Some(ToolResultSummary::Error {
+                exit_code: EXIT_CODE_PATTERN
+                    .captures(&message)
+                    .and_then(|c| c[1].parse().ok()),
+                message,
+            });
+        }
+        None
     }
 
-    fn can_parse(&self, context: &ParserContext) -> bool {
-        // Check if any line matches tool header pattern
-        context.last_lines.iter().any(|line| {
-            let trimmed = line.trim();
-            TOOL_HEADER_BOX_PATTERN.is_match(trimmed)
-                || TOOL_HEADER_INLINE_PATTERN.is_match(trimmed)
-                || INLINE_OUTPUT_LINE_PATTERN.is_match(trimmed)
-        })
+    /// Diff shown under an Edit or Write result, checked against its summary
+    fn parse_diff(&self, summary: &ToolResultSummary, lines: &[String]) -> Option<FileDiff> {
+        let (mut diff, expected) = match summary {
+            ToolResultSummary::Updated {
+                path,
+                additions,
+                removals,
+            } => (FileDiff::parse(path.as_str(), lines), (*additions, *removals)),
+            ToolResultSummary::Wrote { lines: count, path } => {
+                (FileDiff::parse_created(path.as_str(), lines), (*count, 0))
+            }
+            _ => return None,
+        };
+        if diff.hunks.is_empty() {
+            return None;
+        }
+        if (diff.added(), diff.removed()) != (expected.0 as usize, expected.1 as usize) {
+            diff.truncated = true;
+        }
+        Some(diff)
     }
 
-    fn parse(&self, context: &ParserContext) -> Option<ToolOutputResult> {
-        let lines = &context.last_lines;
+    /// Tool name and MCP server from header captures
+    ///
+    /// `mcp__server__tool` names are split like the display form, so both
+    /// give the bare tool name and its server.
+    fn tool_name(&self, caps: &regex::Captures) -> (String, Option<String>) {
+        if let (Some(server), Some(name)) = (caps.name("server"), caps.name("mcp")) {
+            return (name.as_str().to_string(), Some(server.as_str().to_string()));
+        }
+        let name = caps.name("name").map_or("", |m| m.as_str());
+        match name.strip_prefix("mcp__").and_then(|rest| rest.split_once("__")) {
+            Some((server, tool)) if !server.is_empty() && !tool.is_empty() => {
+                (tool.to_string(), Some(server.to_string()))
+            }
+            _ => (name.to_string(), None),
+        }
+    }
+
+    /// Parse every tool block in the context, top to bottom
+    ///
+    /// Each block owns its header and the parameter, `⎿` output and indented
+    /// continuation lines below it, up to the next header or the first line
+    /// that belongs to neither (assistant text, the prompt). Output lines
+    /// above the first header belong to a block whose header scrolled off;
+    /// they are skipped rather than attributed to the next block.
+    pub fn parse_all(&self, context: &ParserContext) -> Vec<ToolOutputResult> {
+        let lines = context.lines();
+        let mut results = Vec::new();
+        let mut i = 0;
+        while i < lines.len() {
+            let is_start = lines.get(i).is_some_and(|line| is_header(line.trim()));
+            match is_start.then(|| self.parse_block(lines, i)).flatten() {
+                Some(result) => {
+                    i = result.end_line;
+                    results.push(result);
+                }
+                None => i += 1,
+            }
+        }
+        results
+    }
+
+    /// Parse the block whose header is at `start`
+    fn parse_block(&self, lines: &Lines, start: usize) -> Option<ToolOutputResult> {
         let mut tool_name: Option<String> = None;
+        let mut mcp_server: Option<String> = None;
         let mut duration_ms: Option<f64> = None;
         let mut params: HashMap<String, serde_json::Value> = HashMap::new();
         let mut output_lines: Vec<String> = Vec::new();
-        let mut in_tool_block = false;
         let mut tool_style: Option<ToolStyle> = None;
         let mut raw_lines: Vec<String> = Vec::new();
+        let mut end_line = start + 1;
 
-        for line in lines {
+        for (i, line) in lines.iter().enumerate().skip(start) {
             let trimmed = line.trim();
 
+            // A header after the first starts the next block
+            if i > start && is_header(trimmed) {
+                break;
+            }
+
             // Check for box-style tool header
             if let Some(caps) = TOOL_HEADER_BOX_PATTERN.captures(trimmed) {
-                tool_name = Some(caps.get(1).unwrap().as_str().to_string());
-                if let Some(duration_match) = caps.get(2) {
+                let (name, server) = self.tool_name(&caps);
+                tool_name = Some(name);
+                mcp_server = server;
+                if let Some(duration_match) = caps.name("secs") {
                     // Convert seconds to milliseconds
                     if let Ok(secs) = duration_match.as_str().parse::<f64>() {
                         duration_ms = Some(secs * 1000.0);
                     }
                 }
-                in_tool_block = true;
                 tool_style = Some(ToolStyle::Box);
-                raw_lines.push(line.clone());
+                raw_lines.push(line.to_string());
                 continue;
             }
 
             // Check for inline-style tool header
             if let Some(caps) = TOOL_HEADER_INLINE_PATTERN.captures(trimmed) {
-                let name = caps.get(1).unwrap().as_str();
-                tool_name = Some(name.to_string());
-                let arg_string = caps.get(2).map(|m| m.as_str()).unwrap_or("");
-                params = self.parse_inline_args(name, arg_string);
-                in_tool_block = true;
+                let (name, server) = self.tool_name(&caps);
+                let arg_string = caps.name("args").map(|m| m.as_str()).unwrap_or("");
+                // An MCP tool named like a built-in takes key: value args
+                let builtin = if server.is_none() { name.as_str() } else { "" };
+                params = self.parse_inline_args(builtin, arg_string);
+                tool_name = Some(name);
+                mcp_server = server;
                 tool_style = Some(ToolStyle::Inline);
-                raw_lines.push(line.clone());
+                raw_lines.push(line.to_string());
                 continue;
             }
 
-            if in_tool_block {
-                match tool_style {
-                    Some(ToolStyle::Box) => {
-                        // Check for parameter line
-                        if let Some(caps) = PARAM_LINE_PATTERN.captures(trimmed) {
-                            let key = caps.get(1).unwrap().as_str();
-                            let value_raw = caps.get(2).unwrap().as_str();
-
-                            // Try to parse JSON value, fallback to string
-                            let value =
-                                if let Ok(v) = serde_json::from_str::<serde_json::Value>(value_raw)
-                                {
-                                    v
-                                } else {
-                                    // Remove surrounding quotes if present
-                                    let cleaned =
-                                        if value_raw.starts_with('"') && value_raw.ends_with('"') {
-                                            &value_raw[1..value_raw.len() - 1]
-                                        } else {
-                                            value_raw
-                                        };
-                                    serde_json::Value::String(cleaned.to_string())
-                                };
-
-                            params.insert(key.to_string(), value);
-                            raw_lines.push(line.clone());
-                            continue;
-                        }
+            match tool_style {
+                Some(ToolStyle::Box) => {
+                    // Check for parameter line
+                    if let Some(caps) = PARAM_LINE_PATTERN.captures(trimmed) {
+                        let key = caps.get(1).unwrap().as_str();
+                        let value_raw = caps.get(2).unwrap().as_str();
+
+                        // Try to parse JSON value, fallback to string
+                        let value =
+                            if let Ok(v) = serde_json::from_str::<serde_json::Value>(value_raw) {
+                                v
+                            } else {
+                                // Remove surrounding quotes if present
+                                let cleaned =
+                                    if value_raw.starts_with('"') && value_raw.ends_with('"') {
+                                        &value_raw[1..value_raw.len() - 1]
+                                    } else {
+                                        value_raw
+                                    };
+                                serde_json::Value::String(cleaned.to_string())
+                            };
+
+                        params.insert(key.to_string(), value);
+                        raw_lines.push(line.to_string());
+                        end_line = i + 1;
+                        continue;
+                    }
 
-                        // Check for output content (lines starting with │ but not key: value)
-                        if trimmed.starts_with('│') {
-                            let content = trimmed[3..].trim(); // Skip "│ "
-                            if !content.is_empty() && !PARAM_LINE_PATTERN.is_match(trimmed) {
-                                output_lines.push(content.to_string());
-                                raw_lines.push(line.clone());
-                            }
-                            continue;
+                    // Check for output content (lines starting with │ but not key: value)
+                    if let Some(rest) = trimmed.strip_prefix('│') {
+                        let content = rest.trim(); // Skip "│ "
+                        if !content.is_empty() && !PARAM_LINE_PATTERN.is_match(trimmed) {
+                            output_lines.push(content.to_string());
+                            raw_lines.push(line.to_string());
                         }
+                        end_line = i + 1;
+                        continue;
+                    }
 
-                        // End of tool block
-                        if !trimmed.is_empty() && !trimmed.starts_with('│') {
-                            break;
-                        }
+                    // End of tool block
+                    if !trimmed.is_empty() {
+                        break;
                     }
-                    Some(ToolStyle::Inline) => {
-                        // Check for inline output line
-                        if let Some(caps) = INLINE_OUTPUT_LINE_PATTERN.captures(trimmed) {
-                            let content = caps.get(1).map(|m| m.as_str().trim()).unwrap_or("");
-                            if !content.is_empty() {
-                                output_lines.push(content.to_string());
-                            }
-                            raw_lines.push(line.clone());
-                            continue;
+                }
+                Some(ToolStyle::Inline) => {
+                    // Check for inline output line
+                    if let Some(caps) = INLINE_OUTPUT_LINE_PATTERN.captures(trimmed) {
+                        let content = caps.get(1).map(|m| m.as_str().trim()).unwrap_or("");
+                        if !content.is_empty() {
+                            output_lines.push(content.to_string());
                         }
+                        raw_lines.push(line.to_string());
+                        end_line = i + 1;
+                        continue;
+                    }
 
-                        // Continuation lines (indented), keep as plain text output
-                        if line.starts_with("  ")
-                            && !trimmed.starts_with('⏺')
-                            && !trimmed.starts_with('❯')
-                            && !trimmed.starts_with('>')
-                        {
-                            output_lines.push(trimmed.to_string());
-                            raw_lines.push(line.clone());
-                            continue;
-                        }
+                    // Continuation lines (indented), keep as plain text output
+                    if line.starts_with("  ")
+                        && !trimmed.starts_with('⏺')
+                        && !trimmed.starts_with('❯')
+                        && !trimmed.starts_with('>')
+                    {
+                        output_lines.push(trimmed.to_string());
+                        raw_lines.push(line.to_string());
+                        end_line = i + 1;
+                        continue;
+                    }
 
-                        // End of tool block
-                        if !trimmed.is_empty() && !INLINE_OUTPUT_LINE_PATTERN.is_match(trimmed) {
-                            break;
-                        }
+                    // End of tool block
+                    if !trimmed.is_empty() {
+                        break;
                     }
-                    None => {}
                 }
+                None => break,
             }
         }
 
@@ -334,9 +489,20 @@ impl ToolOutputParser for ClaudeCodeToolOutputParser {
             ToolStatus::Running
         };
 
+        let is_mcp = mcp_server.is_some();
+        let summary = output_lines
+            .first()
+            .and_then(|line| self.parse_summary(line));
+        let diff = summary
+            .as_ref()
+            .and_then(|summary| self.parse_diff(summary, &output_lines[1..]));
         let data = ClaudeCodeToolOutput {
             tool_name: tool_name.clone(),
+            mcp_server,
+            is_mcp,
             params,
+            summary,
+            diff,
             output: if output_lines.is_empty() {
                 None
             } else {
@@ -347,7 +513,8 @@ impl ToolOutputParser for ClaudeCodeToolOutputParser {
         };
 
         let raw = raw_lines.join("\n");
-        let confidence = if self.is_known_tool(&tool_name) {
+        // MCP headers are as distinctive as built-in tool names
+        let confidence = if is_mcp || self.is_known_tool(&tool_name) {
             0.95
         } else {
             0.8
@@ -358,15 +525,41 @@ impl ToolOutputParser for ClaudeCodeToolOutputParser {
             raw,
             data,
             confidence,
+            start_line: start,
+            end_line,
         })
     }
 }
 
+impl ToolOutputParser for ClaudeCodeToolOutputParser {
+    fn meta(&self) -> &ParserMeta {
+        &self.meta
+    }
+
+    fn can_parse(&self, context: &ParserContext) -> bool {
+        // Check if any line matches tool header pattern
+        context
+            .trimmed_lines()
+            .any(|trimmed| is_header(trimmed) || INLINE_OUTPUT_LINE_PATTERN.is_match(trimmed))
+    }
+
+    fn parse(&self, context: &ParserContext) -> Option<ToolOutputResult> {
+        let lines = context.lines();
+        let start = lines.iter().position(|line| is_header(line.trim()))?;
+        self.parse_block(lines, start)
+    }
+}
+
+/// Whether a trimmed line is a tool header
+fn is_header(trimmed: &str) -> bool {
+    TOOL_HEADER_BOX_PATTERN.is_match(trimmed) || TOOL_HEADER_INLINE_PATTERN.is_match(trimmed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn make_context(lines: &[&str]) -> ParserContext {
+    fn make_context(lines: &[&str]) -> ParserContext<'static> {
         ParserContext::new(lines.iter().map(|s| s.to_string()).collect())
     }
 
@@ -577,6 +770,199 @@ mod tests {
         assert!(!parser.is_known_tool("CustomTool"));
     }
 
+    #[test]
+    fn test_parse_all_blocks() {
+        let parser = ClaudeCodeToolOutputParser::new();
+
+        let context = make_context(&[
+            "     fn main() {}",
+            "  ⎿  Read 12 lines",
+            "⏺ Read(src/lib.rs)",
+            "  ⎿  Read 120 lines",
+            "",
+            "⏺ Bash(cargo test)",
+            "  ⎿  running 3 tests",
+            "     test result: ok",
+            "⏺ Edit",
+            "  │ file_path: \"/src/main.rs\"",
+            "",
+            "⏺ The tests pass now.",
+            "  ⎿  not tool output",
+            "",
+            "❯ ",
+        ]);
+        let results = parser.parse_all(&context);
+        let names: Vec<&str> = results.iter().map(|r| r.data.tool_name.as_str()).collect();
+        assert_eq!(names, ["Read", "Bash", "Edit"]);
+
+        // Output above the first header belongs to a block that scrolled off
+        assert_eq!((results[0].start_line, results[0].end_line), (2, 4));
+        assert_eq!(results[0].data.output.as_deref(), Some("Read 120 lines"));
+
+        assert_eq!((results[1].start_line, results[1].end_line), (5, 8));
+        assert_eq!(
+            results[1].data.output.as_deref(),
+            Some("running 3 tests\ntest result: ok")
+        );
+
+        // Assistant text ends the block
+        assert_eq!((results[2].start_line, results[2].end_line), (8, 10));
+        assert!(results[2].data.output.is_none());
+
+        // `parse` returns the first block without later headers overwriting it
+        let first = parser.parse(&context).unwrap();
+        assert_eq!(first.data.tool_name, "Read");
+        assert_eq!(first.data.params.get("args"), Some(&"src/lib.rs".into()));
+    }
+
+    #[test]
+    fn test_parse_mcp_headers() {
+        let parser = ClaudeCodeToolOutputParser::new();
+
+        let context = make_context(&[
+            "⏺ xjp-mcp - xjp_secret_get (MCP)(key: \"x\")",
+            "  ⎿  ok",
+            "⏺ mcp__github__create_issue(title: \"Bug\", repo: \"a/b\")",
+            "⏺ mcp__xjp-mcp__xjp_secret_list (completed in 0.2s)",
+        ]);
+        let results = parser.parse_all(&context);
+        assert_eq!(results.len(), 3);
+
+        let display = &results[0];
+        assert_eq!(display.data.tool_name, "xjp_secret_get");
+        assert_eq!(display.data.mcp_server.as_deref(), Some("xjp-mcp"));
+        assert!(display.data.is_mcp);
+        assert_eq!(display.data.params.get("key"), Some(&"x".into()));
+        assert_eq!(display.confidence, 0.95);
+
+        let prefixed = &results[1];
+        assert_eq!(prefixed.data.tool_name, "create_issue");
+        assert_eq!(prefixed.data.mcp_server.as_deref(), Some("github"));
+        assert_eq!(prefixed.data.params.get("repo"), Some(&"a/b".into()));
+        assert_eq!(prefixed.confidence, 0.95);
+
+        let boxed = &results[2];
+        assert_eq!(boxed.data.tool_name, "xjp_secret_list");
+        assert_eq!(boxed.data.mcp_server.as_deref(), Some("xjp-mcp"));
+        assert_eq!(boxed.data.status, ToolStatus::Completed);
+
+        // Built-in tools are not MCP; a dash without "(MCP)" is not a header
+        let bash = parser.parse(&make_context(&["⏺ Bash(ls)"])).unwrap();
+        assert!(!bash.data.is_mcp);
+        assert!(parser
+            .parse(&make_context(&["⏺ Done - all tests pass"]))
+            .is_none());
+    }
+
+    #[test]
+    fn test_parse_result_summaries() {
+        let parser = ClaudeCodeToolOutputParser::new();
+
+        let context = make_context(&[
+            "⏺ Read(src/lib.rs)",
+            "  ⎿  Read 120 lines (ctrl+r to expand)",
+            "⏺ Write(src/x.rs)",
+            "  ⎿  Wrote 40 lines to src/x.rs",
+            "⏺ Update(src/x.rs)",
+            "  ⎿  Updated src/x.rs with 3 additions and 1 removal",
+            "⏺ Search(pattern: \"TODO\")",
+            "  ⎿  Found 1 file",
+            "⏺ Bash(cargo test)",
+            "  ⎿  Error: Exit code 101",
+            "     error[E0425]: cannot find value `x`",
+            "⏺ Bash(ls)",
+            "  ⎿  Cargo.toml",
+        ]);
+        let summaries: Vec<Option<ToolResultSummary>> = parser
+            .parse_all(&context)
+            .into_iter()
+            .map(|r| r.data.summary)
+            .collect();
+        assert_eq!(
+            summaries,
+            [
+                Some(ToolResultSummary::Read { lines: 120 }),
+                Some(ToolResultSummary::Wrote {
+                    lines: 40,
+                    path: "src/x.rs".into(),
+                }),
+                Some(ToolResultSummary::Updated {
+                    path: "src/x.rs".into(),
+                    additions: 3,
+                    removals: 1,
+                }),
+                Some(ToolResultSummary::Found {
+                    count: 1,
+                    unit: "files".into(),
+                }),
+                Some(ToolResultSummary::Error {
+                    exit_code: Some(101),
+                    message: "Exit code 101".into(),
+                }),
+                None,
+            ]
+        );
+        assert!(!summaries[4].as_ref().unwrap().is_success());
+
+        assert_eq!(
+            parser.parse_summary("Updated a.rs with 2 removals"),
+            Some(ToolResultSummary::Updated {
+                path: "a.rs".into(),
+                additions: 0,
+                removals: 2,
+            })
+        );
+        assert_eq!(
+            parser.parse_summary("Error: File does not exist."),
+            Some(ToolResultSummary::Error {
+                exit_code: None,
+                message: "File does not exist.".into(),
+            })
+        );
+        assert_eq!(parser.parse_summary("Updated the docs"), None);
+    }
+
+    #[test]
+    fn test_parse_edit_diff() {
+        let parser = ClaudeCodeToolOutputParser::new();
+
+        let context = make_context(&[
+            "⏺ Update(src/main.rs)",
+            "  ⎿  Updated src/main.rs with 1 addition and 1 removal",
+            "       10   fn main() {",
+            "       11 -     println!(\"hi\");",
+            "       11 +     println!(\"hello\");",
+            "       12   }",
+            "⏺ Write(notes.txt)",
+            "  ⎿  Wrote 12 lines to notes.txt",
+            "        1 one",
+            "        2 two",
+            "     … +10 lines (ctrl+r to expand)",
+        ]);
+        let results = parser.parse_all(&context);
+
+        let edit = results[0].data.diff.as_ref().unwrap();
+        assert_eq!(edit.path, "src/main.rs");
+        assert_eq!(edit.hunks.len(), 1);
+        assert_eq!((edit.hunks[0].old_start, edit.hunks[0].new_start), (10, 10));
+        let patch = edit.to_unified().unwrap();
+        assert!(patch.contains("@@ -10,3 +10,3 @@\n fn main() {\n-    println!(\"hi\");\n"));
+
+        let write = results[1].data.diff.as_ref().unwrap();
+        assert!(write.created);
+        assert!(write.truncated);
+        assert_eq!(write.to_unified(), None);
+
+        // Fewer changed lines than the summary counted
+        let context = make_context(&[
+            "⏺ Update(a.rs)",
+            "  ⎿  Updated a.rs with 2 additions",
+            "        3 + x",
+        ]);
+        let diff = parser.parse(&context).unwrap().data.diff.unwrap();
+        assert!(diff.truncated);
+    }
+
     #[test]
     fn test_output_type() {
         let parser = ClaudeCodeToolOutputParser::new();
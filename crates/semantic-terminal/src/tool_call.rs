@@ -0,0 +1,404 @@
+//! Typed tool call parameters
+//!
+//! [`ClaudeCodeToolOutput::params`](super::ClaudeCodeToolOutput) holds
+//! whatever the header showed: `key: value` lines of a box, `key: value`
+//! inline arguments, or a bare inline argument (`Read(src/lib.rs)`, stored as
+//! `args`). [`ToolCall`] decodes those into typed parameters for each tool
+//! in [`KNOWN_TOOLS`](super::KNOWN_TOOLS), including the names the transcript
+//! shows some of them under (`Update` for Edit, `Fetch` for WebFetch).
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::tool::displayed_tool;
+
+/// A tool call with typed parameters
+///
+/// Fields not shown on screen are `None` (inline headers show only the main
+/// argument). Calls missing their main argument, MCP tools and tools not in
+/// `KNOWN_TOOLS` decode as `Unknown`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "tool")]
+pub enum ToolCall {
+    Bash {
+        command: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        /// Timeout in milliseconds
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        run_in_background: Option<bool>,
+    },
+    Read {
+        file_path: String,
+        /// First line to read (1-based)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        offset: Option<u64>,
+        /// Number of lines to read
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        limit: Option<u64>,
+    },
+    Edit {
+        file_path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        old_string: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        new_string: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        replace_all: Option<bool>,
+    },
+    Write {
+        file_path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content: Option<String>,
+    },
+    Glob {
+        pattern: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+    },
+    Grep {
+        pattern: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        glob: Option<String>,
+    },
+    /// Grep or Glob as displayed by newer Claude Code versions
+    Search {
+        pattern: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+    },
+    WebFetch {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prompt: Option<String>,
+    },
+    WebSearch {
+        query: String,
+    },
+    /// Sub-agent task
+    Task {
+        description: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prompt: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        subagent_type: Option<String>,
+    },
+    #[serde(rename = "LSP")]
+    Lsp {
+        /// e.g. `goToDefinition`, `findReferences`
+        operation: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file_path: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        line: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        character: Option<u64>,
+    },
+    NotebookEdit {
+        notebook_path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cell_id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        new_source: Option<String>,
+        /// `replace`, `insert` or `delete`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        edit_mode: Option<String>,
+    },
+    TodoRead,
+    TodoWrite {
+        /// Todo items as shown (`content`, `status`, ...)
+        #[serde(default)]
+        todos: Vec<Value>,
+    },
+    /// MCP tool, unknown tool, or a call missing its main argument
+    Unknown {
+        name: String,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        params: HashMap<String, Value>,
+    },
+}
+
+/// Parameter lookup with the bare inline argument standing in for the main one
+struct Params<'a> {
+    params: &'a HashMap<String, Value>,
+    main: &'static str,
+}
+
+impl Params<'_> {
+    fn value(&self, key: &str) -> Option<&Value> {
+        match self.params.get(key) {
+            None if key == self.main => self.params.get("args"),
+            value => value,
+        }
+    }
+
+    /// String value; numbers and booleans are shown as text
+    fn string(&self, key: &str) -> Option<String> {
+        match self.value(key)? {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+
+    fn number(&self, key: &str) -> Option<u64> {
+        match self.value(key)? {
+            Value::Number(n) => n.as_u64(),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn flag(&self, key: &str) -> Option<bool> {
+        match self.value(key)? {
+            Value::Bool(b) => Some(*b),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl ToolCall {
+    /// Decode a built-in tool call from its name and displayed parameters
+    pub fn decode(tool_name: &str, params: &HashMap<String, Value>) -> Self {
+        Self::decode_known(tool_name, params).unwrap_or_else(|| ToolCall::Unknown {
+            name: tool_name.to_string(),
+            params: params.clone(),
+        })
+    }
+
+    fn decode_known(tool_name: &str, params: &HashMap<String, Value>) -> Option<Self> {
+        // Transcript names like `Update` stand for a built-in tool
+        let tool_name = displayed_tool(tool_name);
+        let main = match tool_name {
+            "Bash" => "command",
+            "Read" | "Edit" | "Write" => "file_path",
+            "Glob" | "Grep" | "Search" => "pattern",
+            "WebFetch" => "url",
+            "WebSearch" => "query",
+            "Task" => "description",
+            "LSP" => "operation",
+            "NotebookEdit" => "notebook_path",
+            "TodoRead" | "TodoWrite" => "todos",
+            _ => return None,
+        };
+        let p = Params { params, main };
+
+        let call = match tool_name {
+            "Bash" => ToolCall::Bash {
+                command: p.string("command")?,
+                description: p.string("description"),
+                timeout: p.number("timeout"),
+                run_in_background: p.flag("run_in_background"),
+            },
+            "Read" => ToolCall::Read {
+                file_path: p.string("file_path")?,
+                offset: p.number("offset"),
+                limit: p.number("limit"),
+            },
+            "Edit" => ToolCall::Edit {
+                file_path: p.string("file_path")?,
+                old_string: p.string("old_string"),
+                new_string: p.string("new_string"),
+                replace_all: p.flag("replace_all"),
+            },
+            "Write" => ToolCall::Write {
+                file_path: p.string("file_path")?,
+                content: p.string("content"),
+            },
+            "Glob" => ToolCall::Glob {
+                pattern: p.string("pattern")?,
+                path: p.string("path"),
+            },
+            "Grep" => ToolCall::Grep {
+                pattern: p.string("pattern")?,
+                path: p.string("path"),
+                glob: p.string("glob"),
+            },
+            "Search" => ToolCall::Search {
+                pattern: p.string("pattern")?,
+                path: p.string("path"),
+            },
+            "WebFetch" => ToolCall::WebFetch {
+                url: p.string("url")?,
+                prompt: p.string("prompt"),
+            },
+            "WebSearch" => ToolCall::WebSearch {
+                query: p.string("query")?,
+            },
+            "Task" => ToolCall::Task {
+                description: p.string("description")?,
+                prompt: p.string("prompt"),
+                subagent_type: p.string("subagent_type"),
+            },
+            "LSP" => ToolCall::Lsp {
+                operation: p.string("operation")?,
+                file_path: p.string("file_path"),
+                line: p.number("line"),
+                character: p.number("character"),
+            },
+            "NotebookEdit" => ToolCall::NotebookEdit {
+                notebook_path: p.string("notebook_path")?,
+                cell_id: p.string("cell_id"),
+                new_source: p.string("new_source"),
+                edit_mode: p.string("edit_mode"),
+            },
+            "TodoRead" => ToolCall::TodoRead,
+            _ => ToolCall::TodoWrite {
+                todos: match p.value("todos") {
+                    Some(Value::Array(todos)) => todos.clone(),
+                    _ => Vec::new(),
+                },
+            },
+        };
+        Some(call)
+    }
+
+    /// Tool name as shown in the header
+    pub fn name(&self) -> &str {
+        match self {
+            ToolCall::Bash { .. } => "Bash",
+            ToolCall::Read { .. } => "Read",
+            ToolCall::Edit { .. } => "Edit",
+            ToolCall::Write { .. } => "Write",
+            ToolCall::Glob { .. } => "Glob",
+            ToolCall::Grep { .. } => "Grep",
+            ToolCall::Search { .. } => "Search",
+            ToolCall::WebFetch { .. } => "WebFetch",
+            ToolCall::WebSearch { .. } => "WebSearch",
+            ToolCall::Task { .. } => "Task",
+            ToolCall::Lsp { .. } => "LSP",
+            ToolCall::NotebookEdit { .. } => "NotebookEdit",
+            ToolCall::TodoRead => "TodoRead",
+            ToolCall::TodoWrite { .. } => "TodoWrite",
+            ToolCall::Unknown { name, .. } => name,
+        }
+    }
+
+    /// File the call reads or changes, if any
+    pub fn file_path(&self) -> Option<&str> {
+        match self {
+            ToolCall::Read { file_path, .. }
+            | ToolCall::Edit { file_path, .. }
+            | ToolCall::Write { file_path, .. } => Some(file_path),
+            ToolCall::NotebookEdit { notebook_path, .. } => Some(notebook_path),
+            ToolCall::Lsp { file_path, .. } => file_path.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClaudeCodeToolOutputParser, ParserContext, ToolOutputParser};
+
+    fn call(lines: &[&str]) -> ToolCall {
+        let context = ParserContext::new(lines.iter().map(|s| s.to_string()).collect());
+        ClaudeCodeToolOutputParser::new()
+            .parse(&context)
+            .unwrap()
+            .data
+            .call()
+    }
+
+    #[test]
+    fn test_decode_box_and_inline_forms() {
+        let boxed = call(&[
+            "⏺ Bash",
+            "  │ command: \"cargo test\"",
+            "  │ description: \"Run tests\"",
+            "  │ timeout: 120000",
+            "  │ run_in_background: false",
+        ]);
+        assert_eq!(
+            boxed,
+            ToolCall::Bash {
+                command: "cargo test".into(),
+                description: Some("Run tests".into()),
+                timeout: Some(120000),
+                run_in_background: Some(false),
+            }
+        );
+
+        let inline = call(&["⏺ Bash(cargo test)"]);
+        assert!(matches!(inline, ToolCall::Bash { ref command, .. } if command == "cargo test"));
+
+        // Bare inline argument is the main parameter
+        assert_eq!(
+            call(&["⏺ Read(src/lib.rs)"]),
+            ToolCall::Read {
+                file_path: "src/lib.rs".into(),
+                offset: None,
+                limit: None,
+            }
+        );
+        let read = call(&["⏺ Read", "  │ file_path: \"/a.rs\"", "  │ limit: \"100\""]);
+        assert_eq!(read.file_path(), Some("/a.rs"));
+        assert!(matches!(
+            read,
+            ToolCall::Read {
+                limit: Some(100),
+                ..
+            }
+        ));
+
+        let grep = call(&["⏺ Grep(pattern: \"fn main\", path: \"src\", glob: \"*.rs\")"]);
+        assert_eq!(
+            grep,
+            ToolCall::Grep {
+                pattern: "fn main".into(),
+                path: Some("src".into()),
+                glob: Some("*.rs".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_unknown() {
+        // Missing main argument
+        assert!(matches!(call(&["⏺ Edit"]), ToolCall::Unknown { ref name, .. } if name == "Edit"));
+        assert!(matches!(
+            call(&["⏺ Deploy(target: \"prod\")"]),
+            ToolCall::Unknown { ref params, .. } if params.contains_key("target")
+        ));
+        // MCP tools never decode as built-ins
+        let mcp = call(&["⏺ mcp__shell__Bash(command: \"ls\")"]);
+        assert_eq!(mcp.name(), "Bash");
+        assert!(matches!(mcp, ToolCall::Unknown { .. }));
+
+        let json = serde_json::to_value(call(&["⏺ WebSearch(query: \"rust\")"])).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"tool": "WebSearch", "query": "rust"})
+        );
+    }
+
+    #[test]
+    fn test_decode_transcript_forms() {
+        // A bare URL is the argument, not a `https: //docs.rs` pair
+        let fetch = ToolCall::WebFetch {
+            url: "https://docs.rs".into(),
+            prompt: None,
+        };
+        assert_eq!(call(&["⏺ WebFetch(https://docs.rs)"]), fetch);
+        assert_eq!(call(&["⏺ Fetch(https://docs.rs)"]), fetch);
+
+        // Edits are shown as "Update"
+        let edit = call(&["⏺ Update(src/x.rs)", "  ⎿  Updated src/x.rs with 1 addition"]);
+        assert_eq!(edit.file_path(), Some("src/x.rs"));
+        assert!(matches!(edit, ToolCall::Edit { .. }));
+
+        let json = serde_json::to_value(call(&["⏺ WebSearch(query: \"rust\")"])).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"tool": "WebSearch", "query": "rust"})
+        );
+    }
+}
@@ -0,0 +1,418 @@
+//! Closed-loop confirmation handling
+//!
+//! A [`ConfirmTransaction`] remembers the dialog a response was sent to and
+//! checks later frames for what happened: the dialog closed with the tool
+//! running, closed with a rejection, stayed open, or was replaced by another
+//! dialog (keys landing where they shouldn't).
+//!
+//! A closed dialog only counts as accepted or denied once the frame shows it:
+//! a busy state or a tool block for the dialog's tool, or a rejection under
+//! that block. Until then the transaction stays open.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use super::tool::{ClaudeCodeToolOutputParser, DISPLAY_NAMES};
+use super::types::{
+    ClaudeCodeToolOutput, ConfirmAction, ConfirmInfo, ConfirmOptionKind, ConfirmResponse,
+    ParserContext, State, ToolInfo,
+};
+
+/// How long a dialog may stay open after a response before giving up
+pub const DEFAULT_CONFIRM_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Transcript lines Claude Code shows under a rejected tool call
+static DENIAL_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)No \(tell Claude what to do differently\)|User rejected|Interrupted by user")
+        .unwrap()
+});
+
+/// What happened to a dialog after a response was sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfirmOutcome {
+    /// The dialog closed and the tool call went ahead
+    Accepted,
+    /// The dialog closed and the tool call was rejected
+    Denied,
+    /// The same dialog is still on screen, or it closed with no effect seen yet
+    StillOpen,
+    /// A different dialog is on screen
+    UnexpectedDialog,
+    /// The feedback field opened: send the deny-with-feedback response again
+    /// to type the text
+    AwaitingFeedback,
+}
+
+/// Something in a frame that supports an outcome
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfirmEvidence {
+    /// What was observed: `same-dialog`, `other-dialog`, `feedback-field`,
+    /// `denial-marker`, `busy-state`, `tool-block`, `dialog-dismissed` or
+    /// `no-effect`
+    pub rule: String,
+    /// Frame lines it was seen on (empty when not tied to a line)
+    pub lines: Vec<usize>,
+    /// The line text or a short description
+    pub detail: String,
+}
+
+impl ConfirmEvidence {
+    fn new(rule: &str, lines: Vec<usize>, detail: impl Into<String>) -> Self {
+        Self {
+            rule: rule.to_string(),
+            lines,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Outcome of a transaction as seen in one frame
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmResolution {
+    pub outcome: ConfirmOutcome,
+    pub evidence: Vec<ConfirmEvidence>,
+    /// Dialog on screen (for `StillOpen` and `UnexpectedDialog`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dialog: Option<ConfirmInfo>,
+}
+
+/// A response sent to a confirmation dialog, awaiting its effect
+///
+/// Feed each later frame to [`ConfirmTransaction::observe`] (or let a
+/// [`Session`](super::Session) do it via `Session::respond`) until the
+/// outcome is no longer `StillOpen` or the transaction expires.
+#[derive(Debug, Clone)]
+pub struct ConfirmTransaction {
+    info: ConfirmInfo,
+    response: ConfirmResponse,
+    started_at: Instant,
+    timeout: Duration,
+}
+
+impl ConfirmTransaction {
+    /// Start a transaction for `response` sent to the dialog `info`
+    pub fn new(info: ConfirmInfo, response: ConfirmResponse) -> Self {
+        Self {
+            info,
+            response,
+            started_at: Instant::now(),
+            timeout: DEFAULT_CONFIRM_TIMEOUT,
+        }
+    }
+
+    /// Set when the response was sent
+    pub fn started_at(mut self, now: Instant) -> Self {
+        self.started_at = now;
+        self
+    }
+
+    /// Set how long the dialog may stay open before the transaction expires
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Dialog the response was sent to
+    pub fn info(&self) -> &ConfirmInfo {
+        &self.info
+    }
+
+    /// Response that was sent
+    pub fn response(&self) -> &ConfirmResponse {
+        &self.response
+    }
+
+    /// Whether the timeout has passed at `now`
+    pub fn is_expired(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.started_at) >= self.timeout
+    }
+
+    /// Whether the response was meant to reject the tool call
+    pub fn intends_denial(&self) -> bool {
+        let is_deny = |kind: ConfirmOptionKind| {
+            matches!(
+                kind,
+                ConfirmOptionKind::Deny | ConfirmOptionKind::DenyWithFeedback
+            )
+        };
+        match self.response.action {
+            ConfirmAction::Deny | ConfirmAction::DenyWithFeedback => true,
+            ConfirmAction::Choose => self.response.kind.is_some_and(is_deny),
+            ConfirmAction::Select => self
+                .response
+                .option
+                .and_then(|n| self.info.option_index(n))
+                .and_then(|i| self.info.options.as_ref()?.get(i))
+                .is_some_and(|o| is_deny(o.kind)),
+            ConfirmAction::Confirm | ConfirmAction::Input => false,
+        }
+    }
+
+    /// Whether `dialog` is the feedback field this response's first step
+    /// opened, waiting for the text
+    fn awaits_feedback(&self, dialog: &ConfirmInfo) -> bool {
+        self.response.action == ConfirmAction::DenyWithFeedback
+            && !self.info.feedback_entry
+            && dialog.feedback_entry
+    }
+
+    /// Judge a frame: the dialog detected in it (if any) and the state
+    pub fn observe(
+        &self,
+        frame: &ParserContext,
+        confirm: Option<&ConfirmInfo>,
+        state: Option<State>,
+    ) -> ConfirmResolution {
+        if let Some(dialog) = confirm {
+            let (outcome, rule) = if self.awaits_feedback(dialog) {
+                (ConfirmOutcome::AwaitingFeedback, "feedback-field")
+            } else if self.info.is_same_dialog(dialog) {
+                (ConfirmOutcome::StillOpen, "same-dialog")
+            } else {
+                (ConfirmOutcome::UnexpectedDialog, "other-dialog")
+            };
+            let detail = match &dialog.tool {
+                Some(tool) => format!("{}: {}", tool.name, dialog.prompt),
+                None => dialog.prompt.clone(),
+            };
+            return ConfirmResolution {
+                outcome,
+                evidence: vec![ConfirmEvidence::new(rule, Vec::new(), detail)],
+                dialog: Some(dialog.clone()),
+            };
+        }
+
+        // Only a block of the dialog's own tool, and only the latest one
+        let block = ClaudeCodeToolOutputParser::new()
+            .parse_all(frame)
+            .pop()
+            .filter(|b| self.info.tool.as_ref().is_some_and(|t| is_call_of(&b.data, t)));
+        let lines = frame.lines();
+        let mut evidence = vec![ConfirmEvidence::new(
+            "dialog-dismissed",
+            Vec::new(),
+            if self.intends_denial() {
+                "dialog closed after a deny response"
+            } else {
+                "dialog closed after an allow response"
+            },
+        )];
+
+        // A rejection shows under the tool call
+        if let Some(block) = &block {
+            let denial = lines
+                .iter()
+                .enumerate()
+                .skip(block.start_line + 1)
+                .find(|(_, l)| DENIAL_PATTERN.is_match(l));
+            if let Some((i, line)) = denial {
+                evidence.push(ConfirmEvidence::new("denial-marker", vec![i], line.trim()));
+                return ConfirmResolution {
+                    outcome: ConfirmOutcome::Denied,
+                    evidence,
+                    dialog: None,
+                };
+            }
+        }
+
+        let busy = matches!(state, Some(State::Thinking | State::ToolRunning));
+        if let Some(state) = state.filter(|_| busy) {
+            evidence.push(ConfirmEvidence::new(
+                "busy-state",
+                Vec::new(),
+                state.to_string(),
+            ));
+        }
+        if let Some(block) = &block {
+            let header = lines.get(block.start_line).unwrap_or_default().trim();
+            evidence.push(ConfirmEvidence::new(
+                "tool-block",
+                vec![block.start_line],
+                header,
+            ));
+        }
+        if busy || block.is_some() {
+            return ConfirmResolution {
+                outcome: ConfirmOutcome::Accepted,
+                evidence,
+                dialog: None,
+            };
+        }
+
+        // Closed, but nothing on screen says what happened yet
+        evidence.push(ConfirmEvidence::new(
+            "no-effect",
+            Vec::new(),
+            "no busy state, rejection or tool block for the dialog's tool",
+        ));
+        ConfirmResolution {
+            outcome: ConfirmOutcome::StillOpen,
+            evidence,
+            dialog: None,
+        }
+    }
+}
+
+/// Whether a transcript tool block is a call of the dialog's tool
+fn is_call_of(block: &ClaudeCodeToolOutput, tool: &ToolInfo) -> bool {
+    let name_matches = block.tool_name == tool.name
+        || DISPLAY_NAMES
+            .iter()
+            .any(|&(shown, name)| tool.name == name && block.tool_name == shown);
+    name_matches && block.mcp_server == tool.mcp_server
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClaudeCodeConfirmParser, ConfirmParser};
+
+    fn make_context(lines: &[&str]) -> ParserContext<'static> {
+        ParserContext::new(lines.iter().map(|s| s.to_string()).collect())
+    }
+
+    fn dialog(tool: &str) -> ConfirmInfo {
+        let context = make_context(&[
+            &format!("xjp-mcp - {}(key: \"test\")", tool),
+            "❯ 1. Yes",
+            "  2. No, and tell Claude what to do differently (esc)",
+        ]);
+        ClaudeCodeConfirmParser::new()
+            .detect_confirm(&context)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_accepted_and_denied() {
+        let info = dialog("xjp_secret_get");
+        let frame = make_context(&[
+            "⏺ xjp-mcp - xjp_secret_get (MCP)(key: \"test\")",
+            "  ⎿  Running…",
+        ]);
+
+        let allow = ConfirmTransaction::new(info.clone(), ConfirmResponse::confirm());
+        let resolution = allow.observe(&frame, None, Some(State::ToolRunning));
+        assert_eq!(resolution.outcome, ConfirmOutcome::Accepted);
+        let rules: Vec<&str> = resolution
+            .evidence
+            .iter()
+            .map(|e| e.rule.as_str())
+            .collect();
+        assert_eq!(rules, ["dialog-dismissed", "busy-state", "tool-block"]);
+
+        // Keys meant to allow, but Claude Code shows a rejection
+        let frame = make_context(&[
+            "⏺ xjp-mcp - xjp_secret_get (MCP)(key: \"test\")",
+            "  ⎿  No (tell Claude what to do differently)",
+        ]);
+        let resolution = allow.observe(&frame, None, Some(State::Idle));
+        assert_eq!(resolution.outcome, ConfirmOutcome::Denied);
+        assert_eq!(resolution.evidence[1].lines, [1]);
+
+        let deny = ConfirmTransaction::new(info, ConfirmResponse::choose(ConfirmOptionKind::Deny));
+        assert!(deny.intends_denial());
+        let resolution = deny.observe(&frame, None, Some(State::Idle));
+        assert_eq!(resolution.outcome, ConfirmOutcome::Denied);
+    }
+
+    #[test]
+    fn test_outcome_needs_evidence() {
+        let info = dialog("xjp_secret_get");
+        let allow = ConfirmTransaction::new(info.clone(), ConfirmResponse::confirm());
+        let deny = ConfirmTransaction::new(info, ConfirmResponse::deny());
+
+        // Dialog gone, but neither intent is confirmed by the frame
+        for tx in [&allow, &deny] {
+            let resolution = tx.observe(&make_context(&["❯ "]), None, Some(State::Idle));
+            assert_eq!(resolution.outcome, ConfirmOutcome::StillOpen);
+            assert!(resolution.dialog.is_none());
+        }
+
+        // The latest block is another tool's: neither its block nor the
+        // rejection under it count
+        let frame = make_context(&[
+            "⏺ xjp-mcp - xjp_secret_get (MCP)(key: \"old\")",
+            "  ⎿  done",
+            "⏺ Bash(ls)",
+            "  ⎿  User rejected",
+        ]);
+        let resolution = deny.observe(&frame, None, Some(State::Idle));
+        assert_eq!(resolution.outcome, ConfirmOutcome::StillOpen);
+        assert!(resolution.evidence.iter().all(|e| e.rule != "tool-block"));
+    }
+
+    #[test]
+    fn test_other_bash_command_is_unexpected() {
+        let bash_dialog = |command: &str| {
+            let context = make_context(&[
+                " Bash command",
+                "",
+                &format!("   {}", command),
+                "",
+                "   Run it",
+                "",
+                " Do you want to proceed?",
+                " ❯ 1. Yes",
+                "   2. No, and tell Claude what to do differently (esc)",
+            ]);
+            ClaudeCodeConfirmParser::new()
+                .detect_confirm(&context)
+                .unwrap()
+        };
+        let tx = ConfirmTransaction::new(bash_dialog("cargo test"), ConfirmResponse::confirm());
+        let next = bash_dialog("rm -rf target");
+        let resolution = tx.observe(&make_context(&[]), Some(&next), Some(State::Confirming));
+        assert_eq!(resolution.outcome, ConfirmOutcome::UnexpectedDialog);
+
+        let same = bash_dialog("cargo test");
+        let resolution = tx.observe(&make_context(&[]), Some(&same), Some(State::Confirming));
+        assert_eq!(resolution.outcome, ConfirmOutcome::StillOpen);
+    }
+
+    #[test]
+    fn test_edit_dialog_matches_update_block() {
+        let context = make_context(&[
+            "Edit file",
+            "src/x.rs",
+            "  1 - a",
+            "  1 + b",
+            "Do you want to make this edit to x.rs?",
+            "❯ 1. Yes",
+            "  2. No, and tell Claude what to do differently (esc)",
+        ]);
+        let info = ClaudeCodeConfirmParser::new()
+            .detect_confirm(&context)
+            .unwrap();
+        let tx = ConfirmTransaction::new(info, ConfirmResponse::confirm());
+        let frame = make_context(&[
+            "⏺ Update(src/x.rs)",
+            "  ⎿  Updated src/x.rs with 1 addition and 1 removal",
+        ]);
+        let resolution = tx.observe(&frame, None, Some(State::Idle));
+        assert_eq!(resolution.outcome, ConfirmOutcome::Accepted);
+        assert_eq!(resolution.evidence[1].lines, [0]);
+    }
+
+    #[test]
+    fn test_still_open_and_unexpected_dialog() {
+        let info = dialog("xjp_secret_get");
+        let tx = ConfirmTransaction::new(info.clone(), ConfirmResponse::confirm());
+        let frame = make_context(&[]);
+
+        let resolution = tx.observe(&frame, Some(&info), Some(State::Confirming));
+        assert_eq!(resolution.outcome, ConfirmOutcome::StillOpen);
+
+        let other = dialog("xjp_secret_set");
+        let resolution = tx.observe(&frame, Some(&other), Some(State::Confirming));
+        assert_eq!(resolution.outcome, ConfirmOutcome::UnexpectedDialog);
+        assert!(resolution.evidence[0].detail.starts_with("xjp_secret_set"));
+
+        let start = Instant::now();
+        let tx = tx.started_at(start).with_timeout(Duration::from_secs(1));
+        assert!(!tx.is_expired(start));
+        assert!(tx.is_expired(start + Duration::from_secs(1)));
+    }
+}
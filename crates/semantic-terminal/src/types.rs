@@ -2,11 +2,20 @@
 //!
 //! This module defines the core types used by state and confirm parsers.
 
+use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::ops::Range;
+
+use super::diff::{DiffHunk, FileDiff};
+use super::fingerprint::FingerprintResult;
+use super::risk::RiskReport;
+use super::tool_call::ToolCall;
+use super::version::ClaudeCodeVersion;
 
 /// Terminal state detected by state parsers
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum State {
     /// Starting up, may need trust confirmation
@@ -23,6 +32,65 @@ pub enum State {
     Error,
 }
 
+impl State {
+    /// All states
+    pub const ALL: [State; 6] = [
+        State::Starting,
+        State::Idle,
+        State::Thinking,
+        State::ToolRunning,
+        State::Confirming,
+        State::Error,
+    ];
+
+    /// States reachable from this state in one step
+    ///
+    /// Nothing returns to `Starting`. Startup ends in the prompt, the trust
+    /// dialog or an error, or goes straight into a turn when launched with a
+    /// prompt (`claude "prompt"`).
+    pub fn successors(self) -> &'static [State] {
+        match self {
+            State::Starting => &[
+                State::Idle,
+                State::Thinking,
+                State::ToolRunning,
+                State::Confirming,
+                State::Error,
+            ],
+            State::Idle => &[
+                State::Thinking,
+                State::ToolRunning,
+                State::Confirming,
+                State::Error,
+            ],
+            State::Thinking => &[
+                State::Idle,
+                State::ToolRunning,
+                State::Confirming,
+                State::Error,
+            ],
+            State::ToolRunning => &[
+                State::Idle,
+                State::Thinking,
+                State::Confirming,
+                State::Error,
+            ],
+            State::Confirming => &[
+                State::Idle,
+                State::Thinking,
+                State::ToolRunning,
+                State::Error,
+            ],
+            State::Error => &[State::Idle, State::Thinking],
+        }
+    }
+
+    /// Check if moving from this state to `to` is a legal transition
+    pub fn can_transition_to(self, to: State) -> bool {
+        self.successors().contains(&to)
+    }
+}
+
 impl std::fmt::Display for State {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -49,27 +117,178 @@ pub struct ParserMeta {
     pub version: String,
 }
 
+/// Lines of terminal output, owned or borrowed
+///
+/// Parsers iterate lines as `&str` regardless of where they live, so a
+/// context can borrow a caller's `&[String]` or `&[&str]` without copying.
+#[derive(Debug, Clone)]
+pub struct Lines<'a>(LineStore<'a>);
+
+#[derive(Debug, Clone)]
+enum LineStore<'a> {
+    Owned(Vec<String>),
+    Strings(&'a [String]),
+    Strs(&'a [&'a str]),
+}
+
+impl<'a> Lines<'a> {
+    /// Number of lines
+    pub fn len(&self) -> usize {
+        match &self.0 {
+            LineStore::Owned(lines) => lines.len(),
+            LineStore::Strings(lines) => lines.len(),
+            LineStore::Strs(lines) => lines.len(),
+        }
+    }
+
+    /// Whether there are no lines
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get a line by index
+    pub fn get(&self, index: usize) -> Option<&str> {
+        match &self.0 {
+            LineStore::Owned(lines) => lines.get(index).map(String::as_str),
+            LineStore::Strings(lines) => lines.get(index).map(String::as_str),
+            LineStore::Strs(lines) => lines.get(index).copied(),
+        }
+    }
+
+    /// Iterate over the lines
+    pub fn iter(&self) -> LinesIter<'_> {
+        LinesIter {
+            lines: self,
+            range: 0..self.len(),
+        }
+    }
+
+    /// Copy the lines into owned strings
+    pub fn to_vec(&self) -> Vec<String> {
+        self.iter().map(str::to_string).collect()
+    }
+
+    /// Borrow the same lines without copying
+    pub fn view(&self) -> Lines<'_> {
+        match &self.0 {
+            LineStore::Owned(lines) => Lines(LineStore::Strings(lines)),
+            LineStore::Strings(lines) => Lines(LineStore::Strings(lines)),
+            LineStore::Strs(lines) => Lines(LineStore::Strs(lines)),
+        }
+    }
+}
+
+impl From<Vec<String>> for Lines<'static> {
+    fn from(lines: Vec<String>) -> Self {
+        Lines(LineStore::Owned(lines))
+    }
+}
+
+impl<'a> From<&'a [String]> for Lines<'a> {
+    fn from(lines: &'a [String]) -> Self {
+        Lines(LineStore::Strings(lines))
+    }
+}
+
+impl<'a> From<&'a [&'a str]> for Lines<'a> {
+    fn from(lines: &'a [&'a str]) -> Self {
+        Lines(LineStore::Strs(lines))
+    }
+}
+
+impl<'a, 'b> IntoIterator for &'b Lines<'a> {
+    type Item = &'b str;
+    type IntoIter = LinesIter<'b>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over [`Lines`]
+#[derive(Debug, Clone)]
+pub struct LinesIter<'b> {
+    lines: &'b Lines<'b>,
+    range: Range<usize>,
+}
+
+impl<'b> Iterator for LinesIter<'b> {
+    type Item = &'b str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let i = self.range.next()?;
+        self.lines.get(i)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for LinesIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let i = self.range.next_back()?;
+        self.lines.get(i)
+    }
+}
+
+impl ExactSizeIterator for LinesIter<'_> {}
+
 /// Context provided to parsers
+///
+/// Lines may be owned ([`ParserContext::new`]) or borrowed
+/// ([`ParserContext::from_lines`], [`ParserContext::from_strs`]). Work that
+/// several parsers need is computed once per context and cached: the joined
+/// text, trimmed lines and the fingerprint scan (see
+/// [`FingerprintRegistry::extract_cached`](crate::FingerprintRegistry::extract_cached)).
 #[derive(Debug, Clone)]
-pub struct ParserContext {
+pub struct ParserContext<'a> {
     /// Last N lines of terminal output
-    pub last_lines: Vec<String>,
+    last_lines: Lines<'a>,
     /// Current detected state (if any)
     pub current_state: Option<State>,
     /// Full terminal content (optional, for complex parsing)
-    pub full_content: Option<String>,
+    full_content: Option<Cow<'a, str>>,
+    /// Claude Code version hint (filters version-ranged fingerprints)
+    version: Option<ClaudeCodeVersion>,
+    /// Joined lines
+    text: OnceCell<String>,
+    /// Byte range of each trimmed line
+    trimmed: OnceCell<Vec<Range<usize>>>,
+    /// Fingerprint scan, keyed by registry generation
+    fingerprints: OnceCell<(u64, FingerprintResult)>,
 }
 
-impl ParserContext {
+impl ParserContext<'static> {
     /// Create a new parser context from lines
     pub fn new(last_lines: Vec<String>) -> Self {
+        Self::with_lines(last_lines.into())
+    }
+}
+
+impl<'a> ParserContext<'a> {
+    fn with_lines(last_lines: Lines<'a>) -> Self {
         Self {
             last_lines,
             current_state: None,
             full_content: None,
+            version: None,
+            text: OnceCell::new(),
+            trimmed: OnceCell::new(),
+            fingerprints: OnceCell::new(),
         }
     }
 
+    /// Create a context borrowing lines
+    pub fn from_lines(last_lines: &'a [String]) -> Self {
+        Self::with_lines(last_lines.into())
+    }
+
+    /// Create a context borrowing string slices
+    pub fn from_strs(last_lines: &'a [&'a str]) -> Self {
+        Self::with_lines(last_lines.into())
+    }
+
     /// Create context with current state
     pub fn with_state(mut self, state: State) -> Self {
         self.current_state = Some(state);
@@ -77,14 +296,118 @@ impl ParserContext {
     }
 
     /// Create context with full content
-    pub fn with_full_content(mut self, content: String) -> Self {
-        self.full_content = Some(content);
+    pub fn with_full_content(mut self, content: impl Into<Cow<'a, str>>) -> Self {
+        self.full_content = Some(content.into());
+        self.fingerprints = OnceCell::new();
+        self
+    }
+
+    /// Create context with a Claude Code version hint
+    pub fn with_version(mut self, version: ClaudeCodeVersion) -> Self {
+        self.version = Some(version);
+        self.fingerprints = OnceCell::new();
         self
     }
 
-    /// Get joined text from last lines
-    pub fn text(&self) -> String {
-        self.last_lines.join("\n")
+    /// Borrow this context's lines and settings without copying them
+    ///
+    /// Cached results are not carried over.
+    pub fn view(&self) -> ParserContext<'_> {
+        ParserContext {
+            current_state: self.current_state,
+            full_content: self.full_content.as_deref().map(Cow::Borrowed),
+            version: self.version,
+            ..ParserContext::with_lines(self.last_lines.view())
+        }
+    }
+
+    /// Last N lines of terminal output
+    pub fn lines(&self) -> &Lines<'a> {
+        &self.last_lines
+    }
+
+    /// Last N lines of terminal output
+    ///
+    /// Same as [`lines`](Self::lines); kept for code written against the
+    /// former public `last_lines` field.
+    pub fn last_lines(&self) -> &Lines<'a> {
+        &self.last_lines
+    }
+
+    /// Full terminal content
+    pub fn full_content(&self) -> Option<&str> {
+        self.full_content.as_deref()
+    }
+
+    /// Set or clear the full terminal content
+    ///
+    /// Replaces assignments to the former public `full_content` field.
+    pub fn set_full_content(&mut self, content: Option<Cow<'a, str>>) {
+        self.full_content = content;
+        self.fingerprints = OnceCell::new();
+    }
+
+    /// Claude Code version hint
+    pub fn version(&self) -> Option<ClaudeCodeVersion> {
+        self.version
+    }
+
+    /// Get joined text from last lines (computed once)
+    pub fn text(&self) -> &str {
+        self.text.get_or_init(|| {
+            let mut text = String::new();
+            for (i, line) in self.last_lines.iter().enumerate() {
+                if i > 0 {
+                    text.push('\n');
+                }
+                text.push_str(line);
+            }
+            text
+        })
+    }
+
+    /// Line with surrounding whitespace removed
+    pub fn trimmed(&self, index: usize) -> Option<&str> {
+        let range = self.trimmed_ranges().get(index)?.clone();
+        self.last_lines.get(index).map(|line| &line[range])
+    }
+
+    /// Iterate over trimmed lines
+    pub fn trimmed_lines(&self) -> impl DoubleEndedIterator<Item = &str> + '_ {
+        self.last_lines
+            .iter()
+            .zip(self.trimmed_ranges())
+            .map(|(line, range)| &line[range.clone()])
+    }
+
+    fn trimmed_ranges(&self) -> &[Range<usize>] {
+        self.trimmed.get_or_init(|| {
+            self.last_lines
+                .iter()
+                .map(|line| {
+                    let start = line.len() - line.trim_start().len();
+                    let end = line.trim_end().len().max(start);
+                    start..end
+                })
+                .collect()
+        })
+    }
+
+    /// Cached fingerprint scan for a registry generation
+    pub(crate) fn cached_fingerprints(
+        &self,
+        generation: u64,
+        scan: impl FnOnce() -> FingerprintResult,
+    ) -> Cow<'_, FingerprintResult> {
+        let mut scan = Some(scan);
+        let (cached_generation, result) = self
+            .fingerprints
+            .get_or_init(|| (generation, (scan.take().expect("scan runs once"))()));
+        match scan {
+            // Only one registry is cached per context
+            Some(scan) if *cached_generation != generation => Cow::Owned(scan()),
+            _ => Cow::Borrowed(result),
+        }
     }
 }
 
@@ -99,6 +422,46 @@ pub struct StateMeta {
     pub confirm_type: Option<ConfirmType>,
 }
 
+/// A rule or fingerprint that fired during state detection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Evidence {
+    /// Rule or fingerprint ID
+    pub rule: String,
+    /// State this evidence supports
+    pub state: State,
+    /// Indices of every context line where the rule matched
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<usize>,
+    /// Strength of the rule (-1.0 - 1.0, negative values suppress the state)
+    pub weight: f64,
+    /// Amount this evidence added to the final confidence
+    /// (0.0 when it supports a different state)
+    #[serde(default)]
+    pub contribution: f64,
+}
+
+impl Evidence {
+    /// Create evidence for `state` found on the given lines
+    pub fn new(rule: impl Into<String>, state: State, lines: Vec<usize>, weight: f64) -> Self {
+        Self {
+            rule: rule.into(),
+            state,
+            lines,
+            weight,
+            contribution: 0.0,
+        }
+    }
+}
+
+/// Score of a candidate state
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StateScore {
+    /// Candidate state
+    pub state: State,
+    /// Score (0.0 - 1.0)
+    pub score: f64,
+}
+
 /// Result of state detection
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StateDetectionResult {
@@ -109,6 +472,15 @@ pub struct StateDetectionResult {
     /// Additional metadata
     #[serde(skip_serializing_if = "Option::is_none")]
     pub meta: Option<StateMeta>,
+    /// Rules that fired on this frame, in evaluation order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub evidence: Vec<Evidence>,
+    /// Best-scoring state that was not chosen
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runner_up: Option<StateScore>,
+    /// Claude Code version profile whose fingerprints were used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<ClaudeCodeVersion>,
 }
 
 impl StateDetectionResult {
@@ -118,9 +490,49 @@ impl StateDetectionResult {
             state,
             confidence,
             meta: None,
+            evidence: Vec::new(),
+            runner_up: None,
+            version: None,
         }
     }
 
+    /// Create a result whose confidence is derived from evidence
+    ///
+    /// Positive evidence for `state` is combined as independent signals
+    /// (`1 - Π(1 - weight)`), then negative evidence suppresses the total
+    /// (`× Π(1 + weight)`). Each item's `contribution` is the amount it moved
+    /// the running total, so contributions sum to the confidence.
+    pub fn from_evidence(state: State, mut evidence: Vec<Evidence>) -> Self {
+        let confidence = score_evidence(state, &mut evidence);
+        Self {
+            state,
+            confidence,
+            meta: None,
+            evidence,
+            runner_up: None,
+            version: None,
+        }
+    }
+
+    /// Set the runner-up state
+    pub fn with_runner_up(mut self, runner_up: StateScore) -> Self {
+        self.runner_up = Some(runner_up);
+        self
+    }
+
+    /// Set the version profile used for detection
+    pub fn with_version(mut self, version: Option<ClaudeCodeVersion>) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Evidence that adds support for the detected state
+    pub fn supporting_evidence(&self) -> impl Iterator<Item = &Evidence> {
+        self.evidence
+            .iter()
+            .filter(move |e| e.state == self.state && e.weight > 0.0)
+    }
+
     /// Add metadata to the result
     pub fn with_meta(mut self, meta: StateMeta) -> Self {
         self.meta = Some(meta);
@@ -128,6 +540,27 @@ impl StateDetectionResult {
     }
 }
 
+/// Combine evidence for `state`, filling in each item's contribution
+pub(crate) fn score_evidence(state: State, evidence: &mut [Evidence]) -> f64 {
+    let mut confidence: f64 = 0.0;
+    for item in evidence.iter_mut() {
+        item.contribution = 0.0;
+        if item.state == state && item.weight > 0.0 {
+            let next = 1.0 - (1.0 - confidence) * (1.0 - item.weight.min(1.0));
+            item.contribution = next - confidence;
+            confidence = next;
+        }
+    }
+    for item in evidence.iter_mut() {
+        if item.state == state && item.weight < 0.0 {
+            let next = confidence * (1.0 + item.weight.max(-1.0));
+            item.contribution = next - confidence;
+            confidence = next;
+        }
+    }
+    confidence
+}
+
 /// Trait for state parsers
 pub trait StateParser {
     /// Get parser metadata
@@ -159,6 +592,34 @@ pub struct ConfirmOption {
     /// Whether this is the default option
     #[serde(default)]
     pub is_default: bool,
+    /// Whether the `❯` selection marker is on this option
+    #[serde(default)]
+    pub selected: bool,
+    /// What choosing this option does
+    #[serde(default)]
+    pub kind: ConfirmOptionKind,
+    /// What an "always" option applies to, e.g. "git status commands in /repo"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+/// What choosing a confirm option does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfirmOptionKind {
+    /// Allow this one action ("Yes")
+    AllowOnce,
+    /// Allow for the rest of the session ("Yes, allow for this session")
+    AllowForSession,
+    /// Allow from now on ("Yes, and don't ask again for ... commands in ...")
+    AllowAlways,
+    /// Deny the action ("No")
+    Deny,
+    /// Deny and tell Claude what to do instead ("No, and tell Claude ...")
+    DenyWithFeedback,
+    /// Unrecognized option
+    #[default]
+    Other,
 }
 
 /// Key type for confirm options
@@ -181,7 +642,7 @@ impl std::fmt::Display for ConfirmKey {
 }
 
 /// Information about a tool being confirmed
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ToolInfo {
     /// Tool name
     pub name: String,
@@ -209,6 +670,96 @@ pub struct ConfirmInfo {
     pub tool: Option<ToolInfo>,
     /// Raw prompt text
     pub raw_prompt: String,
+    /// What confirming the tool call could do
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub risk: Option<RiskReport>,
+    /// Tool-specific details shown in the dialog
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<ConfirmDetails>,
+    /// Whether the "tell Claude what to do differently" field is open
+    #[serde(default)]
+    pub feedback_entry: bool,
+}
+
+/// Tool-specific details of a confirmation dialog
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConfirmDetails {
+    /// "Bash command" approval box
+    Bash(BashConfirm),
+    /// File edit, create or overwrite approval with its diff preview
+    File(FileConfirm),
+}
+
+/// A Bash command awaiting approval
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BashConfirm {
+    /// Full command, lines joined with `\n`
+    pub command: String,
+    /// Claude's description of the command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Command prefix offered by "don't ask again for <prefix> commands in <dir>"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_prefix: Option<String>,
+    /// Directory of that "don't ask again" option
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_directory: Option<String>,
+}
+
+/// File operation awaiting approval
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileOperation {
+    /// "Do you want to make this edit to <file>?"
+    Edit,
+    /// "Do you want to create <file>?"
+    Create,
+    /// "Do you want to overwrite <file>?"
+    Overwrite,
+}
+
+/// A file change awaiting approval
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileConfirm {
+    /// Whether the dialog edits, creates or overwrites the file
+    pub operation: FileOperation,
+    /// Path as shown in the dialog (absolute or relative to the workspace)
+    pub path: String,
+    /// Preview hunks; a new file is a single all-added hunk
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hunks: Vec<DiffHunk>,
+}
+
+impl ConfirmInfo {
+    /// Whether `other` is the same dialog (selection moves don't count)
+    ///
+    /// The tool's parameters and the dialog details (command, path, diff)
+    /// must match too: every Bash dialog asks "Do you want to proceed?".
+    pub fn is_same_dialog(&self, other: &ConfirmInfo) -> bool {
+        self.confirm_type == other.confirm_type
+            && self.prompt == other.prompt
+            && self.tool == other.tool
+            && self.details == other.details
+    }
+
+    /// Position (0-based) of the option with the selection marker
+    pub fn selected_index(&self) -> Option<usize> {
+        self.options.as_ref()?.iter().position(|o| o.selected)
+    }
+
+    /// Position (0-based) of the first option of a kind
+    pub fn kind_index(&self, kind: ConfirmOptionKind) -> Option<usize> {
+        self.options.as_ref()?.iter().position(|o| o.kind == kind)
+    }
+
+    /// Position (0-based) of the option with a numeric key
+    pub fn option_index(&self, number: u32) -> Option<usize> {
+        self.options
+            .as_ref()?
+            .iter()
+            .position(|o| matches!(o.key, ConfirmKey::Number(n) if n == number))
+    }
 }
 
 /// Action to take in response to confirmation
@@ -223,6 +774,10 @@ pub enum ConfirmAction {
     Select,
     /// Input custom text
     Input,
+    /// Choose the option of a kind
+    Choose,
+    /// Deny and tell Claude what to do instead
+    DenyWithFeedback,
 }
 
 /// Response to a confirmation dialog
@@ -233,9 +788,12 @@ pub struct ConfirmResponse {
     /// Option number (for Select action)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub option: Option<u32>,
-    /// Custom value (for Input action)
+    /// Custom value (for Input and DenyWithFeedback actions)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub value: Option<String>,
+    /// Option kind (for Choose action)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ConfirmOptionKind>,
 }
 
 impl ConfirmResponse {
@@ -245,6 +803,7 @@ impl ConfirmResponse {
             action: ConfirmAction::Confirm,
             option: None,
             value: None,
+            kind: None,
         }
     }
 
@@ -254,6 +813,7 @@ impl ConfirmResponse {
             action: ConfirmAction::Deny,
             option: None,
             value: None,
+            kind: None,
         }
     }
 
@@ -263,6 +823,7 @@ impl ConfirmResponse {
             action: ConfirmAction::Select,
             option: Some(option),
             value: None,
+            kind: None,
         }
     }
 
@@ -272,10 +833,76 @@ impl ConfirmResponse {
             action: ConfirmAction::Input,
             option: None,
             value: Some(value.into()),
+            kind: None,
+        }
+    }
+
+    /// Create a response denying with feedback text for Claude
+    ///
+    /// On the dialog this picks the "No, and tell Claude what to do
+    /// differently" option, which opens a text field. Send the same response
+    /// again once the field is open (`ConfirmInfo::feedback_entry`) to type
+    /// and submit the text.
+    pub fn deny_with_feedback(text: impl Into<String>) -> Self {
+        Self {
+            action: ConfirmAction::DenyWithFeedback,
+            option: None,
+            value: Some(text.into()),
+            kind: None,
+        }
+    }
+
+    /// Create a response choosing the option of a kind
+    pub fn choose(kind: ConfirmOptionKind) -> Self {
+        Self {
+            action: ConfirmAction::Choose,
+            option: None,
+            value: None,
+            kind: Some(kind),
+        }
+    }
+}
+
+/// Why a confirm response could not be formatted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfirmResponseError {
+    /// No option carries the selection marker, so arrow moves would be a guess
+    UnknownSelection,
+    /// The requested option is not in the dialog
+    UnknownOption(u32),
+    /// Two options share a number, so the dialog was misread
+    DuplicateOption(u32),
+    /// The dialog has no option of the requested kind
+    NoOptionOfKind(ConfirmOptionKind),
+    /// The action needs an option number or value that was not given
+    MissingArgument(ConfirmAction),
+    /// No confirmation dialog is visible
+    NoDialog,
+}
+
+impl std::fmt::Display for ConfirmResponseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfirmResponseError::UnknownSelection => {
+                write!(f, "cannot determine the selected confirm option")
+            }
+            ConfirmResponseError::UnknownOption(n) => write!(f, "no confirm option {}", n),
+            ConfirmResponseError::DuplicateOption(n) => {
+                write!(f, "confirm option {} appears more than once", n)
+            }
+            ConfirmResponseError::NoOptionOfKind(kind) => {
+                write!(f, "no confirm option of kind {:?}", kind)
+            }
+            ConfirmResponseError::MissingArgument(action) => {
+                write!(f, "missing argument for {:?} response", action)
+            }
+            ConfirmResponseError::NoDialog => write!(f, "no confirmation dialog is visible"),
         }
     }
 }
 
+impl std::error::Error for ConfirmResponseError {}
+
 /// Trait for confirm parsers
 pub trait ConfirmParser {
     /// Get parser metadata
@@ -285,7 +912,11 @@ pub trait ConfirmParser {
     fn detect_confirm(&self, context: &ParserContext) -> Option<ConfirmInfo>;
 
     /// Format a response for the terminal
-    fn format_response(&self, info: &ConfirmInfo, response: &ConfirmResponse) -> String;
+    fn format_response(
+        &self,
+        info: &ConfirmInfo,
+        response: &ConfirmResponse,
+    ) -> Result<String, ConfirmResponseError>;
 }
 
 // ============ Title Types ============
@@ -367,14 +998,26 @@ impl std::fmt::Display for ToolStatus {
 /// Parsed tool output from Claude Code
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClaudeCodeToolOutput {
-    /// Tool name (e.g., "Bash", "Read", "Edit")
+    /// Tool name (e.g., "Bash", "Read", "Edit"; bare name for MCP tools)
     pub tool_name: String,
+    /// MCP server name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mcp_server: Option<String>,
+    /// Whether the tool comes from an MCP server
+    #[serde(default)]
+    pub is_mcp: bool,
     /// Tool parameters
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub params: HashMap<String, serde_json::Value>,
     /// Tool output content
     #[serde(skip_serializing_if = "Option::is_none")]
     pub output: Option<String>,
+    /// Result summary from the first output line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<ToolResultSummary>,
+    /// Change shown under an Edit or Write result
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff: Option<FileDiff>,
     /// Duration in milliseconds (if completed)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub duration_ms: Option<f64>,
@@ -382,6 +1025,51 @@ pub struct ClaudeCodeToolOutput {
     pub status: ToolStatus,
 }
 
+/// Summary Claude Code shows under a finished tool call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ToolResultSummary {
+    /// "Read 120 lines"
+    Read { lines: u32 },
+    /// "Wrote 40 lines to src/x.rs"
+    Wrote { lines: u32, path: String },
+    /// "Updated src/x.rs with 3 additions and 1 removal"
+    Updated {
+        path: String,
+        additions: u32,
+        removals: u32,
+    },
+    /// "Found 12 files" (Glob, Grep); `unit` is `files`, `lines` or `matches`
+    Found { count: u32, unit: String },
+    /// "Error: Exit code 1" or another error message
+    Error {
+        /// Exit code of a failed command
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i32>,
+        message: String,
+    },
+}
+
+impl ToolResultSummary {
+    /// Whether the tool call succeeded
+    pub fn is_success(&self) -> bool {
+        !matches!(self, ToolResultSummary::Error { .. })
+    }
+}
+
+impl ClaudeCodeToolOutput {
+    /// Parameters decoded for the tool (`Unknown` for MCP and unknown tools)
+    pub fn call(&self) -> ToolCall {
+        if self.is_mcp {
+            return ToolCall::Unknown {
+                name: self.tool_name.clone(),
+                params: self.params.clone(),
+            };
+        }
+        ToolCall::decode(&self.tool_name, &self.params)
+    }
+}
+
 /// Result of tool output parsing
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolOutputResult {
@@ -394,6 +1082,12 @@ pub struct ToolOutputResult {
     pub data: ClaudeCodeToolOutput,
     /// Parser confidence (0.0 - 1.0)
     pub confidence: f64,
+    /// Index of the block's header line in the context lines
+    #[serde(default)]
+    pub start_line: usize,
+    /// Index one past the block's last line
+    #[serde(default)]
+    pub end_line: usize,
 }
 
 /// Trait for tool output parsers
@@ -456,3 +1150,44 @@ pub trait StatusParser {
     /// Parse status from context
     fn parse(&self, context: &ParserContext) -> Option<ClaudeCodeStatus>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_borrowed_context() {
+        let strs = ["  ⏺ Bash(ls)  ", "❯ "];
+        let context = ParserContext::from_strs(&strs);
+        assert_eq!(context.lines().len(), 2);
+        assert_eq!(context.lines().get(1), Some("❯ "));
+        assert_eq!(context.text(), "  ⏺ Bash(ls)  \n❯ ");
+
+        let owned: Vec<String> = strs.iter().map(|s| s.to_string()).collect();
+        let borrowed = ParserContext::from_lines(&owned).with_state(State::Idle);
+        let view = borrowed.view();
+        assert_eq!(view.current_state, Some(State::Idle));
+        assert_eq!(view.lines().to_vec(), owned);
+    }
+
+    #[test]
+    fn test_text_and_trimmed_lines_are_cached() {
+        let context = ParserContext::new(vec!["  a  ".into(), "".into(), "\tb".into()]);
+        assert!(std::ptr::eq(context.text(), context.text()));
+        assert_eq!(context.trimmed(0), Some("a"));
+        assert_eq!(context.trimmed(3), None);
+        let trimmed: Vec<&str> = context.trimmed_lines().collect();
+        assert_eq!(trimmed, vec!["a", "", "b"]);
+        assert_eq!(context.trimmed_lines().next_back(), Some("b"));
+    }
+
+    #[test]
+    fn test_field_compat_accessors() {
+        let mut context = ParserContext::new(vec!["❯ ".into()]);
+        assert_eq!(context.last_lines().to_vec(), vec!["❯ ".to_string()]);
+        context.set_full_content(Some("full".into()));
+        assert_eq!(context.full_content(), Some("full"));
+        context.set_full_content(None);
+        assert_eq!(context.full_content(), None);
+    }
+}
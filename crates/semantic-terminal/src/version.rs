@@ -0,0 +1,266 @@
+//! Claude Code version detection
+//!
+//! Parses the CLI version from the startup banner (`Claude Code v2.0.14`) or
+//! `claude --version` output (`1.0.35 (Claude Code)`), and describes the
+//! version ranges fingerprints apply to.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Startup banner: "Claude Code v2.0.14" / "Claude Code 1.0.35"
+static BANNER_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"Claude Code\s+v?(\d+\.\d+(?:\.\d+)?)").unwrap());
+
+/// `claude --version` output: "1.0.35 (Claude Code)"
+static VERSION_OUTPUT_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(\d+\.\d+(?:\.\d+)?)\s+\(Claude Code\)").unwrap());
+
+/// A Claude Code CLI version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ClaudeCodeVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl ClaudeCodeVersion {
+    /// Create a version
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Detect the version from a startup banner or `--version` output
+    pub fn detect<S: AsRef<str>>(lines: impl IntoIterator<Item = S>) -> Option<Self> {
+        lines.into_iter().find_map(|line| {
+            let line = line.as_ref();
+            let caps = BANNER_PATTERN
+                .captures(line)
+                .or_else(|| VERSION_OUTPUT_PATTERN.captures(line))?;
+            caps[1].parse().ok()
+        })
+    }
+}
+
+impl fmt::Display for ClaudeCodeVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for ClaudeCodeVersion {
+    type Err = String;
+
+    /// Parse `major.minor[.patch]`, with an optional leading `v`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let digits = s.strip_prefix('v').unwrap_or(s);
+        let parts: Vec<&str> = digits.split('.').collect();
+        if !(2..=3).contains(&parts.len()) {
+            return Err(format!("invalid version: {}", s));
+        }
+        let num = |p: &str| {
+            p.parse::<u32>()
+                .map_err(|_| format!("invalid version: {}", s))
+        };
+        Ok(Self {
+            major: num(parts[0])?,
+            minor: num(parts[1])?,
+            patch: parts.get(2).map(|p| num(p)).transpose()?.unwrap_or(0),
+        })
+    }
+}
+
+impl TryFrom<String> for ClaudeCodeVersion {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<ClaudeCodeVersion> for String {
+    fn from(v: ClaudeCodeVersion) -> Self {
+        v.to_string()
+    }
+}
+
+/// Range of versions a fingerprint applies to
+///
+/// `min` is inclusive and `max` is exclusive; written as `">=1.0.0, <2.0.0"`,
+/// `">=2.0"` or `"*"` for all versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct VersionRange {
+    /// Lowest matching version (inclusive)
+    pub min: Option<ClaudeCodeVersion>,
+    /// First version past the range (exclusive)
+    pub max: Option<ClaudeCodeVersion>,
+}
+
+impl VersionRange {
+    /// Range matching every version
+    pub const fn any() -> Self {
+        Self {
+            min: None,
+            max: None,
+        }
+    }
+
+    /// Range matching `min` and later
+    pub const fn at_least(min: ClaudeCodeVersion) -> Self {
+        Self {
+            min: Some(min),
+            max: None,
+        }
+    }
+
+    /// Range matching versions before `max`
+    pub const fn before(max: ClaudeCodeVersion) -> Self {
+        Self {
+            min: None,
+            max: Some(max),
+        }
+    }
+
+    /// Range matching `min` up to (not including) `max`
+    pub const fn between(min: ClaudeCodeVersion, max: ClaudeCodeVersion) -> Self {
+        Self {
+            min: Some(min),
+            max: Some(max),
+        }
+    }
+
+    /// Whether the range places no restriction
+    pub fn is_any(&self) -> bool {
+        self.min.is_none() && self.max.is_none()
+    }
+
+    /// Check if a version is in the range
+    pub fn contains(&self, version: ClaudeCodeVersion) -> bool {
+        self.min.is_none_or(|min| version >= min) && self.max.is_none_or(|max| version < max)
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.min, self.max) {
+            (None, None) => write!(f, "*"),
+            (Some(min), None) => write!(f, ">={}", min),
+            (None, Some(max)) => write!(f, "<{}", max),
+            (Some(min), Some(max)) => write!(f, ">={}, <{}", min, max),
+        }
+    }
+}
+
+impl FromStr for VersionRange {
+    type Err = String;
+
+    /// Parse comma-separated `>=` / `<` bounds, or `*`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut range = Self::any();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            if part == "*" {
+                continue;
+            }
+            if let Some(v) = part.strip_prefix(">=") {
+                range.min = Some(v.parse()?);
+            } else if let Some(v) = part.strip_prefix('<') {
+                range.max = Some(v.parse()?);
+            } else {
+                return Err(format!("invalid version bound: {}", part));
+            }
+        }
+        Ok(range)
+    }
+}
+
+impl TryFrom<String> for VersionRange {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<VersionRange> for String {
+    fn from(r: VersionRange) -> Self {
+        r.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_detect_from_banner() {
+        let banner = lines(&[
+            "╭───────────────────────────╮",
+            " ▐▛███▜▌   Claude Code v2.0.14",
+            "▝▜█████▛▘  Sonnet 4.5 · Claude Max",
+        ]);
+        assert_eq!(
+            ClaudeCodeVersion::detect(&banner),
+            Some(ClaudeCodeVersion::new(2, 0, 14))
+        );
+    }
+
+    #[test]
+    fn test_detect_from_version_output() {
+        let output = lines(&["1.0.35 (Claude Code)"]);
+        assert_eq!(
+            ClaudeCodeVersion::detect(&output),
+            Some(ClaudeCodeVersion::new(1, 0, 35))
+        );
+        assert_eq!(ClaudeCodeVersion::detect(lines(&["❯ "])), None);
+    }
+
+    #[test]
+    fn test_parse_and_order() {
+        let v: ClaudeCodeVersion = "v1.2".parse().unwrap();
+        assert_eq!(v, ClaudeCodeVersion::new(1, 2, 0));
+        assert!(ClaudeCodeVersion::new(1, 10, 0) > ClaudeCodeVersion::new(1, 9, 9));
+        assert!("1".parse::<ClaudeCodeVersion>().is_err());
+        assert!("1.x.0".parse::<ClaudeCodeVersion>().is_err());
+    }
+
+    #[test]
+    fn test_range_contains() {
+        let range: VersionRange = ">=1.0.0, <2.0.0".parse().unwrap();
+        assert!(range.contains(ClaudeCodeVersion::new(1, 0, 0)));
+        assert!(range.contains(ClaudeCodeVersion::new(1, 99, 0)));
+        assert!(!range.contains(ClaudeCodeVersion::new(2, 0, 0)));
+        assert!(!range.contains(ClaudeCodeVersion::new(0, 9, 0)));
+
+        let any: VersionRange = "*".parse().unwrap();
+        assert!(any.is_any());
+        assert!(any.contains(ClaudeCodeVersion::new(0, 0, 1)));
+        assert!("~1.0".parse::<VersionRange>().is_err());
+    }
+
+    #[test]
+    fn test_serde_as_strings() {
+        let range = VersionRange::between(
+            ClaudeCodeVersion::new(1, 0, 0),
+            ClaudeCodeVersion::new(2, 0, 0),
+        );
+        let json = serde_json::to_string(&range).unwrap();
+        assert_eq!(json, r#"">=1.0.0, <2.0.0""#);
+        assert_eq!(serde_json::from_str::<VersionRange>(&json).unwrap(), range);
+
+        let json = serde_json::to_string(&ClaudeCodeVersion::new(2, 0, 14)).unwrap();
+        assert_eq!(json, r#""2.0.14""#);
+    }
+}
@@ -12,11 +12,64 @@ export const enum State {
   Confirming = 'Confirming',
   Error = 'Error'
 }
+/** Input action for Claude Code */
+export const enum InputAction {
+  Submit = 'Submit',
+  Interrupt = 'Interrupt',
+  Exit = 'Exit',
+  CycleMode = 'CycleMode',
+  SlashCommand = 'SlashCommand',
+  Rewind = 'Rewind'
+}
 /** Type of confirmation dialog */
 export const enum ConfirmType {
   Options = 'Options',
   YesNo = 'YesNo'
 }
+/** Action to take in response to confirmation */
+export const enum ConfirmAction {
+  Confirm = 'Confirm',
+  Deny = 'Deny',
+  Select = 'Select',
+  Input = 'Input',
+  Choose = 'Choose',
+  DenyWithFeedback = 'DenyWithFeedback'
+}
+/** What choosing a confirm option does */
+export const enum ConfirmOptionKind {
+  AllowOnce = 'AllowOnce',
+  AllowForSession = 'AllowForSession',
+  AllowAlways = 'AllowAlways',
+  Deny = 'Deny',
+  DenyWithFeedback = 'DenyWithFeedback',
+  Other = 'Other'
+}
+/** File operation of an edit/write dialog */
+export const enum FileOperation {
+  Edit = 'Edit',
+  Create = 'Create',
+  Overwrite = 'Overwrite'
+}
+/** Kind of a diff line */
+export const enum DiffLineKind {
+  Context = 'Context',
+  Added = 'Added',
+  Removed = 'Removed'
+}
+/** How risky a flagged action is */
+export const enum RiskLevel {
+  None = 'None',
+  Low = 'Low',
+  Medium = 'Medium',
+  High = 'High'
+}
+/** What kind of risk a flag describes */
+export const enum RiskCategory {
+  DestructiveCommand = 'DestructiveCommand',
+  OutsideWorkspace = 'OutsideWorkspace',
+  Secret = 'Secret',
+  Network = 'Network'
+}
 /** Status phase */
 export const enum StatusPhase {
   Thinking = 'Thinking',
@@ -39,18 +92,34 @@ export const enum FingerprintCategory {
   Error = 'Error',
   Confirm = 'Confirm'
 }
+/** Rule that fired during state detection */
+export interface StateEvidence {
+  rule: string
+  state: State
+  lines: Array<number>
+  weight: number
+  contribution: number
+}
 /** State detection result */
 export interface StateResult {
   state: State
   confidence: number
   needsTrustConfirm?: boolean
   confirmType?: ConfirmType
+  evidence: Array<StateEvidence>
+  /** Claude Code version profile used for detection */
+  version?: string
 }
 /** Confirm option */
 export interface ConfirmOption {
   key: string
   label: string
   isDefault: boolean
+  /** Whether the ❯ selection marker is on this option */
+  selected: boolean
+  kind: ConfirmOptionKind
+  /** What an "always" option applies to */
+  scope?: string
 }
 /** Tool info in confirmation */
 export interface ToolInfo {
@@ -65,6 +134,63 @@ export interface ConfirmInfo {
   options?: Array<ConfirmOption>
   tool?: ToolInfo
   rawPrompt: string
+  risk?: RiskReport
+  /** Details of a Bash command dialog */
+  bash?: BashConfirm
+  /** Details of a file edit/create/overwrite dialog */
+  file?: FileConfirm
+  /** Whether the "tell Claude what to do differently" field is open */
+  feedbackEntry: boolean
+}
+/** Bash command dialog details */
+export interface BashConfirm {
+  command: string
+  description?: string
+  /** Command prefix the "don't ask again" option allows */
+  allowPrefix?: string
+  /** Directory the "don't ask again" option applies to */
+  allowDirectory?: string
+}
+/** File dialog details */
+export interface FileConfirm {
+  operation: FileOperation
+  path: string
+  hunks: Array<DiffHunk>
+}
+/** A change to one file shown under an Edit or Write result */
+export interface FileDiff {
+  path: string
+  /** Whether the file is new */
+  created: boolean
+  hunks: Array<DiffHunk>
+  /** Whether the display left lines out */
+  truncated: boolean
+  /** Unified diff for `git apply` (unset when truncated) */
+  unified?: string
+}
+/** A contiguous block of changed lines */
+export interface DiffHunk {
+  oldStart: number
+  newStart: number
+  lines: Array<DiffLine>
+}
+/** A line of a diff hunk */
+export interface DiffLine {
+  kind: DiffLineKind
+  oldLine?: number
+  newLine?: number
+  text: string
+}
+/** A single flagged risk */
+export interface RiskFlag {
+  category: RiskCategory
+  level: RiskLevel
+  reason: string
+}
+/** Risk assessment of a confirmation dialog */
+export interface RiskReport {
+  level: RiskLevel
+  flags: Array<RiskFlag>
 }
 /** Status bar info */
 export interface StatusInfo {
@@ -82,10 +208,35 @@ export interface TitleInfo {
 /** Tool output result */
 export interface ToolOutput {
   toolName: string
+  mcpServer?: string
+  isMcp: boolean
   params: Record<string, any>
+  /** Typed parameters: `{ tool: 'Read', file_path, offset, limit }` etc. */
+  call: any
   output?: string
+  /** Result summary: `{ kind: 'read', lines }`, `{ kind: 'error', exit_code, message }` etc. */
+  summary?: any
+  /** Change shown under an Edit or Write result */
+  diff?: FileDiff
   durationMs?: number
   status: ToolStatus
+  /** Index of the block's header line */
+  startLine: number
+  /** Index one past the block's last line */
+  endLine: number
+}
+/** Policy decision for a confirmation dialog */
+export interface PolicyVerdict {
+  /** `allow`, `deny`, `ask`, `option` or `choose` */
+  decision: string
+  /** Option number (for `option`) */
+  option?: number
+  /** Option kind (for `choose`) */
+  kind?: ConfirmOptionKind
+  /** Index of the matching rule (unset when the default applied) */
+  ruleIndex?: number
+  /** Label of the matching rule */
+  rule?: string
 }
 /** Fingerprint match result */
 export interface FingerprintMatch {
@@ -93,6 +244,8 @@ export interface FingerprintMatch {
   matched: boolean
   captures?: Array<string>
   lineIndex?: number
+  /** Every line index where the pattern matched */
+  lineIndices: Array<number>
 }
 /** Fingerprint hints */
 export interface FingerprintHints {
@@ -130,14 +283,52 @@ export declare class StateParser {
 /** Claude Code confirmation parser */
 export declare class ConfirmParser {
   constructor()
+  /**
+   * Encode responses for the terminal's input modes (default: bracketed
+   * paste on, normal cursor keys)
+   */
+  setInputModes(applicationCursor: boolean, bracketedPaste: boolean): void
+  /** Flag file writes outside `root` in risk reports */
+  setWorkspace(root: string): void
   /** Detect and parse confirmation dialog */
   detect(lines: Array<string>): ConfirmInfo | null
-  /** Format a response for the terminal */
-  formatConfirm(): string
-  /** Format a deny response for the terminal */
-  formatDeny(confirmType: ConfirmType): string
-  /** Format a select response for the terminal */
-  formatSelect(option: number): string
+  /**
+   * Format a response to the dialog on screen, moving from the ❯ selection
+   *
+   * Fails when no dialog is detected or the selection cannot be determined.
+   */
+  formatResponse(lines: Array<string>, action: ConfirmAction, option?: number | undefined | null, value?: string | undefined | null, kind?: ConfirmOptionKind | undefined | null): string
+  /** Format a confirm response, picking the allow-once option by kind */
+  formatConfirm(lines: Array<string>): string
+  /** Format a deny response, picking the deny option by kind */
+  formatDeny(lines: Array<string>): string
+  /** Format a select response, moving from the ❯ selection to `option` */
+  formatSelect(lines: Array<string>, option: number): string
+}
+/** Claude Code input actions */
+export declare class Input {
+  constructor()
+  /**
+   * Encode for the terminal's input modes (default: bracketed paste on,
+   * normal cursor keys)
+   */
+  setInputModes(applicationCursor: boolean, bracketedPaste: boolean): void
+  /** States an action is meant to be sent in */
+  expectedStates(action: InputAction): Array<State>
+  /** Encode an action, failing when it is not meant for `state` */
+  encode(action: InputAction, state: State, text?: string | undefined | null): string
+  /** Type a prompt and press Enter (expects Idle) */
+  submit(prompt: string): string
+  /** Esc to stop the running request (expects Thinking or ToolRunning) */
+  interrupt(): string
+  /** Ctrl+C twice to quit (expects Idle with an empty prompt) */
+  exit(): string
+  /** Shift+Tab to cycle permission modes (expects Idle) */
+  cycleMode(): string
+  /** Run a slash command, leading `/` optional (expects Idle) */
+  slashCommand(command: string): string
+  /** Esc twice to open the rewind list (expects Idle with an empty prompt) */
+  rewind(): string
 }
 /** Claude Code status parser */
 export declare class StatusParser {
@@ -160,13 +351,24 @@ export declare class ToolOutputParser {
   constructor()
   /** Check if context can be parsed as tool output */
   canParse(lines: Array<string>): boolean
-  /** Parse tool output */
+  /** Parse tool output (the first block) */
   parse(lines: Array<string>): ToolOutput | null
+  /** Parse every tool block, top to bottom */
+  parseAll(lines: Array<string>): Array<ToolOutput>
+}
+/** Ordered permission rules deciding confirmation dialogs */
+export declare class ConfirmPolicy {
+  /** Load a policy file (`.toml` or JSON) */
+  static fromFile(path: string): ConfirmPolicy
+  /** Decide the confirmation dialog on screen, if any */
+  evaluate(lines: Array<string>): PolicyVerdict | null
 }
 /** Fingerprint registry for pattern matching */
 export declare class Registry {
   /** Create a new registry with default Claude Code fingerprints */
   constructor()
+  /** Load a fingerprint pack file (`.toml` or JSON), overriding or disabling built-ins */
+  loadPack(path: string): void
   /** Extract fingerprints from terminal lines */
   extract(lines: Array<string>): FingerprintResult
   /** Quick check for spinner */
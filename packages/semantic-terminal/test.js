@@ -35,17 +35,18 @@ console.log('Confirming:', confirmResult)
 console.log('\n=== ConfirmParser ===')
 const confirmParser = new ConfirmParser()
 
-const confirm = confirmParser.detect([
+const confirmLines = [
   'xjp-mcp - xjp_secret_get(key: "test")',
   '❯ 1. Yes, allow this action',
   '  2. Yes, allow for this session',
   '  3. No, deny this action',
   'Esc to cancel',
-])
+]
+const confirm = confirmParser.detect(confirmLines)
 console.log('Confirm info:', JSON.stringify(confirm, null, 2))
-console.log('Format confirm:', JSON.stringify(confirmParser.formatConfirm()))
-console.log('Format deny (Options):', JSON.stringify(confirmParser.formatDeny('Options')))
-console.log('Format select(2):', JSON.stringify(confirmParser.formatSelect(2)))
+console.log('Format confirm:', JSON.stringify(confirmParser.formatConfirm(confirmLines)))
+console.log('Format deny:', JSON.stringify(confirmParser.formatDeny(confirmLines)))
+console.log('Format select(2):', JSON.stringify(confirmParser.formatSelect(confirmLines, 2)))
 
 // Test StatusParser
 console.log('\n=== StatusParser ===')